@@ -0,0 +1,268 @@
+//! Lets the player pan and zoom the view of the play area, so that levels fit
+//! on small screens.
+//!
+//! - Mouse wheel zooms in and out around the cursor
+//! - Middle mouse button (held) or WASD / arrow keys pan
+//! - F zooms to fit the whole level
+
+use bevy::{
+    input::mouse::{AccumulatedMouseScroll, MouseScrollUnit},
+    prelude::*,
+    window::PrimaryWindow,
+};
+
+use crate::{
+    cursor_over_play_area, level::Level, mouse_movement_system, selected_level_bounds,
+    DrawingInput, GameState, Handles, MainCamera, MousePos, PlayAreaNode, SelectedLevel,
+    BOTTOM_BAR_HEIGHT,
+};
+
+/// The most the view can be zoomed in, as a camera scale.
+const MIN_SCALE: f32 = 0.5;
+/// The most the view can be zoomed out, as a camera scale.
+const MAX_SCALE: f32 = 4.0;
+/// How much one line of mouse wheel scrolling zooms by.
+const ZOOM_STEP: f32 = 1.1;
+/// Scrolling this many pixels on a touchpad is one line of scrolling.
+const PIXELS_PER_LINE: f32 = 100.0;
+/// How fast the keyboard pans the view, in screen pixels per second.
+const PAN_SPEED: f32 = 800.0;
+/// Space left around the level when zooming to fit, in world units.
+const FIT_MARGIN: f32 = 24.0;
+/// The camera position when the whole level fits in the window.
+pub const DEFAULT_TRANSLATION: Vec2 = Vec2::new(0.0, -10.0);
+
+pub struct CameraPlugin;
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Playing), reset_camera_system);
+        // the editor and other screens aren't zoomed or panned
+        app.add_systems(OnExit(GameState::Playing), restore_camera_system);
+        app.add_systems(
+            Update,
+            (zoom_system, pan_system, zoom_to_fit_system)
+                .before(mouse_movement_system)
+                .in_set(DrawingInput),
+        );
+    }
+}
+
+/// Returns the camera translation and scale that show all of `rect` in the
+/// part of a window of `window_size` that is above the bottom bar.
+pub fn fit_view(rect: Rect, window_size: Vec2) -> (Vec2, f32) {
+    let area = (window_size - Vec2::new(0.0, BOTTOM_BAR_HEIGHT)).max(Vec2::ONE);
+    let size = rect.size() + Vec2::splat(FIT_MARGIN * 2.0);
+    let scale = (size / area).max_element().clamp(MIN_SCALE, MAX_SCALE);
+
+    // the bottom bar covers the bottom of the window, so look a little lower
+    // to center the board in the space above it
+    let translation = rect.center() - Vec2::new(0.0, BOTTOM_BAR_HEIGHT / 2.0 * scale);
+
+    (translation, scale)
+}
+
+/// Returns the camera translation that keeps the world position under the
+/// cursor in place when zooming from `scale` to `new_scale`. `cursor` is
+/// the cursor's offset from the center of the window, in screen pixels.
+pub fn zoom_at(translation: Vec2, scale: f32, new_scale: f32, cursor: Vec2) -> Vec2 {
+    // screen y points down, world y points up
+    let cursor = Vec2::new(cursor.x, -cursor.y);
+
+    translation + cursor * (scale - new_scale)
+}
+
+fn set_scale(projection: &mut Projection, scale: f32) {
+    if let Projection::Orthographic(ortho) = projection {
+        ortho.scale = scale;
+    }
+}
+
+fn scale(projection: &Projection) -> f32 {
+    match projection {
+        Projection::Orthographic(ortho) => ortho.scale,
+        _ => 1.0,
+    }
+}
+
+fn reset_camera_system(
+    handles: Res<Handles>,
+    levels: Res<Assets<Level>>,
+    selected_level: Res<SelectedLevel>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    mut q_camera: Query<(&mut Transform, &mut Projection), With<MainCamera>>,
+) {
+    let Ok(window) = q_window.single() else {
+        return;
+    };
+
+    let bounds = selected_level_bounds(&handles, &levels, &selected_level);
+    let (fit_translation, fit_scale) = fit_view(bounds.rect(), window.size());
+
+    // only zoom out if the level doesn't fit as it is
+    let (translation, scale) = if fit_scale > 1.0 {
+        (fit_translation, fit_scale)
+    } else {
+        (DEFAULT_TRANSLATION, 1.0)
+    };
+
+    for (mut transform, mut projection) in &mut q_camera {
+        transform.translation.x = translation.x;
+        transform.translation.y = translation.y;
+        set_scale(&mut projection, scale);
+    }
+}
+
+fn restore_camera_system(mut q_camera: Query<(&mut Transform, &mut Projection), With<MainCamera>>) {
+    for (mut transform, mut projection) in &mut q_camera {
+        transform.translation.x = DEFAULT_TRANSLATION.x;
+        transform.translation.y = DEFAULT_TRANSLATION.y;
+        set_scale(&mut projection, 1.0);
+    }
+}
+
+fn zoom_system(
+    scroll: Res<AccumulatedMouseScroll>,
+    mouse: Res<MousePos>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    q_play_area: Query<&Interaction, With<PlayAreaNode>>,
+    mut q_camera: Query<(&mut Transform, &mut Projection), With<MainCamera>>,
+) {
+    if scroll.delta.y == 0.0 {
+        return;
+    }
+
+    let Ok(window) = q_window.single() else {
+        return;
+    };
+
+    // leave scrolling over the UI alone
+    if !cursor_over_play_area(&q_play_area) {
+        return;
+    }
+
+    let lines = match scroll.unit {
+        MouseScrollUnit::Line => scroll.delta.y,
+        MouseScrollUnit::Pixel => scroll.delta.y / PIXELS_PER_LINE,
+    };
+
+    let cursor = mouse.window - window.size() / 2.0;
+
+    for (mut transform, mut projection) in &mut q_camera {
+        let old_scale = scale(&projection);
+        let new_scale = (old_scale * ZOOM_STEP.powf(-lines)).clamp(MIN_SCALE, MAX_SCALE);
+
+        let translation = zoom_at(
+            transform.translation.truncate(),
+            old_scale,
+            new_scale,
+            cursor,
+        );
+        transform.translation.x = translation.x;
+        transform.translation.y = translation.y;
+        set_scale(&mut projection, new_scale);
+    }
+}
+
+fn pan_system(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    mouse: Res<MousePos>,
+    time: Res<Time>,
+    mut drag_from: Local<Option<Vec2>>,
+    mut q_camera: Query<(&mut Transform, &Projection), With<MainCamera>>,
+) {
+    let mut pan = Vec2::ZERO;
+
+    // drag the board along with the cursor. raw mouse motion doesn't match
+    // how far the cursor moves on screen, so follow the cursor instead.
+    if mouse_input.pressed(MouseButton::Middle) {
+        if let Some(from) = *drag_from {
+            let delta = mouse.window - from;
+            pan += Vec2::new(-delta.x, delta.y);
+        }
+        *drag_from = Some(mouse.window);
+    } else {
+        *drag_from = None;
+    }
+
+    let mut direction = Vec2::ZERO;
+    if keyboard_input.any_pressed([KeyCode::KeyW, KeyCode::ArrowUp]) {
+        direction.y += 1.0;
+    }
+    if keyboard_input.any_pressed([KeyCode::KeyS, KeyCode::ArrowDown]) {
+        direction.y -= 1.0;
+    }
+    if keyboard_input.any_pressed([KeyCode::KeyA, KeyCode::ArrowLeft]) {
+        direction.x -= 1.0;
+    }
+    if keyboard_input.any_pressed([KeyCode::KeyD, KeyCode::ArrowRight]) {
+        direction.x += 1.0;
+    }
+    pan += direction.normalize_or_zero() * PAN_SPEED * time.delta_secs();
+
+    if pan == Vec2::ZERO {
+        return;
+    }
+
+    for (mut transform, projection) in &mut q_camera {
+        let pan = pan * scale(projection);
+        transform.translation.x += pan.x;
+        transform.translation.y += pan.y;
+    }
+}
+
+fn zoom_to_fit_system(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    handles: Res<Handles>,
+    levels: Res<Assets<Level>>,
+    selected_level: Res<SelectedLevel>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    mut q_camera: Query<(&mut Transform, &mut Projection), With<MainCamera>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::KeyF) {
+        return;
+    }
+
+    let Ok(window) = q_window.single() else {
+        return;
+    };
+
+    let bounds = selected_level_bounds(&handles, &levels, &selected_level);
+    let (translation, scale) = fit_view(bounds.rect(), window.size());
+
+    for (mut transform, mut projection) in &mut q_camera {
+        transform.translation.x = translation.x;
+        transform.translation.y = translation.y;
+        set_scale(&mut projection, scale);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zoom_keeps_point_under_cursor() {
+        let translation = Vec2::new(100.0, -50.0);
+        let cursor = Vec2::new(200.0, -120.0);
+        let world =
+            |translation: Vec2, scale: f32| translation + Vec2::new(cursor.x, -cursor.y) * scale;
+
+        let zoomed = zoom_at(translation, 1.0, 2.0, cursor);
+
+        assert_eq!(world(translation, 1.0), world(zoomed, 2.0));
+    }
+
+    #[test]
+    fn fit_view_shows_whole_rect() {
+        let rect = Rect::new(-1200.0, -720.0, 1200.0, 720.0);
+
+        let (translation, scale) = fit_view(rect, Vec2::new(1280.0, 720.0));
+
+        let area = Vec2::new(1280.0, 720.0 - BOTTOM_BAR_HEIGHT) * scale;
+        assert!(area.x >= rect.width() && area.y >= rect.height());
+        // centered in the space above the bottom bar
+        let visible_center = translation + Vec2::new(0.0, BOTTOM_BAR_HEIGHT / 2.0 * scale);
+        assert_eq!(visible_center, rect.center());
+    }
+}
@@ -118,6 +118,200 @@ pub fn segment_collision(a1: Vec2, a2: Vec2, b1: Vec2, b2: Vec2) -> SegmentColli
     SegmentCollision::None
 }
 
+/// How far apart two points may be while still counting as the same point
+/// when arcs are involved. Arcs rarely line up with the grid, so their points
+/// can't be compared exactly.
+pub(crate) const ARC_TOLERANCE: f32 = 0.01;
+
+/// Returns the angle swept around `center` from `a` to `b`, going the short way
+/// around. Positive angles are counter-clockwise.
+pub fn arc_sweep(a: Vec2, b: Vec2, center: Vec2) -> f32 {
+    (a - center).angle_to(b - center)
+}
+
+/// Like [`point_segment_collision`], but `points` may be the ends of an arc
+/// around `arc`.
+pub fn point_road_collision(p: Vec2, points: (Vec2, Vec2), arc: Option<Vec2>) -> PointCollision {
+    match arc {
+        Some(_) => near_road(p, points, arc),
+        None => point_segment_collision(p, points.0, points.1),
+    }
+}
+
+/// Like [`segment_collision`], but either segment may be an arc around the
+/// given center.
+///
+/// Arcs can't be extended, so collisions involving an arc are never
+/// [`SegmentCollision::ConnectingParallel`]. Where an arc crosses the other
+/// segment in more than one place, the most severe collision is returned.
+pub fn road_collision(
+    a: (Vec2, Vec2),
+    a_arc: Option<Vec2>,
+    b: (Vec2, Vec2),
+    b_arc: Option<Vec2>,
+) -> SegmentCollision {
+    let crossings = match (a_arc, b_arc) {
+        (None, None) => return segment_collision(a.0, a.1, b.0, b.1),
+        (Some(center), None) => line_circle_intersections(b, center, center.distance(a.0)),
+        (None, Some(center)) => line_circle_intersections(a, center, center.distance(b.0)),
+        (Some(a_center), Some(b_center)) => {
+            let a_radius = a_center.distance(a.0);
+            let b_radius = b_center.distance(b.0);
+
+            if a_center.distance(b_center) <= ARC_TOLERANCE
+                && (a_radius - b_radius).abs() <= ARC_TOLERANCE
+            {
+                return same_circle_collision(a, b, a_center);
+            }
+
+            circle_intersections(a_center, a_radius, b_center, b_radius)
+        }
+    };
+
+    let mut collision = SegmentCollision::None;
+
+    // the end of one segment may rest against the other without crossing it,
+    // which the intersections above can miss.
+    for point in crossings.into_iter().chain([a.0, a.1, b.0, b.1]) {
+        let found = match (near_road(point, a, a_arc), near_road(point, b, b_arc)) {
+            (PointCollision::None, _) | (_, PointCollision::None) => continue,
+            (PointCollision::End, PointCollision::End) => {
+                SegmentCollision::Connecting(nearest_end(point, b))
+            }
+            (PointCollision::End, PointCollision::Middle) => {
+                SegmentCollision::Touching(nearest_end(point, a))
+            }
+            (PointCollision::Middle, PointCollision::End) => {
+                SegmentCollision::Touching(nearest_end(point, b))
+            }
+            (PointCollision::Middle, PointCollision::Middle) => {
+                return SegmentCollision::Intersecting;
+            }
+        };
+
+        if matches!(collision, SegmentCollision::None)
+            || matches!(found, SegmentCollision::Touching(_))
+        {
+            collision = found;
+        }
+    }
+
+    collision
+}
+
+/// Like [`point_road_collision`], but points within [`ARC_TOLERANCE`] of the
+/// segment or its ends count as being on them.
+fn near_road(p: Vec2, points: (Vec2, Vec2), arc: Option<Vec2>) -> PointCollision {
+    if p.distance(points.0) <= ARC_TOLERANCE || p.distance(points.1) <= ARC_TOLERANCE {
+        return PointCollision::End;
+    }
+
+    let on_road = match arc {
+        Some(center) => on_arc(p, points, center),
+        None => {
+            let diff = points.1 - points.0;
+            let t = ((p - points.0).dot(diff) / diff.length_squared()).clamp(0.0, 1.0);
+
+            p.distance(points.0 + t * diff) <= ARC_TOLERANCE
+        }
+    };
+
+    if on_road {
+        PointCollision::Middle
+    } else {
+        PointCollision::None
+    }
+}
+
+/// Whether `p` lies on the arc around `center` between `points`.
+fn on_arc(p: Vec2, points: (Vec2, Vec2), center: Vec2) -> bool {
+    let radius = center.distance(points.0);
+    if (p.distance(center) - radius).abs() > ARC_TOLERANCE {
+        return false;
+    }
+
+    let sweep = arc_sweep(points.0, points.1, center);
+    let angle = arc_sweep(points.0, p, center);
+    let slack = ARC_TOLERANCE / radius;
+
+    if sweep >= 0.0 {
+        (-slack..=sweep + slack).contains(&angle)
+    } else {
+        (sweep - slack..=slack).contains(&angle)
+    }
+}
+
+fn nearest_end(p: Vec2, points: (Vec2, Vec2)) -> Vec2 {
+    if p.distance(points.0) <= p.distance(points.1) {
+        points.0
+    } else {
+        points.1
+    }
+}
+
+/// Returns the points where the infinite line through `points` meets the circle.
+fn line_circle_intersections(points: (Vec2, Vec2), center: Vec2, radius: f32) -> Vec<Vec2> {
+    // working relative to the center keeps the numbers small
+    let start = points.0 - center;
+    let diff = points.1 - points.0;
+
+    let a = diff.length_squared();
+    let b = 2.0 * start.dot(diff);
+    let c = start.length_squared() - radius * radius;
+    let discriminant = b * b - 4.0 * a * c;
+
+    if a == 0.0 || discriminant < 0.0 {
+        return vec![];
+    }
+
+    let root = discriminant.sqrt();
+
+    [(-b - root) / (2.0 * a), (-b + root) / (2.0 * a)]
+        .into_iter()
+        .map(|t| points.0 + t * diff)
+        .collect()
+}
+
+/// Returns the points where two circles meet.
+fn circle_intersections(a: Vec2, a_radius: f32, b: Vec2, b_radius: f32) -> Vec<Vec2> {
+    let diff = b - a;
+    let dist = diff.length();
+
+    if dist == 0.0 || dist > a_radius + b_radius || dist < (a_radius - b_radius).abs() {
+        return vec![];
+    }
+
+    let along = (a_radius * a_radius - b_radius * b_radius + dist * dist) / (2.0 * dist);
+    let across = (a_radius * a_radius - along * along).max(0.0).sqrt();
+
+    let middle = a + diff * along / dist;
+    let offset = diff.perp() * across / dist;
+
+    vec![middle + offset, middle - offset]
+}
+
+/// Classifies the collision between two arcs of the same circle.
+fn same_circle_collision(a: (Vec2, Vec2), b: (Vec2, Vec2), center: Vec2) -> SegmentCollision {
+    let a_middle =
+        center + Vec2::from_angle(arc_sweep(a.0, a.1, center) / 2.0).rotate(a.0 - center);
+
+    let overlapping = [b.0, b.1]
+        .into_iter()
+        .any(|p| matches!(near_road(p, a, Some(center)), PointCollision::Middle))
+        || [a.0, a.1, a_middle]
+            .into_iter()
+            .any(|p| matches!(near_road(p, b, Some(center)), PointCollision::Middle));
+
+    if overlapping {
+        return SegmentCollision::Overlapping;
+    }
+
+    [b.0, b.1]
+        .into_iter()
+        .find(|p| p.distance(a.0) <= ARC_TOLERANCE || p.distance(a.1) <= ARC_TOLERANCE)
+        .map_or(SegmentCollision::None, SegmentCollision::Connecting)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -387,4 +581,176 @@ mod tests {
             SegmentCollision::None
         ));
     }
+
+    // a quarter circle from (10, 0) to (0, 10), counter-clockwise around the
+    // origin
+    const ARC: (Vec2, Vec2) = (Vec2::new(10.0, 0.0), Vec2::new(0.0, 10.0));
+
+    #[test]
+    fn point_arc() {
+        assert!(matches!(
+            point_road_collision(Vec2::new(0.0, 10.0), ARC, Some(Vec2::ZERO)),
+            PointCollision::End
+        ));
+        assert!(matches!(
+            point_road_collision(Vec2::from_angle(0.5) * 10.0, ARC, Some(Vec2::ZERO)),
+            PointCollision::Middle
+        ));
+        // on the circle, but not the arc
+        assert!(matches!(
+            point_road_collision(Vec2::new(-10.0, 0.0), ARC, Some(Vec2::ZERO)),
+            PointCollision::None
+        ));
+        // the chord between the ends isn't part of the arc
+        assert!(matches!(
+            point_road_collision(Vec2::new(5.0, 5.0), ARC, Some(Vec2::ZERO)),
+            PointCollision::None
+        ));
+    }
+
+    #[test]
+    fn arc_seg_intersecting() {
+        assert!(matches!(
+            road_collision(
+                ARC,
+                Some(Vec2::ZERO),
+                (Vec2::ZERO, Vec2::new(10.0, 10.0)),
+                None
+            ),
+            SegmentCollision::Intersecting
+        ));
+    }
+
+    #[test]
+    fn arc_seg_connecting() {
+        // carrying straight on from the end of the arc
+        if let SegmentCollision::Connecting(point) = road_collision(
+            ARC,
+            Some(Vec2::ZERO),
+            (Vec2::new(10.0, -10.0), Vec2::new(10.0, 0.0)),
+            None,
+        ) {
+            assert_eq!(point, Vec2::new(10.0, 0.0));
+        } else {
+            panic!("Expected Connecting collision");
+        }
+
+        if let SegmentCollision::Connecting(point) = road_collision(
+            (Vec2::new(0.0, 10.0), Vec2::new(10.0, 20.0)),
+            None,
+            ARC,
+            Some(Vec2::ZERO),
+        ) {
+            assert_eq!(point, Vec2::new(0.0, 10.0));
+        } else {
+            panic!("Expected Connecting collision");
+        }
+    }
+
+    #[test]
+    fn arc_seg_touching() {
+        let middle = Vec2::from_angle(std::f32::consts::FRAC_PI_4) * 10.0;
+
+        if let SegmentCollision::Touching(point) =
+            road_collision(ARC, Some(Vec2::ZERO), (Vec2::new(20.0, 20.0), middle), None)
+        {
+            assert_eq!(point, middle);
+        } else {
+            panic!("Expected Touching collision");
+        }
+
+        // the end of the arc resting on the middle of a segment
+        if let SegmentCollision::Touching(point) = road_collision(
+            (Vec2::new(10.0, -10.0), Vec2::new(10.0, 10.0)),
+            None,
+            ARC,
+            Some(Vec2::ZERO),
+        ) {
+            assert_eq!(point, Vec2::new(10.0, 0.0));
+        } else {
+            panic!("Expected Touching collision");
+        }
+    }
+
+    #[test]
+    fn arc_seg_none() {
+        assert!(matches!(
+            road_collision(
+                ARC,
+                Some(Vec2::ZERO),
+                (Vec2::new(20.0, 0.0), Vec2::new(20.0, 10.0)),
+                None
+            ),
+            SegmentCollision::None
+        ));
+        // crosses the circle, but not the arc
+        assert!(matches!(
+            road_collision(
+                ARC,
+                Some(Vec2::ZERO),
+                (Vec2::new(-20.0, -5.0), Vec2::new(20.0, -5.0)),
+                None
+            ),
+            SegmentCollision::None
+        ));
+    }
+
+    #[test]
+    fn arc_arc_same_circle() {
+        // the second arc starts halfway along the first
+        assert!(matches!(
+            road_collision(
+                ARC,
+                Some(Vec2::ZERO),
+                (
+                    Vec2::from_angle(std::f32::consts::FRAC_PI_4) * 10.0,
+                    Vec2::new(-10.0, 0.0)
+                ),
+                Some(Vec2::ZERO)
+            ),
+            SegmentCollision::Overlapping
+        ));
+
+        // the same arc, drawn the other way
+        assert!(matches!(
+            road_collision(ARC, Some(Vec2::ZERO), (ARC.1, ARC.0), Some(Vec2::ZERO)),
+            SegmentCollision::Overlapping
+        ));
+
+        if let SegmentCollision::Connecting(point) = road_collision(
+            ARC,
+            Some(Vec2::ZERO),
+            (Vec2::new(0.0, 10.0), Vec2::new(-10.0, 0.0)),
+            Some(Vec2::ZERO),
+        ) {
+            assert_eq!(point, Vec2::new(0.0, 10.0));
+        } else {
+            panic!("Expected Connecting collision");
+        }
+    }
+
+    #[test]
+    fn arc_arc_intersecting() {
+        // a quarter circle from (10, 10) to the origin around (10, 0)
+        assert!(matches!(
+            road_collision(
+                ARC,
+                Some(Vec2::ZERO),
+                (Vec2::new(10.0, 10.0), Vec2::ZERO),
+                Some(Vec2::new(10.0, 0.0))
+            ),
+            SegmentCollision::Intersecting
+        ));
+
+        // the same circle, but only the part below the first arc
+        assert!(matches!(
+            road_collision(
+                ARC,
+                Some(Vec2::ZERO),
+                (Vec2::ZERO, Vec2::new(10.0, -10.0)),
+                Some(Vec2::new(10.0, 0.0))
+            ),
+            SegmentCollision::None
+        ));
+    }
 }
@@ -0,0 +1,324 @@
+//! Tracking of the pixies delivered to each terminus, and the quotas that
+//! determine whether a run passes.
+
+use bevy::{platform::collections::HashMap, prelude::*};
+use bevy_prototype_lyon::prelude::*;
+
+use crate::{
+    layer, level::Terminus, pixie::PixieFlavor, sim::SimulationState, theme, AfterUpdate,
+    GameState, ScoreUi,
+};
+
+pub const PROGRESS_BAR_WIDTH: f32 = 40.0;
+pub const PROGRESS_BAR_HEIGHT: f32 = 6.0;
+
+pub struct DeliveriesPlugin;
+impl Plugin for DeliveriesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Deliveries>();
+        app.init_resource::<QuotasMet>();
+        app.add_systems(
+            AfterUpdate,
+            (progress_bar_system, delivery_counter_system)
+                .in_set(ScoreUi)
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+/// The pixies that arrived at each terminus during the current run.
+#[derive(Resource, Default)]
+pub struct Deliveries {
+    /// The number of pixies of each flavor that were accepted.
+    pub delivered: HashMap<Entity, HashMap<PixieFlavor, u32>>,
+    /// The number of pixies that a terminus turned away, either because it
+    /// was full or because it doesn't collect their flavor.
+    pub rejected: HashMap<Entity, u32>,
+}
+impl Deliveries {
+    pub fn get(&self, terminus: Entity, flavor: &PixieFlavor) -> u32 {
+        self.delivered
+            .get(&terminus)
+            .and_then(|flavors| flavors.get(flavor))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    pub fn total(&self, terminus: Entity) -> u32 {
+        self.delivered
+            .get(&terminus)
+            .map(|flavors| flavors.values().sum())
+            .unwrap_or(0)
+    }
+
+    /// Records a pixie arriving at a terminus, returning `false` if the
+    /// terminus was already full and rejected it.
+    pub fn deliver(&mut self, entity: Entity, terminus: &Terminus, flavor: PixieFlavor) -> bool {
+        if terminus
+            .capacity
+            .is_some_and(|capacity| self.total(entity) >= capacity)
+        {
+            self.reject(entity);
+            return false;
+        }
+
+        *self
+            .delivered
+            .entry(entity)
+            .or_default()
+            .entry(flavor)
+            .or_insert(0) += 1;
+
+        true
+    }
+
+    /// Records a pixie that a terminus turned away.
+    pub fn reject(&mut self, entity: Entity) {
+        *self.rejected.entry(entity).or_insert(0) += 1;
+    }
+
+    pub fn rejected(&self, terminus: Entity) -> u32 {
+        self.rejected.get(&terminus).copied().unwrap_or(0)
+    }
+}
+
+/// Whether every terminus received its quota of pixies during the last
+/// completed run.
+#[derive(Resource)]
+pub struct QuotasMet(pub bool);
+impl Default for QuotasMet {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+/// Returns `true` if every terminus has received at least as many pixies of
+/// each flavor as its quotas require.
+pub fn quotas_met<'a>(
+    terminuses: impl IntoIterator<Item = (Entity, &'a Terminus)>,
+    deliveries: &Deliveries,
+) -> bool {
+    terminuses.into_iter().all(|(entity, terminus)| {
+        terminus
+            .quotas
+            .iter()
+            .all(|(flavor, quota)| deliveries.get(entity, flavor) >= *quota)
+    })
+}
+
+/// The background of the progress bar shown next to a collected flavor that
+/// has a quota.
+#[derive(Component)]
+pub struct DeliveryProgressBar(pub PixieFlavor);
+
+/// The filled portion of a [`DeliveryProgressBar`].
+#[derive(Component)]
+pub struct DeliveryProgressFill;
+
+/// Spawns a progress bar for a terminus's quota, initially hidden. `position`
+/// is the left edge of the bar, relative to the terminus.
+pub fn spawn_progress_bar(parent: &mut ChildSpawnerCommands, flavor: PixieFlavor, position: Vec2) {
+    let shape = shapes::Rectangle {
+        extents: Vec2::new(PROGRESS_BAR_WIDTH, PROGRESS_BAR_HEIGHT),
+        origin: shapes::RectangleOrigin::BottomLeft,
+        ..default()
+    };
+
+    parent
+        .spawn((
+            ShapeBuilder::with(&shape).fill(theme::GRID).build(),
+            Transform::from_translation(
+                (position - Vec2::new(0.0, PROGRESS_BAR_HEIGHT / 2.0)).extend(layer::TERMINUS),
+            ),
+            Visibility::Hidden,
+            DeliveryProgressBar(flavor),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                ShapeBuilder::with(&shape)
+                    .fill(theme::PIXIE[flavor.color as usize])
+                    .build(),
+                Transform::from_xyz(0.0, 0.0, 0.1).with_scale(Vec3::new(0.0, 1.0, 1.0)),
+                DeliveryProgressFill,
+            ));
+        });
+}
+
+/// Shows the number of pixies a terminus has accepted, and the number it has
+/// rejected, if any.
+#[derive(Component)]
+pub struct DeliveryCounter;
+
+/// Spawns a delivery counter for a terminus, initially hidden.
+pub fn spawn_delivery_counter(
+    parent: &mut ChildSpawnerCommands,
+    font: Handle<Font>,
+    position: Vec2,
+) {
+    parent
+        .spawn((
+            Text2d::default(),
+            TextFont {
+                font: font.clone(),
+                font_size: 20.0,
+                ..default()
+            },
+            TextLayout::new_with_justify(Justify::Center),
+            Transform::from_translation(position.extend(layer::TERMINUS)),
+            Visibility::Hidden,
+            DeliveryCounter,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextSpan::new("0"),
+                TextFont {
+                    font: font.clone(),
+                    font_size: 20.0,
+                    ..default()
+                },
+                TextColor(theme::UI_LABEL.into()),
+            ));
+            parent.spawn((
+                TextSpan::default(),
+                TextFont {
+                    font,
+                    font_size: 20.0,
+                    ..default()
+                },
+                TextColor(theme::UI_LABEL_BAD.into()),
+            ));
+        });
+}
+
+fn delivery_counter_system(
+    deliveries: Res<Deliveries>,
+    sim_state: Res<SimulationState>,
+    mut q_counters: Query<(Entity, &ChildOf, &mut Visibility), With<DeliveryCounter>>,
+    mut writer: Text2dWriter,
+) {
+    if !deliveries.is_changed() && !sim_state.is_changed() {
+        return;
+    }
+
+    for (entity, child_of, mut visibility) in q_counters.iter_mut() {
+        *visibility = if *sim_state == SimulationState::NotStarted {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+
+        let terminus = child_of.parent();
+
+        *writer.text(entity, 1) = format!("{}", deliveries.total(terminus));
+
+        let rejected = deliveries.rejected(terminus);
+        *writer.text(entity, 2) = if rejected > 0 {
+            format!(" -{rejected}")
+        } else {
+            "".to_string()
+        };
+    }
+}
+
+fn progress_bar_system(
+    deliveries: Res<Deliveries>,
+    sim_state: Res<SimulationState>,
+    q_terminuses: Query<&Terminus>,
+    mut q_bars: Query<(&DeliveryProgressBar, &ChildOf, &Children, &mut Visibility)>,
+    mut q_fills: Query<&mut Transform, With<DeliveryProgressFill>>,
+) {
+    if !deliveries.is_changed() && !sim_state.is_changed() {
+        return;
+    }
+
+    for (bar, child_of, children, mut visibility) in q_bars.iter_mut() {
+        // progress is only interesting once the pixies are on their way
+        *visibility = if *sim_state == SimulationState::NotStarted {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+
+        let Ok(terminus) = q_terminuses.get(child_of.parent()) else {
+            continue;
+        };
+
+        let Some(quota) = terminus.quotas.get(&bar.0) else {
+            continue;
+        };
+
+        let delivered = deliveries.get(child_of.parent(), &bar.0);
+        let progress = if *quota == 0 {
+            1.0
+        } else {
+            (delivered as f32 / *quota as f32).min(1.0)
+        };
+
+        let mut iter = q_fills.iter_many_mut(children);
+        while let Some(mut transform) = iter.fetch_next() {
+            transform.scale.x = progress;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{entity, flavor};
+
+    #[test]
+    fn deliver_respects_capacity() {
+        let terminus = Terminus {
+            collects: [flavor(0, 0), flavor(1, 0)].into_iter().collect(),
+            capacity: Some(2),
+            ..default()
+        };
+        let mut deliveries = Deliveries::default();
+
+        assert!(deliveries.deliver(entity(0), &terminus, flavor(0, 0)));
+        assert!(deliveries.deliver(entity(0), &terminus, flavor(1, 0)));
+        assert!(!deliveries.deliver(entity(0), &terminus, flavor(0, 0)));
+        assert_eq!(deliveries.total(entity(0)), 2);
+        assert_eq!(deliveries.rejected(entity(0)), 1);
+    }
+
+    #[test]
+    fn reject_counted_separately() {
+        let terminus = Terminus {
+            collects: [flavor(0, 0)].into_iter().collect(),
+            ..default()
+        };
+        let mut deliveries = Deliveries::default();
+
+        deliveries.deliver(entity(0), &terminus, flavor(0, 0));
+        deliveries.reject(entity(0));
+
+        assert_eq!(deliveries.total(entity(0)), 1);
+        assert_eq!(deliveries.rejected(entity(0)), 1);
+        assert_eq!(deliveries.rejected(entity(1)), 0);
+    }
+
+    #[test]
+    fn quotas_met_each_flavor() {
+        let terminus = Terminus {
+            collects: [flavor(0, 0), flavor(1, 0)].into_iter().collect(),
+            quotas: [(flavor(0, 0), 2), (flavor(1, 0), 1)].into_iter().collect(),
+            ..default()
+        };
+        let mut deliveries = Deliveries::default();
+
+        deliveries.deliver(entity(0), &terminus, flavor(0, 0));
+        deliveries.deliver(entity(0), &terminus, flavor(1, 0));
+        assert!(!quotas_met([(entity(0), &terminus)], &deliveries));
+
+        deliveries.deliver(entity(0), &terminus, flavor(0, 0));
+        assert!(quotas_met([(entity(0), &terminus)], &deliveries));
+    }
+
+    #[test]
+    fn quotas_met_without_quotas() {
+        let terminus = Terminus::default();
+
+        assert!(quotas_met([(entity(0), &terminus)], &Deliveries::default()));
+    }
+}
@@ -0,0 +1,1296 @@
+//! Sharing a level's solution as a file or as a short text code.
+//!
+//! Press Ctrl+E while playing a level to export its solution. In the browser
+//! the code is shown in a prompt to copy it from. On native builds it's copied
+//! to the clipboard and the solution is also written to the `exports`
+//! directory.
+//!
+//! Press Ctrl+V to import a code. In the browser this opens a prompt to paste
+//! it into, and on native builds the code is read from the clipboard. Exported
+//! files can also be dropped onto the window on native builds. Roads that an
+//! import replaces are kept in a solution slot unless one already has them.
+//!
+//! All of the player's save data can be exported the same way from the
+//! settings panel on the level select screen.
+
+use std::{collections::BTreeMap, fmt};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use bevy::{asset::ron, platform::collections::HashMap, prelude::*, window::FileDragAndDrop};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    level::Level,
+    level_reload::{misplaced_segments, LevelRebuild},
+    lines::is_arc,
+    road_class::RoadClass,
+    run_history::{AxisBests, RunBests, RunHistory, RunRecord, MAX_RUNS_PER_LEVEL},
+    save::{BestScores, BestSolutions, MusicVolume, Solution, Solutions},
+    sim::SimulationState,
+    solution_slots::{LevelSlots, SolutionSlot, SolutionSlots},
+    traffic::{TrafficControl, TrafficControlKind},
+    GameState, Handles, RoadSegment, SelectedLevel,
+};
+
+/// The current version of the export format. Bump this when the format
+/// changes in a way that older versions of the game can't read.
+pub const SOLUTION_EXPORT_VERSION: u32 = 1;
+/// Marks text as a solution code rather than plain RON.
+const CODE_PREFIX: &str = "PW:";
+/// The current version of the save data export format.
+pub const SAVE_EXPORT_VERSION: u32 = 1;
+/// Marks text as a save data code rather than plain RON.
+pub const SAVE_CODE_PREFIX: &str = "PWSAVE:";
+/// Where save data is exported to and imported from on native builds.
+pub const SAVE_EXPORT_PATH: &str = "exports/save.ron";
+
+pub struct ExportPlugin;
+impl Plugin for ExportPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (export_solution_system, import_solution_system).run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+/// A level's solution in a form that can be shared with other players.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SolutionExport {
+    pub version: u32,
+    /// The number of the level this is a solution to.
+    pub level: u32,
+    pub segments: Vec<ExportedSegment>,
+    #[serde(default)]
+    pub controls: Vec<ExportedControl>,
+}
+
+/// A road segment's start and end points, layer, whether it is one-way, its
+/// class, and the center of its arc if it is one.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ExportedSegment(
+    pub Vec2,
+    pub Vec2,
+    pub u32,
+    #[serde(default)] pub bool,
+    #[serde(default)] pub RoadClass,
+    #[serde(default)] pub Option<Vec2>,
+);
+
+/// A traffic control's kind, position and layer.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ExportedControl(pub TrafficControlKind, pub Vec2, pub u32);
+
+/// A level's named solution slots, and which of them edits are saved to.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ExportedSlots {
+    pub level: u32,
+    #[serde(default)]
+    pub active: Option<usize>,
+    pub slots: Vec<ExportedSlot>,
+}
+
+/// A solution slot's name, solution and score.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ExportedSlot(
+    pub String,
+    pub SolutionExport,
+    #[serde(default)] pub Option<u32>,
+);
+
+/// A level's completed runs, oldest first, and its best runs along each axis.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ExportedRuns {
+    pub level: u32,
+    #[serde(default)]
+    pub runs: Vec<ExportedRun>,
+    #[serde(default)]
+    pub cheapest: Option<ExportedRun>,
+    #[serde(default)]
+    pub fastest: Option<ExportedRun>,
+    #[serde(default)]
+    pub most_delivered: Option<ExportedRun>,
+}
+
+/// A run's number, timestamp, score, cost, elapsed seconds, delivered pixies,
+/// whether it met its quotas, and the hash of its layout.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ExportedRun(
+    pub u32,
+    pub u64,
+    pub u32,
+    pub u32,
+    pub f32,
+    pub u32,
+    pub bool,
+    pub u64,
+);
+
+/// All of the player's save data in a form that can be moved to another
+/// machine or browser.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SaveExport {
+    pub version: u32,
+    /// Best scores by level number.
+    #[serde(default)]
+    pub scores: BTreeMap<u32, u32>,
+    #[serde(default)]
+    pub solutions: Vec<SolutionExport>,
+    #[serde(default)]
+    pub music_volume: Option<u8>,
+    #[serde(default)]
+    pub slots: Vec<ExportedSlots>,
+    /// The solutions that earned each of the best scores.
+    #[serde(default)]
+    pub best_solutions: Vec<SolutionExport>,
+    #[serde(default)]
+    pub runs: Vec<ExportedRuns>,
+}
+
+/// Imported save data that has been checked against the game's levels.
+#[derive(Debug, Clone, Default)]
+pub struct SaveData {
+    pub scores: BestScores,
+    pub solutions: Solutions,
+    pub music_volume: Option<MusicVolume>,
+    pub slots: SolutionSlots,
+    pub best_solutions: BestSolutions,
+    pub runs: RunHistory,
+    pub run_bests: RunBests,
+}
+
+/// Level numbers where imported save data disagrees with the player's.
+#[derive(Debug, Default, PartialEq)]
+pub struct SaveConflicts {
+    pub scores: Vec<u32>,
+    pub solutions: Vec<u32>,
+}
+impl SaveConflicts {
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty() && self.solutions.is_empty()
+    }
+
+    /// Every level with a conflict, in order.
+    pub fn levels(&self) -> Vec<u32> {
+        let mut levels = self
+            .scores
+            .iter()
+            .chain(self.solutions.iter())
+            .copied()
+            .collect::<Vec<_>>();
+        levels.sort_unstable();
+        levels.dedup();
+
+        levels
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ImportError {
+    /// The text isn't a solution code or a solution file.
+    Malformed,
+    /// The solution was exported by a newer version of the game.
+    UnsupportedVersion(u32),
+    /// Save data refers to a level that doesn't exist.
+    UnknownLevel(u32),
+    /// One of the solutions in save data can't be imported.
+    InvalidSolution(u32, Box<ImportError>),
+    WrongLevel {
+        expected: u32,
+        found: u32,
+    },
+    /// A road or traffic control is on a layer the level doesn't have.
+    MissingLayer(u32),
+    /// Some roads run through the level's obstacles or terminuses.
+    Misplaced(usize),
+}
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Malformed => write!(f, "not a solution"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "solution version {version} is not supported")
+            }
+            Self::WrongLevel { expected, found } => {
+                write!(f, "solution is for level {found}, not level {expected}")
+            }
+            Self::MissingLayer(layer) => write!(f, "level has no layer {layer}"),
+            Self::Misplaced(count) => write!(f, "{count} roads run through obstacles"),
+            Self::UnknownLevel(level) => write!(f, "there is no level {level}"),
+            Self::InvalidSolution(level, e) => write!(f, "solution for level {level}: {e}"),
+        }
+    }
+}
+
+impl SolutionExport {
+    pub fn new(level: u32, solution: &Solution) -> Self {
+        Self {
+            version: SOLUTION_EXPORT_VERSION,
+            level,
+            segments: solution
+                .segments
+                .iter()
+                .map(|s| {
+                    ExportedSegment(s.points.0, s.points.1, s.layer, s.one_way, s.class, s.arc)
+                })
+                .collect(),
+            controls: solution
+                .controls
+                .iter()
+                .map(|c| ExportedControl(c.kind, c.point, c.layer))
+                .collect(),
+        }
+    }
+
+    /// Returns a code containing this solution that is safe to paste into
+    /// chat.
+    pub fn to_code(&self) -> String {
+        to_code(CODE_PREFIX, self)
+    }
+
+    /// Reads a solution from either a code or plain RON.
+    pub fn parse(text: &str) -> Result<Self, ImportError> {
+        let export: Self = parse(CODE_PREFIX, text)?;
+
+        if export.version > SOLUTION_EXPORT_VERSION {
+            return Err(ImportError::UnsupportedVersion(export.version));
+        }
+
+        Ok(export)
+    }
+
+    /// Checks that this solution fits level number `number`, returning it as a
+    /// [`Solution`] if it does.
+    pub fn validate(&self, number: u32, level: &Level) -> Result<Solution, ImportError> {
+        if self.level != number {
+            return Err(ImportError::WrongLevel {
+                expected: number,
+                found: self.level,
+            });
+        }
+
+        let layers = self
+            .segments
+            .iter()
+            .map(|s| s.2)
+            .chain(self.controls.iter().map(|c| c.2));
+        for layer in layers {
+            if layer == 0 || layer > level.layers {
+                return Err(ImportError::MissingLayer(layer));
+            }
+        }
+
+        if self.segments.iter().any(|s| {
+            !s.0.is_finite()
+                || !s.1.is_finite()
+                || s.0 == s.1
+                || s.5.is_some_and(|center| !is_arc((s.0, s.1), center))
+        }) {
+            return Err(ImportError::Malformed);
+        }
+
+        let solution = Solution {
+            segments: self
+                .segments
+                .iter()
+                .map(|s| RoadSegment {
+                    points: (s.0, s.1),
+                    layer: s.2,
+                    one_way: s.3,
+                    class: s.4,
+                    arc: s.5,
+                })
+                .collect(),
+            controls: self
+                .controls
+                .iter()
+                .map(|c| TrafficControl {
+                    kind: c.0,
+                    point: c.1,
+                    layer: c.2,
+                })
+                .collect(),
+        };
+
+        let misplaced = misplaced_segments(level, solution.segments.iter().enumerate());
+        if !misplaced.is_empty() {
+            return Err(ImportError::Misplaced(misplaced.len()));
+        }
+
+        Ok(solution)
+    }
+}
+
+impl From<&RunRecord> for ExportedRun {
+    fn from(record: &RunRecord) -> Self {
+        Self(
+            record.run,
+            record.timestamp,
+            record.score,
+            record.cost,
+            record.elapsed,
+            record.delivered,
+            record.quotas_met,
+            record.solution_hash,
+        )
+    }
+}
+
+impl From<&ExportedRun> for RunRecord {
+    fn from(run: &ExportedRun) -> Self {
+        Self {
+            run: run.0,
+            timestamp: run.1,
+            score: run.2,
+            cost: run.3,
+            elapsed: run.4,
+            delivered: run.5,
+            quotas_met: run.6,
+            solution_hash: run.7,
+        }
+    }
+}
+
+impl SaveExport {
+    pub fn new(
+        scores: &BestScores,
+        solutions: &Solutions,
+        music_volume: MusicVolume,
+        slots: &SolutionSlots,
+        best_solutions: &BestSolutions,
+        runs: &RunHistory,
+        run_bests: &RunBests,
+    ) -> Self {
+        let export_all = |solutions: &HashMap<u32, Solution>| {
+            let mut exports = solutions
+                .iter()
+                .map(|(level, solution)| SolutionExport::new(*level, solution))
+                .collect::<Vec<_>>();
+            exports.sort_by_key(|s| s.level);
+            exports
+        };
+
+        let mut exported_slots = slots
+            .0
+            .iter()
+            .map(|(level, level_slots)| ExportedSlots {
+                level: *level,
+                active: level_slots.active,
+                slots: level_slots
+                    .slots
+                    .iter()
+                    .map(|slot| {
+                        ExportedSlot(
+                            slot.name.clone(),
+                            SolutionExport::new(*level, &slot.solution),
+                            slot.score,
+                        )
+                    })
+                    .collect(),
+            })
+            .collect::<Vec<_>>();
+        exported_slots.sort_by_key(|s| s.level);
+
+        let mut exported_runs = runs
+            .0
+            .keys()
+            .chain(run_bests.0.keys())
+            .copied()
+            .collect::<Vec<_>>();
+        exported_runs.sort_unstable();
+        exported_runs.dedup();
+        let exported_runs = exported_runs
+            .into_iter()
+            .map(|level| {
+                let bests = run_bests.0.get(&level);
+                let best = |axis: fn(&AxisBests) -> &Option<RunRecord>| {
+                    bests.and_then(|b| axis(b).as_ref()).map(ExportedRun::from)
+                };
+
+                ExportedRuns {
+                    level,
+                    runs: runs
+                        .0
+                        .get(&level)
+                        .into_iter()
+                        .flatten()
+                        .map(ExportedRun::from)
+                        .collect(),
+                    cheapest: best(|b| &b.cheapest),
+                    fastest: best(|b| &b.fastest),
+                    most_delivered: best(|b| &b.most_delivered),
+                }
+            })
+            .collect();
+
+        Self {
+            version: SAVE_EXPORT_VERSION,
+            scores: scores.0.iter().map(|(k, v)| (*k, *v)).collect(),
+            solutions: export_all(&solutions.0),
+            music_volume: Some(music_volume.0),
+            slots: exported_slots,
+            best_solutions: export_all(&best_solutions.0),
+            runs: exported_runs,
+        }
+    }
+
+    /// Returns a code containing all of this save data.
+    pub fn to_code(&self) -> String {
+        to_code(SAVE_CODE_PREFIX, self)
+    }
+
+    /// Reads save data from either a code or plain RON.
+    pub fn parse(text: &str) -> Result<Self, ImportError> {
+        let export: Self = parse(SAVE_CODE_PREFIX, text)?;
+
+        if export.version > SAVE_EXPORT_VERSION {
+            return Err(ImportError::UnsupportedVersion(export.version));
+        }
+
+        Ok(export)
+    }
+
+    /// Checks every score and solution against the level it belongs to, which
+    /// `level` looks up by number.
+    pub fn validate<'a>(
+        &self,
+        level: impl Fn(u32) -> Option<&'a Level>,
+    ) -> Result<SaveData, ImportError> {
+        let mut data = SaveData::default();
+
+        for (number, score) in &self.scores {
+            if level(*number).is_none() {
+                return Err(ImportError::UnknownLevel(*number));
+            }
+
+            data.scores.0.insert(*number, *score);
+        }
+
+        let validate_solution = |export: &SolutionExport| {
+            let Some(found) = level(export.level) else {
+                return Err(ImportError::UnknownLevel(export.level));
+            };
+
+            export
+                .validate(export.level, found)
+                .map_err(|e| ImportError::InvalidSolution(export.level, Box::new(e)))
+        };
+
+        for export in &self.solutions {
+            let solution = validate_solution(export)?;
+
+            // two solutions for one level means someone has been editing by hand
+            if data.solutions.0.insert(export.level, solution).is_some() {
+                return Err(ImportError::Malformed);
+            }
+        }
+
+        for export in &self.best_solutions {
+            let solution = validate_solution(export)?;
+
+            // a best solution is only kept along with the score it earned
+            if !data.scores.0.contains_key(&export.level)
+                || data
+                    .best_solutions
+                    .0
+                    .insert(export.level, solution)
+                    .is_some()
+            {
+                return Err(ImportError::Malformed);
+            }
+        }
+
+        for export in &self.slots {
+            let Some(found) = level(export.level) else {
+                return Err(ImportError::UnknownLevel(export.level));
+            };
+
+            if export
+                .active
+                .is_some_and(|active| active >= export.slots.len())
+            {
+                return Err(ImportError::Malformed);
+            }
+
+            let mut level_slots = LevelSlots {
+                active: export.active,
+                slots: vec![],
+            };
+            for ExportedSlot(name, solution, score) in &export.slots {
+                let solution = solution
+                    .validate(export.level, found)
+                    .map_err(|e| ImportError::InvalidSolution(export.level, Box::new(e)))?;
+
+                level_slots.slots.push(SolutionSlot {
+                    name: name.clone(),
+                    solution,
+                    score: *score,
+                });
+            }
+
+            if data.slots.0.insert(export.level, level_slots).is_some() {
+                return Err(ImportError::Malformed);
+            }
+        }
+
+        for export in &self.runs {
+            if level(export.level).is_none() {
+                return Err(ImportError::UnknownLevel(export.level));
+            }
+
+            // runs are numbered in order, and only runs that met their quotas
+            // can be the best
+            let records = export.runs.iter().map(RunRecord::from).collect::<Vec<_>>();
+            let bests = AxisBests {
+                cheapest: export.cheapest.as_ref().map(RunRecord::from),
+                fastest: export.fastest.as_ref().map(RunRecord::from),
+                most_delivered: export.most_delivered.as_ref().map(RunRecord::from),
+            };
+            if records.first().is_some_and(|r| r.run == 0)
+                || records.windows(2).any(|w| w[0].run >= w[1].run)
+                || records
+                    .iter()
+                    .chain(bests.iter())
+                    .any(|r| !r.elapsed.is_finite() || r.elapsed < 0.0)
+                || bests.iter().any(|r| !r.quotas_met)
+            {
+                return Err(ImportError::Malformed);
+            }
+
+            let excess = records.len().saturating_sub(MAX_RUNS_PER_LEVEL);
+            let records = records.into_iter().skip(excess).collect();
+            if data.runs.0.insert(export.level, records).is_some() {
+                return Err(ImportError::Malformed);
+            }
+            data.run_bests.0.insert(export.level, bests);
+        }
+
+        if let Some(volume) = self.music_volume {
+            if volume > 100 {
+                return Err(ImportError::Malformed);
+            }
+
+            data.music_volume = Some(MusicVolume(volume));
+        }
+
+        Ok(data)
+    }
+}
+
+impl SaveData {
+    /// Returns the levels where both this data and the player's existing data
+    /// have a record, but the records differ.
+    pub fn conflicts(&self, scores: &BestScores, solutions: &Solutions) -> SaveConflicts {
+        let mut conflicts = SaveConflicts {
+            scores: self
+                .scores
+                .0
+                .iter()
+                .filter(|(level, score)| scores.0.get(*level).is_some_and(|s| s != *score))
+                .map(|(level, _)| *level)
+                .collect(),
+            solutions: self
+                .solutions
+                .0
+                .iter()
+                .filter(|(level, solution)| solutions.0.get(*level).is_some_and(|s| s != *solution))
+                .map(|(level, _)| *level)
+                .collect(),
+        };
+        conflicts.scores.sort_unstable();
+        conflicts.solutions.sort_unstable();
+
+        conflicts
+    }
+
+    /// Adds this data to the player's existing data. Where both have a score
+    /// for a level the better one is kept along with the solution that earned
+    /// it, and where both have a solution the player's own is kept. Imported
+    /// slots are added unless the player already has a slot with the same
+    /// roads, and imported runs are numbered after the player's own unless
+    /// the player already has them.
+    pub fn merge_into(
+        mut self,
+        scores: &mut BestScores,
+        solutions: &mut Solutions,
+        slots: &mut SolutionSlots,
+        best_solutions: &mut BestSolutions,
+        runs: &mut RunHistory,
+        run_bests: &mut RunBests,
+    ) {
+        for (level, score) in self.scores.0 {
+            let imported_best = self.best_solutions.0.remove(&level);
+
+            match scores.0.get(&level) {
+                Some(best) if *best > score => {}
+                Some(best) if *best == score => {
+                    if let Some(solution) = imported_best {
+                        best_solutions.0.entry(level).or_insert(solution);
+                    }
+                }
+                _ => {
+                    scores.0.insert(level, score);
+                    match imported_best {
+                        Some(solution) => best_solutions.0.insert(level, solution),
+                        None => best_solutions.0.remove(&level),
+                    };
+                }
+            }
+        }
+
+        for (level, solution) in self.solutions.0 {
+            solutions.0.entry(level).or_insert(solution);
+        }
+
+        for (level, imported) in self.slots.0 {
+            let level_slots = slots.0.entry(level).or_default();
+
+            for slot in imported.slots {
+                if level_slots
+                    .slots
+                    .iter()
+                    .any(|s| s.solution.matches(&slot.solution))
+                {
+                    continue;
+                }
+
+                let name = if level_slots.slots.iter().any(|s| s.name == slot.name) {
+                    level_slots.next_name()
+                } else {
+                    slot.name
+                };
+                level_slots.slots.push(SolutionSlot { name, ..slot });
+            }
+        }
+
+        for (level, imported) in self.runs.0 {
+            for record in imported {
+                if runs
+                    .0
+                    .get(&level)
+                    .is_some_and(|r| r.iter().any(|r| r.is_same_run(&record)))
+                {
+                    continue;
+                }
+
+                runs.push(level, record);
+            }
+        }
+
+        for (level, imported) in self.run_bests.0 {
+            let bests = run_bests.0.entry(level).or_default();
+            for record in imported.iter() {
+                bests.record(record);
+            }
+        }
+    }
+
+    /// Throws away the player's existing data in favor of this data.
+    pub fn replace(
+        self,
+        scores: &mut BestScores,
+        solutions: &mut Solutions,
+        music_volume: &mut MusicVolume,
+        slots: &mut SolutionSlots,
+        best_solutions: &mut BestSolutions,
+        runs: &mut RunHistory,
+        run_bests: &mut RunBests,
+    ) {
+        *scores = self.scores;
+        *solutions = self.solutions;
+        *slots = self.slots;
+        *best_solutions = self.best_solutions;
+        *runs = self.runs;
+        *run_bests = self.run_bests;
+        if let Some(volume) = self.music_volume {
+            *music_volume = volume;
+        }
+
+        // otherwise edits would be saved over a slot that isn't what's loaded
+        for (level, level_slots) in slots.0.iter_mut() {
+            let loaded = solutions.0.get(level);
+            if level_slots
+                .active_slot()
+                .is_some_and(|slot| loaded.is_none_or(|s| !slot.solution.matches(s)))
+            {
+                level_slots.active = None;
+            }
+        }
+    }
+}
+
+fn to_code<T: Serialize>(prefix: &str, value: &T) -> String {
+    let text = ron::to_string(value).unwrap();
+
+    format!("{prefix}{}", URL_SAFE_NO_PAD.encode(text))
+}
+
+fn parse<T: for<'de> Deserialize<'de>>(prefix: &str, text: &str) -> Result<T, ImportError> {
+    let text = text.trim();
+
+    let text = match text.strip_prefix(prefix) {
+        Some(code) => {
+            let bytes = URL_SAFE_NO_PAD
+                .decode(code)
+                .map_err(|_| ImportError::Malformed)?;
+            String::from_utf8(bytes).map_err(|_| ImportError::Malformed)?
+        }
+        None => text.to_string(),
+    };
+
+    ron::from_str(&text).map_err(|_| ImportError::Malformed)
+}
+
+fn export_solution_system(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    selected_level: Res<SelectedLevel>,
+    q_segments: Query<&RoadSegment>,
+    q_controls: Query<&TrafficControl>,
+) {
+    if !keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight])
+        || !keyboard_input.just_pressed(KeyCode::KeyE)
+    {
+        return;
+    }
+
+    let solution = Solution {
+        segments: q_segments.iter().cloned().collect(),
+        controls: q_controls.iter().cloned().collect(),
+    };
+    let export = SolutionExport::new(selected_level.0, &solution);
+
+    let code = export.to_code();
+    info!("Solution code for level {}: {code}", export.level);
+    copy_code(&code, "solution code");
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let path =
+            std::path::Path::new("exports").join(format!("level-{}.solution.ron", export.level));
+        let text = ron::ser::to_string_pretty(&export, ron::ser::PrettyConfig::default()).unwrap();
+
+        match write_export(&path, &text) {
+            Ok(()) => info!("Exported solution to {}", path.display()),
+            Err(e) => warn!("Failed to export solution: {e}"),
+        }
+    }
+}
+
+/// Writes exported text to `path`, creating its directory if needed.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write_export(path: &std::path::Path, text: &str) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    std::fs::write(path, text)
+}
+
+/// Gives the player a code to share, described by `what`. In the browser
+/// this opens a prompt with the code selected, and elsewhere it's copied to
+/// the clipboard. Returns whether the player was given the code.
+pub fn copy_code(code: &str, what: &str) -> bool {
+    #[cfg(target_arch = "wasm32")]
+    {
+        web_sys::window().is_some_and(|w| {
+            w.prompt_with_message_and_default(&format!("Copy this {what}"), code)
+                .is_ok()
+        })
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    match arboard::Clipboard::new().and_then(|mut clipboard| clipboard.set_text(code)) {
+        Ok(()) => {
+            info!("Copied {what} to the clipboard");
+            true
+        }
+        Err(e) => {
+            warn!("Failed to copy {what} to the clipboard: {e}");
+            false
+        }
+    }
+}
+
+/// Asks the player for a code to import, described by `what`. In the browser
+/// this opens a prompt to paste it into, and elsewhere it's read from the
+/// clipboard.
+pub fn paste_code(what: &str) -> Option<String> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        web_sys::window()?
+            .prompt_with_message(&format!("Paste a {what}"))
+            .ok()
+            .flatten()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    match arboard::Clipboard::new().and_then(|mut clipboard| clipboard.get_text()) {
+        Ok(text) => Some(text),
+        Err(e) => {
+            warn!("Failed to read a {what} from the clipboard: {e}");
+            None
+        }
+    }
+}
+
+fn import_solution_system(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut drops: MessageReader<FileDragAndDrop>,
+    mut rebuild: ResMut<LevelRebuild>,
+    mut slots: ResMut<SolutionSlots>,
+    solutions: Res<Solutions>,
+    selected_level: Res<SelectedLevel>,
+    sim_state: Res<SimulationState>,
+    levels: Res<Assets<Level>>,
+    handles: Res<Handles>,
+) {
+    let mut imports = vec![];
+
+    for drop in drops.read() {
+        let FileDragAndDrop::DroppedFile { path_buf, .. } = drop else {
+            continue;
+        };
+
+        match std::fs::read_to_string(path_buf) {
+            Ok(text) => imports.push((path_buf.display().to_string(), text)),
+            Err(e) => warn!("Failed to read {}: {e}", path_buf.display()),
+        }
+    }
+
+    if keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight])
+        && keyboard_input.just_pressed(KeyCode::KeyV)
+    {
+        imports.extend(paste_code("solution code").map(|text| ("pasted code".to_string(), text)));
+    }
+
+    for (source, text) in imports {
+        if *sim_state != SimulationState::NotStarted {
+            warn!("Reset the simulation before importing a solution");
+            continue;
+        }
+
+        let Some(level) = handles
+            .levels
+            .get(selected_level.0 as usize - 1)
+            .and_then(|handle| levels.get(handle))
+        else {
+            continue;
+        };
+
+        match SolutionExport::parse(&text).and_then(|e| e.validate(selected_level.0, level)) {
+            Ok(solution) => {
+                info!("Imported solution from {source}");
+
+                if let Some(current) = solutions.0.get(&selected_level.0) {
+                    let level_slots = slots.0.entry(selected_level.0).or_default();
+                    if let Some(index) = level_slots.set_aside(current, &solution) {
+                        info!(
+                            "Kept the previous roads in slot {}",
+                            level_slots.slots[index].name
+                        );
+                    }
+                }
+
+                rebuild.request(Some(solution));
+            }
+            Err(e) => warn!("Failed to import {source}: {e}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{level, segment, solution};
+
+    #[test]
+    fn code_round_trip() {
+        let export = SolutionExport::new(
+            3,
+            &solution([
+                segment((0.0, 0.0), (48.0, 0.0)),
+                RoadSegment {
+                    layer: 2,
+                    ..segment((48.0, 0.0), (96.0, 0.0))
+                },
+            ]),
+        );
+
+        let code = export.to_code();
+        assert!(code.starts_with(CODE_PREFIX));
+        assert_eq!(SolutionExport::parse(&code), Ok(export));
+    }
+
+    #[test]
+    fn parse_plain_ron() {
+        let export = SolutionExport::parse(
+            "(version: 1, level: 2, segments: [((0.0, 0.0), (48.0, 0.0), 1)])",
+        )
+        .unwrap();
+
+        assert_eq!(export.level, 2);
+        assert!(!export.segments[0].3);
+        assert_eq!(export.segments[0].4, RoadClass::Standard);
+    }
+
+    #[test]
+    fn parse_rejects_garbage() {
+        assert_eq!(
+            SolutionExport::parse("PW:not a code"),
+            Err(ImportError::Malformed)
+        );
+        assert_eq!(SolutionExport::parse("hello"), Err(ImportError::Malformed));
+    }
+
+    #[test]
+    fn parse_rejects_newer_versions() {
+        let mut export = SolutionExport::new(1, &Solution::default());
+        export.version = SOLUTION_EXPORT_VERSION + 1;
+
+        assert_eq!(
+            SolutionExport::parse(&export.to_code()),
+            Err(ImportError::UnsupportedVersion(SOLUTION_EXPORT_VERSION + 1))
+        );
+    }
+
+    #[test]
+    fn validate_checks_level() {
+        let level = level();
+
+        let export = SolutionExport::new(1, &solution([segment((0.0, 0.0), (48.0, 0.0))]));
+        assert!(export.validate(1, &level).is_ok());
+        assert_eq!(
+            export.validate(2, &level).unwrap_err(),
+            ImportError::WrongLevel {
+                expected: 2,
+                found: 1
+            }
+        );
+
+        let export = SolutionExport::new(
+            1,
+            &solution([RoadSegment {
+                layer: 3,
+                ..segment((0.0, 0.0), (48.0, 0.0))
+            }]),
+        );
+        assert_eq!(
+            export.validate(1, &level).unwrap_err(),
+            ImportError::MissingLayer(3)
+        );
+
+        let export = SolutionExport::new(1, &solution([segment((-48.0, 48.0), (48.0, 48.0))]));
+        assert_eq!(
+            export.validate(1, &level).unwrap_err(),
+            ImportError::Misplaced(1)
+        );
+
+        let mut export = SolutionExport::new(1, &solution([segment((0.0, 0.0), (48.0, 48.0))]));
+        export.segments[0].5 = Some(Vec2::new(0.0, 48.0));
+        assert!(export.validate(1, &level).is_ok());
+
+        // the ends of an arc must be the same distance from its center
+        export.segments[0].5 = Some(Vec2::new(96.0, 0.0));
+        assert_eq!(
+            export.validate(1, &level).unwrap_err(),
+            ImportError::Malformed
+        );
+    }
+
+    fn save_data(scores: &[(u32, u32)], solutions: &[(u32, Solution)]) -> SaveData {
+        SaveData {
+            scores: BestScores(scores.iter().copied().collect()),
+            solutions: Solutions(solutions.iter().cloned().collect()),
+            music_volume: None,
+            ..default()
+        }
+    }
+
+    fn save_export(data: &SaveData) -> SaveExport {
+        SaveExport::new(
+            &data.scores,
+            &data.solutions,
+            MusicVolume(30),
+            &data.slots,
+            &data.best_solutions,
+            &data.runs,
+            &data.run_bests,
+        )
+    }
+
+    fn run(run: u32, cost: u32) -> RunRecord {
+        RunRecord {
+            run,
+            timestamp: 1_700_000_000 + u64::from(run),
+            score: 1000 - cost,
+            cost,
+            elapsed: 30.0,
+            delivered: 50,
+            quotas_met: true,
+            solution_hash: u64::from(cost),
+        }
+    }
+
+    fn named_slots(active: Option<usize>, slots: &[(&str, Solution)]) -> LevelSlots {
+        LevelSlots {
+            active,
+            slots: slots
+                .iter()
+                .map(|(name, solution)| SolutionSlot {
+                    name: name.to_string(),
+                    solution: solution.clone(),
+                    score: None,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn save_code_round_trip() {
+        let mut data = save_data(
+            &[(1, 100), (2, 200)],
+            &[(1, solution([segment((0.0, 0.0), (48.0, 0.0))]))],
+        );
+        data.slots.0.insert(
+            1,
+            named_slots(
+                Some(0),
+                &[("A", solution([segment((0.0, 0.0), (0.0, 48.0))]))],
+            ),
+        );
+        data.best_solutions
+            .0
+            .insert(2, solution([segment((0.0, 0.0), (48.0, 0.0))]));
+        data.runs.push(1, run(0, 400));
+        data.runs.push(1, run(0, 300));
+        data.run_bests.0.entry(1).or_default().record(&run(2, 300));
+        let export = save_export(&data);
+
+        let code = export.to_code();
+        assert!(code.starts_with(SAVE_CODE_PREFIX));
+        assert_eq!(SaveExport::parse(&code), Ok(export.clone()));
+        assert_eq!(export.runs[0].runs.len(), 2);
+        assert_eq!(
+            export.runs[0].cheapest,
+            Some(ExportedRun::from(&run(2, 300)))
+        );
+
+        // a solution code isn't save data, and vice versa
+        assert!(SolutionExport::parse(&code).is_err());
+        assert!(
+            SaveExport::parse(&SolutionExport::new(1, &Solution::default()).to_code()).is_err()
+        );
+    }
+
+    #[test]
+    fn save_validate_checks_levels() {
+        let level = level();
+        let lookup = |number: u32| (number <= 2).then_some(&level);
+
+        let data = save_data(
+            &[(1, 100)],
+            &[(2, solution([segment((0.0, 0.0), (48.0, 0.0))]))],
+        );
+        let export = save_export(&data);
+        let validated = export.validate(lookup).unwrap();
+        assert_eq!(validated.scores.0.get(&1), Some(&100));
+        assert_eq!(validated.music_volume, Some(MusicVolume(30)));
+
+        let data = save_data(&[(3, 100)], &[]);
+        let export = save_export(&data);
+        assert_eq!(
+            export.validate(lookup).unwrap_err(),
+            ImportError::UnknownLevel(3)
+        );
+
+        let data = save_data(
+            &[],
+            &[(
+                1,
+                solution([RoadSegment {
+                    layer: 3,
+                    ..segment((0.0, 0.0), (48.0, 0.0))
+                }]),
+            )],
+        );
+        let export = save_export(&data);
+        assert_eq!(
+            export.validate(lookup).unwrap_err(),
+            ImportError::InvalidSolution(1, Box::new(ImportError::MissingLayer(3)))
+        );
+
+        let road = solution([segment((0.0, 0.0), (48.0, 0.0))]);
+        let mut data = save_data(&[(1, 100)], &[]);
+        data.best_solutions.0.insert(1, road.clone());
+        data.slots
+            .0
+            .insert(2, named_slots(Some(0), &[("A", road.clone())]));
+        let validated = save_export(&data).validate(lookup).unwrap();
+        assert_eq!(validated.best_solutions.0.get(&1), Some(&road));
+        assert_eq!(validated.slots.0[&2].active, Some(0));
+
+        // the active slot must exist
+        let mut export = save_export(&data);
+        export.slots[0].active = Some(1);
+        assert_eq!(export.validate(lookup).unwrap_err(), ImportError::Malformed);
+
+        // a best solution needs a best score
+        let mut data = save_data(&[], &[]);
+        data.best_solutions.0.insert(1, road);
+        assert_eq!(
+            save_export(&data).validate(lookup).unwrap_err(),
+            ImportError::Malformed
+        );
+
+        let mut data = save_data(&[], &[]);
+        data.runs.0.insert(1, vec![run(1, 400), run(2, 300)]);
+        data.run_bests.0.entry(1).or_default().record(&run(2, 300));
+        let validated = save_export(&data).validate(lookup).unwrap();
+        assert_eq!(validated.runs.0[&1], data.runs.0[&1]);
+        assert_eq!(validated.run_bests.0[&1], data.run_bests.0[&1]);
+
+        let mut export = save_export(&data);
+        export.runs[0].level = 3;
+        assert_eq!(
+            export.validate(lookup).unwrap_err(),
+            ImportError::UnknownLevel(3)
+        );
+
+        // runs are numbered in order
+        let mut export = save_export(&data);
+        export.runs[0].runs.swap(0, 1);
+        assert_eq!(export.validate(lookup).unwrap_err(), ImportError::Malformed);
+
+        // a best run must have met its quotas
+        let mut export = save_export(&data);
+        export.runs[0].fastest.as_mut().unwrap().6 = false;
+        assert_eq!(export.validate(lookup).unwrap_err(), ImportError::Malformed);
+    }
+
+    #[test]
+    fn save_conflicts_and_merge() {
+        let a = solution([segment((0.0, 0.0), (48.0, 0.0))]);
+        let b = solution([segment((0.0, 0.0), (0.0, 48.0))]);
+
+        let mut scores = BestScores([(1, 100), (2, 200)].into_iter().collect());
+        let mut solutions = Solutions([(1, a.clone()), (2, a.clone())].into_iter().collect());
+        let mut slots = SolutionSlots(
+            [(1, named_slots(Some(0), &[("A", a.clone())]))]
+                .into_iter()
+                .collect(),
+        );
+        let mut best_solutions =
+            BestSolutions([(1, a.clone()), (2, a.clone())].into_iter().collect());
+        let mut runs = RunHistory([(1, vec![run(1, 400)])].into_iter().collect());
+        let mut run_bests = RunBests::default();
+        run_bests.0.entry(1).or_default().record(&run(1, 400));
+
+        let mut imported = save_data(
+            &[(1, 100), (2, 300), (3, 50)],
+            &[(1, a.clone()), (2, b.clone()), (3, b.clone())],
+        );
+        imported
+            .slots
+            .0
+            .insert(1, named_slots(None, &[("A", a.clone()), ("A", b.clone())]));
+        imported.best_solutions.0.insert(1, b.clone());
+        imported.best_solutions.0.insert(2, b.clone());
+        imported.runs.0.insert(1, vec![run(1, 400), run(2, 300)]);
+        imported
+            .run_bests
+            .0
+            .entry(1)
+            .or_default()
+            .record(&run(2, 300));
+
+        let conflicts = imported.conflicts(&scores, &solutions);
+        assert_eq!(
+            conflicts,
+            SaveConflicts {
+                scores: vec![2],
+                solutions: vec![2]
+            }
+        );
+        assert_eq!(conflicts.levels(), vec![2]);
+
+        imported.merge_into(
+            &mut scores,
+            &mut solutions,
+            &mut slots,
+            &mut best_solutions,
+            &mut runs,
+            &mut run_bests,
+        );
+        assert_eq!(scores.0.get(&2), Some(&300));
+        assert_eq!(scores.0.get(&3), Some(&50));
+        assert_eq!(solutions.0.get(&2), Some(&a));
+        assert_eq!(solutions.0.get(&3), Some(&b));
+
+        // best solutions follow the best scores
+        assert_eq!(best_solutions.0.get(&1), Some(&a));
+        assert_eq!(best_solutions.0.get(&2), Some(&b));
+        assert_eq!(best_solutions.0.get(&3), None);
+
+        // slots with the same roads aren't duplicated, and names aren't reused
+        let level_slots = &slots.0[&1];
+        assert_eq!(level_slots.active, Some(0));
+        assert_eq!(level_slots.slots.len(), 2);
+        assert_eq!(level_slots.slots[1].name, "B");
+        assert_eq!(level_slots.slots[1].solution, b);
+
+        // runs the player already has aren't duplicated, and the rest are
+        // numbered after the player's own
+        assert_eq!(runs.0[&1], vec![run(1, 400), run(2, 300)]);
+        assert_eq!(run_bests.0[&1].cheapest, Some(run(2, 300)));
+        assert_eq!(run_bests.0[&1].fastest, Some(run(1, 400)));
+    }
+
+    #[test]
+    fn save_replace() {
+        let a = solution([segment((0.0, 0.0), (48.0, 0.0))]);
+        let b = solution([segment((0.0, 0.0), (0.0, 48.0))]);
+
+        let mut scores = BestScores([(1, 100), (2, 200)].into_iter().collect());
+        let mut solutions = Solutions::default();
+        let mut volume = MusicVolume(50);
+        let mut slots = SolutionSlots([(3, LevelSlots::default())].into_iter().collect());
+        let mut best_solutions = BestSolutions([(1, a.clone())].into_iter().collect());
+        let mut runs = RunHistory([(1, vec![run(1, 400)])].into_iter().collect());
+        let mut run_bests = RunBests::default();
+        run_bests.0.entry(1).or_default().record(&run(1, 400));
+
+        let mut imported = save_data(&[(2, 50)], &[(1, a.clone()), (2, a.clone())]);
+        imported.runs.0.insert(2, vec![run(1, 300)]);
+        imported.music_volume = Some(MusicVolume(0));
+        imported
+            .slots
+            .0
+            .insert(1, named_slots(Some(0), &[("A", a.clone())]));
+        imported
+            .slots
+            .0
+            .insert(2, named_slots(Some(0), &[("A", b)]));
+        imported.replace(
+            &mut scores,
+            &mut solutions,
+            &mut volume,
+            &mut slots,
+            &mut best_solutions,
+            &mut runs,
+            &mut run_bests,
+        );
+
+        assert_eq!(scores.0.len(), 1);
+        assert_eq!(scores.0.get(&2), Some(&50));
+        assert_eq!(volume, MusicVolume(0));
+        assert!(best_solutions.0.is_empty());
+        assert!(!slots.0.contains_key(&3));
+        assert_eq!(runs.0.len(), 1);
+        assert_eq!(runs.0[&2], vec![run(1, 300)]);
+        assert!(run_bests.0.is_empty());
+
+        // a slot stays active only if it holds the roads that will be loaded
+        assert_eq!(slots.0[&1].active, Some(0));
+        assert_eq!(slots.0[&2].active, None);
+    }
+}
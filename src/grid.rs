@@ -0,0 +1,116 @@
+//! Draws the grid of points that roads snap to as a single mesh, so that
+//! large levels don't need an entity for every point.
+
+use std::f32::consts::TAU;
+
+use bevy::{
+    asset::RenderAssetUsages,
+    mesh::{Indices, PrimitiveTopology},
+    prelude::*,
+};
+
+use crate::{level::LevelBounds, theme, GRID_SIZE};
+
+const POINT_RADIUS: f32 = 2.5;
+/// The number of sides of the polygon drawn for each grid point.
+const POINT_SIDES: u32 = 8;
+
+pub struct GridPlugin;
+impl Plugin for GridPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_observer(add_grid_mesh);
+    }
+}
+
+/// The grid of a level with these bounds. Its mesh is added when it is
+/// spawned.
+#[derive(Component)]
+pub struct Grid(pub LevelBounds);
+
+/// Returns a mesh with a small polygon at each grid point within `bounds`.
+pub fn grid_mesh(bounds: &LevelBounds) -> Mesh {
+    let mut positions: Vec<[f32; 3]> = vec![];
+    let mut indices: Vec<u32> = vec![];
+
+    for x in bounds.min.x..=bounds.max.x {
+        for y in bounds.min.y..=bounds.max.y {
+            let center = Vec2::new(x as f32, y as f32) * GRID_SIZE;
+            let first = positions.len() as u32;
+
+            positions.push([center.x, center.y, 0.0]);
+
+            for i in 0..POINT_SIDES {
+                let angle = i as f32 / POINT_SIDES as f32 * TAU;
+                let corner = center + Vec2::from_angle(angle) * POINT_RADIUS;
+                positions.push([corner.x, corner.y, 0.0]);
+
+                indices.extend([first, first + 1 + i, first + 1 + (i + 1) % POINT_SIDES]);
+            }
+        }
+    }
+
+    let num_vertices = positions.len();
+
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::RENDER_WORLD,
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(
+        Mesh::ATTRIBUTE_NORMAL,
+        vec![[0.0_f32, 0.0, 1.0]; num_vertices],
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0_f32, 0.0]; num_vertices])
+    .with_inserted_indices(Indices::U32(indices))
+}
+
+fn add_grid_mesh(
+    trigger: On<Add, Grid>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    q_grid: Query<&Grid>,
+) {
+    let Ok(grid) = q_grid.get(trigger.event().entity) else {
+        return;
+    };
+
+    commands.entity(trigger.event().entity).insert((
+        Mesh2d(meshes.add(grid_mesh(&grid.0))),
+        MeshMaterial2d(materials.add(Color::from(theme::GRID))),
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_polygon_per_point() {
+        let bounds = LevelBounds {
+            min: IVec2::new(-2, -1),
+            max: IVec2::new(2, 1),
+        };
+
+        let mesh = grid_mesh(&bounds);
+
+        // 5 × 3 points
+        let points = 15;
+        assert_eq!(mesh.count_vertices(), points * (POINT_SIDES as usize + 1));
+        assert_eq!(
+            mesh.indices().unwrap().len(),
+            points * POINT_SIDES as usize * 3
+        );
+    }
+
+    #[test]
+    fn bounds_clamp_to_grid() {
+        let bounds = LevelBounds::default();
+
+        assert_eq!(
+            bounds.clamp(Vec2::new(-5000.0, 100.0)),
+            Vec2::new(-25.0 * GRID_SIZE, 100.0)
+        );
+        assert_eq!(bounds.clamp(Vec2::ZERO), Vec2::ZERO);
+    }
+}
@@ -0,0 +1,152 @@
+use bevy::{platform::collections::HashMap, prelude::*};
+use bevy_prototype_lyon::prelude::*;
+
+use crate::{
+    layer, pixie::PixieFlavor, sim::SimulationState, snap_to_grid, theme, AfterUpdate, GameState,
+    Handles, ScoreUi, GRID_SIZE,
+};
+
+pub struct HotspotsPlugin;
+impl Plugin for HotspotsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ExplosionLog>();
+        app.add_systems(
+            AfterUpdate,
+            hotspot_markers_system
+                .before(ScoreUi)
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+/// A record of a single pair of pixies annihilating each other.
+#[derive(Clone, Debug)]
+pub struct Explosion {
+    pub position: Vec2,
+    pub tick: u32,
+    pub flavors: (PixieFlavor, PixieFlavor),
+}
+
+/// Every explosion that has occurred during the current run.
+#[derive(Resource, Default)]
+pub struct ExplosionLog(pub Vec<Explosion>);
+
+/// A group of explosions that occurred near the same grid point.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hotspot {
+    pub position: Vec2,
+    pub count: u32,
+    pub first_tick: u32,
+}
+
+#[derive(Component)]
+struct HotspotMarker;
+
+/// Groups explosions by their nearest grid point, returning the resulting
+/// hotspots ordered from most to fewest explosions.
+pub fn hotspots(explosions: &[Explosion]) -> Vec<Hotspot> {
+    let mut by_point: HashMap<IVec2, Hotspot> = HashMap::new();
+
+    for explosion in explosions.iter() {
+        let position = snap_to_grid(explosion.position, GRID_SIZE);
+
+        by_point
+            .entry(position.as_ivec2())
+            .and_modify(|hotspot| {
+                hotspot.count += 1;
+                hotspot.first_tick = hotspot.first_tick.min(explosion.tick);
+            })
+            .or_insert(Hotspot {
+                position,
+                count: 1,
+                first_tick: explosion.tick,
+            });
+    }
+
+    let mut hotspots = by_point.into_values().collect::<Vec<_>>();
+    hotspots.sort_by(|a, b| b.count.cmp(&a.count).then(a.first_tick.cmp(&b.first_tick)));
+
+    hotspots
+}
+
+fn hotspot_markers_system(
+    mut commands: Commands,
+    sim_state: Res<SimulationState>,
+    explosion_log: Res<ExplosionLog>,
+    handles: Res<Handles>,
+    q_markers: Query<Entity, With<HotspotMarker>>,
+) {
+    if !sim_state.is_changed() {
+        return;
+    }
+
+    for entity in q_markers.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    if *sim_state != SimulationState::Finished {
+        return;
+    }
+
+    for hotspot in hotspots(&explosion_log.0) {
+        commands
+            .spawn((
+                ShapeBuilder::with(&shapes::Circle {
+                    radius: GRID_SIZE / 3.0,
+                    ..default()
+                })
+                .stroke((theme::EXPLOSION_HOTSPOT, 2.0))
+                .build(),
+                Transform::from_translation(hotspot.position.extend(layer::ROAD_OVERLAY)),
+                HotspotMarker,
+                DespawnOnExit(GameState::Playing),
+            ))
+            .with_children(|parent| {
+                parent.spawn((
+                    Text2d::new(format!("{}", hotspot.count)),
+                    TextFont {
+                        font: handles.fonts[0].clone(),
+                        font_size: 18.0,
+                        ..default()
+                    },
+                    TextColor(theme::EXPLOSION_HOTSPOT.into()),
+                    TextLayout::new_with_justify(Justify::Center),
+                    Transform::from_xyz(GRID_SIZE / 3.0, GRID_SIZE / 3.0, 0.0),
+                ));
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn explosion(x: f32, y: f32, tick: u32) -> Explosion {
+        Explosion {
+            position: Vec2::new(x, y),
+            tick,
+            flavors: (PixieFlavor::default(), PixieFlavor::default()),
+        }
+    }
+
+    #[test]
+    fn hotspots_grouped_by_grid_point() {
+        let result = hotspots(&[
+            explosion(2.0, -3.0, 10),
+            explosion(GRID_SIZE * 2.0, 0.0, 5),
+            explosion(-4.0, 1.0, 20),
+        ]);
+
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].position, Vec2::ZERO);
+        assert_eq!(result[0].count, 2);
+        assert_eq!(result[0].first_tick, 10);
+        assert_eq!(result[1].position, Vec2::new(GRID_SIZE * 2.0, 0.0));
+        assert_eq!(result[1].count, 1);
+    }
+
+    #[test]
+    fn hotspots_empty() {
+        assert!(hotspots(&[]).is_empty());
+    }
+}
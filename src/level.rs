@@ -1,7 +1,25 @@
-use crate::PixieFlavor;
-use bevy::{platform::collections::HashSet, prelude::*, reflect::TypePath};
+use crate::{physics::PixiePhysicsOverrides, PixieFlavor, GRID_SIZE};
+use bevy::{
+    platform::collections::{HashMap, HashSet},
+    prelude::*,
+    reflect::TypePath,
+};
 use serde::Deserialize;
 
+/// The number of pixies emitted for each flavor a terminus emits, unless the
+/// level says otherwise.
+pub const DEFAULT_EMIT_COUNT: u32 = 50;
+/// The time between pixies of a particular flavor leaving a terminus, unless
+/// the level says otherwise.
+pub const DEFAULT_EMIT_INTERVAL: f32 = 0.4;
+
+pub struct LevelPlugin;
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, check_levels_system);
+    }
+}
+
 #[derive(Deserialize, Debug, Asset, TypePath)]
 pub struct Level {
     pub name: String,
@@ -10,16 +28,359 @@ pub struct Level {
     pub terminuses: Vec<Terminus>,
     pub obstacles: Vec<Obstacle>,
     pub star_thresholds: Vec<u32>,
+    /// When set, pixies of different colors may not travel along the same
+    /// road segment, and the level can't be started until they don't.
+    #[serde(default)]
+    pub forbid_shared_roads: bool,
+    /// Tweaks to the way pixies move in this level.
+    #[serde(default)]
+    pub physics: PixiePhysicsOverrides,
+    /// The part of the grid that roads may be built on.
+    #[serde(default)]
+    pub bounds: LevelBounds,
+}
+
+impl Level {
+    /// Describes anything in this level that is likely to be a mistake, and
+    /// would otherwise be silently ignored.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+
+        for terminus in &self.terminuses {
+            for flavor in terminus.unknown_schedules() {
+                problems.push(format!(
+                    "Terminus at {} has a schedule for {flavor:?}, which it doesn't emit",
+                    terminus.point
+                ));
+            }
+        }
+
+        problems.extend(self.physics.problems());
+
+        // nothing outside the bounds can be reached by roads
+        let rect = self.bounds.rect();
+        for terminus in &self.terminuses {
+            if !rect.contains(terminus.point) {
+                problems.push(format!(
+                    "Terminus at {} is outside the level's bounds",
+                    terminus.point
+                ));
+            }
+        }
+        for obstacle in &self.obstacles {
+            if obstacle.edges().iter().any(|(a, _)| !rect.contains(*a)) {
+                problems.push(format!("{obstacle:?} is outside the level's bounds"));
+            }
+        }
+
+        problems
+    }
+}
+
+/// A rectangle of grid points, measured in grid cells from the origin.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LevelBounds {
+    pub min: IVec2,
+    pub max: IVec2,
+}
+impl Default for LevelBounds {
+    fn default() -> Self {
+        // the size of the arena before levels could choose their own
+        Self {
+            min: IVec2::new(-25, -15),
+            max: IVec2::new(25, 15),
+        }
+    }
+}
+impl LevelBounds {
+    /// The area covered by the grid, in world units.
+    pub fn rect(&self) -> Rect {
+        Rect::from_corners(
+            self.min.as_vec2() * GRID_SIZE,
+            self.max.as_vec2() * GRID_SIZE,
+        )
+    }
+
+    /// Returns the point within the bounds that is closest to `point`.
+    pub fn clamp(&self, point: Vec2) -> Vec2 {
+        let rect = self.rect();
+        point.clamp(rect.min, rect.max)
+    }
 }
 
 #[derive(Deserialize, Debug, Clone, Component)]
 pub enum Obstacle {
     Rect(Vec2, Vec2),
 }
+impl Obstacle {
+    /// The line segments that make up the outline of this obstacle.
+    pub fn edges(&self) -> Vec<(Vec2, Vec2)> {
+        match self {
+            Self::Rect(top_left, bottom_right) => vec![
+                (
+                    Vec2::new(top_left.x, top_left.y),
+                    Vec2::new(bottom_right.x, top_left.y),
+                ),
+                (
+                    Vec2::new(bottom_right.x, top_left.y),
+                    Vec2::new(bottom_right.x, bottom_right.y),
+                ),
+                (
+                    Vec2::new(bottom_right.x, bottom_right.y),
+                    Vec2::new(top_left.x, bottom_right.y),
+                ),
+                (
+                    Vec2::new(top_left.x, bottom_right.y),
+                    Vec2::new(top_left.x, top_left.y),
+                ),
+            ],
+        }
+    }
+}
 
 #[derive(Default, Debug, Deserialize, Clone, Component)]
 pub struct Terminus {
     pub point: Vec2,
     pub emits: HashSet<PixieFlavor>,
     pub collects: HashSet<PixieFlavor>,
+    /// Emission schedules for any of the flavors in `emits` that shouldn't use
+    /// the default schedule.
+    #[serde(default)]
+    pub schedules: HashMap<PixieFlavor, EmitSchedule>,
+    #[serde(default)]
+    pub kind: TerminusKind,
+    /// The number of pixies of each collected flavor that must be delivered
+    /// here for a run to pass.
+    #[serde(default)]
+    pub quotas: HashMap<PixieFlavor, u32>,
+    /// The total number of pixies this terminus will accept. Pixies arriving
+    /// after it is full are not counted.
+    #[serde(default)]
+    pub capacity: Option<u32>,
+}
+impl Terminus {
+    pub fn schedule(&self, flavor: &PixieFlavor) -> EmitSchedule {
+        self.schedules.get(flavor).cloned().unwrap_or_default()
+    }
+
+    /// The flavors that have a schedule but aren't emitted by this terminus.
+    pub fn unknown_schedules(&self) -> impl Iterator<Item = &PixieFlavor> {
+        self.schedules
+            .keys()
+            .filter(|flavor| !self.emits.contains(*flavor))
+    }
+
+    /// Whether this terminus only emits pixies in response to collecting
+    /// others.
+    pub fn is_processor(&self) -> bool {
+        self.kind != TerminusKind::Normal
+    }
+}
+
+#[derive(Default, Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum TerminusKind {
+    /// Emits pixies on a schedule and collects pixies for points.
+    #[default]
+    Normal,
+    /// Consumes one pixie of each flavor in `collects`, then emits one pixie
+    /// of each flavor in `emits`.
+    Combiner,
+    /// Emits each pixie it collects, unchanged, alternating between the
+    /// terminuses that collect its flavor.
+    Splitter,
+}
+
+/// Describes when the pixies of a particular flavor leave a terminus.
+///
+/// ```ron
+/// schedules: {
+///     PixieFlavor(color: 1, net: 0): EmitSchedule(
+///         count: 30,
+///         interval: 0.2,
+///         delay: 5.0,
+///         pattern: Bursts(size: 5, pause: 2.0),
+///     ),
+/// },
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct EmitSchedule {
+    /// The total number of pixies emitted.
+    pub count: u32,
+    /// Seconds between pixies.
+    pub interval: f32,
+    /// Seconds before the first pixie is emitted.
+    pub delay: f32,
+    pub pattern: EmitPattern,
+}
+impl Default for EmitSchedule {
+    fn default() -> Self {
+        Self {
+            count: DEFAULT_EMIT_COUNT,
+            interval: DEFAULT_EMIT_INTERVAL,
+            delay: 0.0,
+            pattern: EmitPattern::Steady,
+        }
+    }
+}
+impl EmitSchedule {
+    /// Returns the time, in seconds after the simulation starts, at which the
+    /// `n`th pixie (counting from zero) should be emitted.
+    pub fn emit_time(&self, n: u32) -> f32 {
+        let n_f = n as f32;
+
+        let offset = match self.pattern {
+            EmitPattern::Steady => n_f * self.interval,
+            EmitPattern::Bursts { size, pause } => {
+                n_f * self.interval + (n / size.max(1)) as f32 * pause
+            }
+            EmitPattern::RampUp { initial_interval } => {
+                // the gap between pixies shrinks (or grows) linearly from
+                // `initial_interval` to `interval` over the course of the
+                // schedule, so the last of the `count - 1` gaps is `interval`.
+                let steps = self.count.saturating_sub(2).max(1) as f32;
+                let step = (self.interval - initial_interval) / steps;
+
+                n_f * initial_interval + step * n_f * (n_f - 1.0) / 2.0
+            }
+        };
+
+        self.delay + offset
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Default, PartialEq)]
+pub enum EmitPattern {
+    /// Pixies are emitted one `interval` apart.
+    #[default]
+    Steady,
+    /// Groups of `size` pixies are emitted one `interval` apart, with an extra
+    /// `pause` seconds between groups.
+    Bursts { size: u32, pause: f32 },
+    /// The first pixies are emitted `initial_interval` apart, gradually
+    /// approaching `interval`.
+    RampUp { initial_interval: f32 },
+}
+
+fn check_levels_system(mut events: MessageReader<AssetEvent<Level>>, levels: Res<Assets<Level>>) {
+    for event in events.read() {
+        let (AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }) = event
+        else {
+            continue;
+        };
+
+        let Some(level) = levels.get(*id) else {
+            continue;
+        };
+
+        for problem in level.problems() {
+            warn!("{}: {problem}", level.name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::abs_diff_eq;
+
+    use super::*;
+    use crate::test_util::level;
+
+    #[test]
+    fn unknown_schedules() {
+        let emitted = PixieFlavor { color: 0, net: 0 };
+        let other = PixieFlavor { color: 1, net: 0 };
+
+        let terminus = Terminus {
+            emits: [emitted].into_iter().collect(),
+            schedules: [(emitted, default()), (other, default())]
+                .into_iter()
+                .collect(),
+            ..default()
+        };
+
+        assert_eq!(terminus.unknown_schedules().collect::<Vec<_>>(), [&other]);
+    }
+
+    #[test]
+    fn outside_bounds() {
+        let mut level = level();
+        assert!(level.problems().is_empty());
+
+        level.terminuses[0].point = Vec2::new(1440.0, 0.0);
+        level.obstacles.push(Obstacle::Rect(
+            Vec2::new(-1248.0, 48.0),
+            Vec2::new(-1152.0, 0.0),
+        ));
+        assert_eq!(level.problems().len(), 2);
+
+        // a wider level has room for both
+        level.bounds = LevelBounds {
+            min: IVec2::new(-30, -15),
+            max: IVec2::new(30, 15),
+        };
+        assert!(level.problems().is_empty());
+    }
+
+    #[test]
+    fn emit_time_steady() {
+        let schedule = EmitSchedule {
+            delay: 1.0,
+            ..default()
+        };
+
+        assert!(abs_diff_eq!(schedule.emit_time(0), 1.0));
+        assert!(abs_diff_eq!(
+            schedule.emit_time(3),
+            1.0 + 3.0 * DEFAULT_EMIT_INTERVAL
+        ));
+    }
+
+    #[test]
+    fn emit_time_bursts() {
+        let schedule = EmitSchedule {
+            interval: 0.5,
+            pattern: EmitPattern::Bursts {
+                size: 2,
+                pause: 3.0,
+            },
+            ..default()
+        };
+
+        assert!(abs_diff_eq!(schedule.emit_time(1), 0.5));
+        assert!(abs_diff_eq!(schedule.emit_time(2), 4.0));
+        assert!(abs_diff_eq!(schedule.emit_time(3), 4.5));
+    }
+
+    #[test]
+    fn emit_time_ramp_up() {
+        let schedule = EmitSchedule {
+            count: 3,
+            interval: 1.0,
+            pattern: EmitPattern::RampUp {
+                initial_interval: 3.0,
+            },
+            ..default()
+        };
+
+        // gaps of 3.0, then 1.0
+        assert!(abs_diff_eq!(schedule.emit_time(1), 3.0));
+        assert!(abs_diff_eq!(schedule.emit_time(2), 4.0));
+    }
+
+    #[test]
+    fn emit_time_ramp_up_ends_at_interval() {
+        let schedule = EmitSchedule {
+            count: 10,
+            interval: 0.5,
+            pattern: EmitPattern::RampUp {
+                initial_interval: 2.0,
+            },
+            ..default()
+        };
+
+        let gap = |n: u32| schedule.emit_time(n) - schedule.emit_time(n - 1);
+        assert!(abs_diff_eq!(gap(1), 2.0, epsilon = 0.0001));
+        assert!(abs_diff_eq!(gap(9), 0.5, epsilon = 0.0001));
+    }
 }
@@ -0,0 +1,274 @@
+//! Rebuilds the current level when its file changes on disk, keeping the
+//! player's roads and flagging any that no longer fit. Also used to swap in a
+//! different solution without leaving the level.
+
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+
+use crate::{
+    build_level,
+    collision::{point_road_collision, road_collision, PointCollision, SegmentCollision},
+    grid::Grid,
+    layer,
+    level::{Level, Obstacle, Terminus},
+    net_ripping::NetRippingState,
+    physics::{level_physics, PixiePhysics},
+    road_drawing::RoadDrawingState,
+    road_path,
+    save::Solution,
+    sim::SimulationState,
+    theme,
+    traffic::TrafficControl,
+    AfterUpdate, GameState, Handles, LevelName, PathfindingState, RoadGraph, RoadSegment,
+    ScoreCalc, SelectedLevel,
+};
+
+pub struct LevelReloadPlugin;
+impl Plugin for LevelReloadPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelRebuild>();
+        app.add_systems(
+            Update,
+            reload_level_system.run_if(in_state(GameState::Playing)),
+        );
+        app.add_systems(
+            AfterUpdate,
+            misplaced_road_indicator_system
+                .after(ScoreCalc)
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+/// A pending rebuild of the current level, which happens as soon as the
+/// simulation isn't running.
+#[derive(Resource, Default)]
+pub struct LevelRebuild {
+    pending: bool,
+    solution: Option<Solution>,
+}
+impl LevelRebuild {
+    /// Rebuilds the level with `solution` in place of the player's current
+    /// roads, or keeps the current roads if `None`.
+    pub fn request(&mut self, solution: Option<Solution>) {
+        self.pending = true;
+        self.solution = solution;
+    }
+}
+
+/// Marks a road segment that runs through an obstacle or terminus.
+#[derive(Component)]
+struct MisplacedRoadIndicator;
+
+/// Returns the road segments that cross one of the level's obstacles, pass
+/// through the middle of one of its terminuses or leave its bounds. These can
+/// only exist if the level changed after they were built.
+pub fn misplaced_segments<'a, T: Ord>(
+    level: &Level,
+    segments: impl IntoIterator<Item = (T, &'a RoadSegment)>,
+) -> Vec<T> {
+    let edges = level
+        .obstacles
+        .iter()
+        .flat_map(Obstacle::edges)
+        .collect::<Vec<_>>();
+
+    let bounds = level.bounds.rect();
+
+    let mut misplaced = segments
+        .into_iter()
+        .filter(|(_, segment)| {
+            let (a, b) = segment.points;
+
+            !bounds.contains(a)
+                || !bounds.contains(b)
+                || edges.iter().any(|edge| {
+                    !matches!(
+                        road_collision(*edge, None, segment.points, segment.arc),
+                        SegmentCollision::None
+                    )
+                })
+                || level.terminuses.iter().any(|terminus| {
+                    matches!(
+                        point_road_collision(terminus.point, segment.points, segment.arc),
+                        PointCollision::Middle
+                    )
+                })
+        })
+        .map(|(id, _)| id)
+        .collect::<Vec<_>>();
+    misplaced.sort();
+
+    misplaced
+}
+
+fn reload_level_system(
+    mut commands: Commands,
+    mut events: MessageReader<AssetEvent<Level>>,
+    mut rebuild: ResMut<LevelRebuild>,
+    mut graph: ResMut<RoadGraph>,
+    mut road_state: ResMut<RoadDrawingState>,
+    mut ripping_state: ResMut<NetRippingState>,
+    mut physics: ResMut<PixiePhysics>,
+    physics_assets: Res<Assets<PixiePhysics>>,
+    levels: Res<Assets<Level>>,
+    selected_level: Res<SelectedLevel>,
+    handles: Res<Handles>,
+    sim_state: Res<SimulationState>,
+    q_level: Query<Entity, Or<(With<Terminus>, With<Obstacle>, With<LevelName>, With<Grid>)>>,
+    q_segments: Query<(Entity, &RoadSegment)>,
+    q_controls: Query<(Entity, &TrafficControl)>,
+) {
+    let Some(handle) = handles.levels.get(selected_level.0 as usize - 1) else {
+        return;
+    };
+
+    for event in events.read() {
+        if event.is_modified(handle) {
+            rebuild.pending = true;
+        }
+    }
+
+    // pulling the level out from under a running simulation would be chaos,
+    // so wait until the player resets it.
+    if !rebuild.pending || *sim_state != SimulationState::NotStarted {
+        return;
+    }
+
+    let Some(level) = levels.get(handle) else {
+        return;
+    };
+
+    rebuild.pending = false;
+
+    info!("Rebuilding level {}", selected_level.0);
+
+    let solution = rebuild.solution.take().unwrap_or_else(|| Solution {
+        segments: q_segments.iter().map(|(_, s)| s.clone()).collect(),
+        controls: q_controls.iter().map(|(_, c)| c.clone()).collect(),
+    });
+
+    for entity in q_level
+        .iter()
+        .chain(q_segments.iter().map(|(e, _)| e))
+        .chain(q_controls.iter().map(|(e, _)| e))
+    {
+        commands.entity(entity).despawn();
+    }
+
+    graph.graph.clear();
+
+    // anything the player was in the middle of refers to roads that are gone
+    road_state.cancel();
+    ripping_state.reset();
+
+    *physics = level_physics(&physics_assets, &handles, Some(level));
+
+    build_level(
+        &mut commands,
+        &mut graph,
+        &handles,
+        selected_level.0,
+        level,
+        Some(&solution),
+    );
+}
+
+fn misplaced_road_indicator_system(
+    mut commands: Commands,
+    pathfinding: Res<PathfindingState>,
+    sim_state: Res<SimulationState>,
+    q_segments: Query<&RoadSegment>,
+    q_indicators: Query<Entity, With<MisplacedRoadIndicator>>,
+) {
+    if !pathfinding.is_changed() && !sim_state.is_changed() {
+        return;
+    }
+
+    for entity in q_indicators.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    if *sim_state != SimulationState::NotStarted {
+        return;
+    }
+
+    for segment in q_segments.iter_many(&pathfinding.misplaced_segments) {
+        commands.spawn((
+            ShapeBuilder::with(&road_path(segment.points, segment.arc))
+                .stroke((theme::MISPLACED_ROAD_WARNING, 4.0))
+                .build(),
+            Transform::from_xyz(0.0, 0.0, layer::ROAD_OVERLAY),
+            MisplacedRoadIndicator,
+            DespawnOnExit(GameState::Playing),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        level::LevelBounds,
+        test_util::{entity, level, segment},
+    };
+
+    #[test]
+    fn misplaced_through_obstacle() {
+        let segments = [
+            segment((-48.0, 48.0), (48.0, 48.0)),
+            segment((-48.0, 0.0), (48.0, 0.0)),
+        ];
+
+        let misplaced = misplaced_segments(
+            &level(),
+            segments
+                .iter()
+                .enumerate()
+                .map(|(i, s)| (entity(i as u32), s)),
+        );
+
+        assert_eq!(misplaced, vec![entity(0)]);
+    }
+
+    #[test]
+    fn misplaced_through_terminus() {
+        let segments = [
+            segment((48.0, 0.0), (144.0, 0.0)),
+            segment((48.0, 0.0), (96.0, 0.0)),
+        ];
+
+        let misplaced = misplaced_segments(
+            &level(),
+            segments
+                .iter()
+                .enumerate()
+                .map(|(i, s)| (entity(i as u32), s)),
+        );
+
+        assert_eq!(misplaced, vec![entity(0)]);
+    }
+
+    #[test]
+    fn misplaced_out_of_bounds() {
+        let mut level = level();
+        level.bounds = LevelBounds {
+            min: IVec2::new(-4, -4),
+            max: IVec2::new(4, 4),
+        };
+        let segments = [
+            segment((144.0, -96.0), (240.0, -96.0)),
+            segment((96.0, -96.0), (192.0, -96.0)),
+        ];
+
+        let misplaced = misplaced_segments(
+            &level,
+            segments
+                .iter()
+                .enumerate()
+                .map(|(i, s)| (entity(i as u32), s)),
+        );
+
+        assert_eq!(misplaced, vec![entity(0)]);
+    }
+}
@@ -1,8 +1,10 @@
+use std::f32::consts::PI;
+
 use bevy::prelude::*;
 
 use crate::{
-    collision::{point_segment_collision, PointCollision},
-    RoadSegment,
+    collision::{arc_sweep, point_road_collision, PointCollision, ARC_TOLERANCE},
+    RoadSegment, GRID_SIZE,
 };
 
 #[derive(Clone, Copy, Debug)]
@@ -26,6 +28,128 @@ pub fn corner_angle(a: Vec2, b: Vec2, c: Vec2) -> f32 {
     seg_a.perp_dot(seg_b).abs().atan2(seg_a.dot(seg_b))
 }
 
+/// The angle between the points that arcs are drawn through.
+pub const ARC_STEP: f32 = PI / 12.0;
+
+/// Rounds off the corner at `b` with an arc of the given radius, returning
+/// the point where the arc leaves `a - b`, the point where it joins `b - c`,
+/// and the center of the arc.
+///
+/// Returns `None` if there is no corner to round, or if the arc would not fit
+/// on either of the segments.
+///
+/// ```text
+/// a - .
+///      `.
+///       |
+///       c
+/// ```
+pub fn round_corner(a: Vec2, b: Vec2, c: Vec2, radius: f32) -> Option<(Vec2, Vec2, Vec2)> {
+    let into = (b - a).normalize_or_zero();
+    let out = (c - b).normalize_or_zero();
+
+    let turn = into.angle_to(out);
+    if turn.abs() < f32::EPSILON || turn.abs() >= PI - f32::EPSILON {
+        return None;
+    }
+
+    let tangent = radius * (turn.abs() / 2.0).tan();
+    if tangent > a.distance(b) + ARC_TOLERANCE || tangent > b.distance(c) + ARC_TOLERANCE {
+        return None;
+    }
+
+    let start = b - into * tangent;
+    let end = b + out * tangent;
+    let center = start + into.perp() * radius * turn.signum();
+
+    // right-angled corners line up with the grid, give or take some rounding
+    // error. snap those points back onto it so they can be connected to.
+    let snap = |point: Vec2| {
+        let snapped = (point / GRID_SIZE).round() * GRID_SIZE;
+        if point.distance(snapped) < ARC_TOLERANCE {
+            snapped
+        } else {
+            point
+        }
+    };
+
+    Some((snap(start), snap(end), snap(center)))
+}
+
+/// Whether `points` could be the ends of an arc around `center`. Arcs are never
+/// a half circle or more, so that there is no doubt about which way they go.
+pub fn is_arc(points: (Vec2, Vec2), center: Vec2) -> bool {
+    let radius = center.distance(points.0);
+
+    center.is_finite()
+        && radius > 0.0
+        && (center.distance(points.1) - radius).abs() < ARC_TOLERANCE
+        && arc_sweep(points.0, points.1, center).abs() < PI - ARC_TOLERANCE
+}
+
+/// Returns the length of a road segment, following the arc around `arc` if it
+/// has one.
+pub fn segment_length(points: (Vec2, Vec2), arc: Option<Vec2>) -> f32 {
+    match arc {
+        Some(center) => center.distance(points.0) * arc_sweep(points.0, points.1, center).abs(),
+        None => points.0.distance(points.1),
+    }
+}
+
+/// Returns the point halfway along a road segment.
+pub fn midpoint(points: (Vec2, Vec2), arc: Option<Vec2>) -> Vec2 {
+    match arc {
+        Some(center) => {
+            center
+                + Vec2::from_angle(arc_sweep(points.0, points.1, center) / 2.0)
+                    .rotate(points.0 - center)
+        }
+        None => (points.0 + points.1) / 2.0,
+    }
+}
+
+/// Returns the direction of travel at `point` along a road segment traveled
+/// from `points.0` to `points.1`.
+pub fn direction_at(points: (Vec2, Vec2), arc: Option<Vec2>, point: Vec2) -> Vec2 {
+    match arc {
+        Some(center) => {
+            (point - center).perp().normalize_or_zero()
+                * arc_sweep(points.0, points.1, center).signum()
+        }
+        None => (points.1 - points.0).normalize_or_zero(),
+    }
+}
+
+/// Returns points along an arc around `center`, close enough together to draw
+/// it with straight lines.
+pub fn arc_points(points: (Vec2, Vec2), center: Vec2) -> Vec<Vec2> {
+    let sweep = arc_sweep(points.0, points.1, center);
+    let steps = (sweep.abs() / ARC_STEP).ceil().max(1.0) as usize;
+    let spoke = points.0 - center;
+
+    let mut arc = vec![points.0];
+    arc.extend(
+        (1..steps)
+            .map(|i| center + Vec2::from_angle(sweep * i as f32 / steps as f32).rotate(spoke)),
+    );
+    arc.push(points.1);
+
+    arc
+}
+
+/// Returns the angle formed where one road segment leads into the next, taking
+/// the direction of any arcs at that point into account. Returns values in
+/// the range of 0.0..=PI radians.
+pub fn joining_angle(from: &RoadSegment, to: &RoadSegment) -> f32 {
+    let point = to.points.0;
+
+    corner_angle(
+        point - direction_at(from.points, from.arc, point),
+        point,
+        point + direction_at(to.points, to.arc, point),
+    )
+}
+
 /// Given a start and endpoint, return up to two points that represent the
 /// middle of possible 45-degree-only two segment polylines that connect them.
 /// ```text
@@ -80,18 +204,18 @@ pub fn possible_lines(
     vec![vec![(from, b), (b, to)], vec![(from, a), (a, to)]]
 }
 
-pub fn distance_on_path(start: Vec2, point: Vec2, segments: &[(Vec2, Vec2)]) -> Option<f32> {
+/// Returns the distance along `segments` from the start of the first to
+/// `point`, or `None` if `point` isn't on any of them.
+pub fn distance_on_path(point: Vec2, segments: &[((Vec2, Vec2), Option<Vec2>)]) -> Option<f32> {
     let mut total_dist = 0.0;
-    let mut starting_point = start;
 
-    for segment in segments.iter() {
-        match point_segment_collision(point, segment.0, segment.1) {
+    for (points, arc) in segments.iter() {
+        match point_road_collision(point, *points, *arc) {
             PointCollision::None => {
-                total_dist += starting_point.distance(segment.1);
-                starting_point = segment.0;
+                total_dist += segment_length(*points, *arc);
             }
             _ => {
-                return Some(total_dist + starting_point.distance(point));
+                return Some(total_dist + segment_length((points.0, point), *arc));
             }
         }
     }
@@ -99,34 +223,48 @@ pub fn distance_on_path(start: Vec2, point: Vec2, segments: &[(Vec2, Vec2)]) ->
     None
 }
 
+/// Returns the distance from `current` to the end of `segment`.
+///
+/// * `current` A point on the segment
+pub fn distance_remaining(current: Vec2, segment: &RoadSegment) -> f32 {
+    segment_length((current, segment.points.1), segment.arc)
+}
+
+/// Returns the point `distance` further along `segment` than `current`.
+fn advance(current: Vec2, distance: f32, segment: &RoadSegment) -> Vec2 {
+    let (prev, next) = segment.points;
+
+    match segment.arc {
+        Some(center) => {
+            let angle = distance / center.distance(prev) * arc_sweep(prev, next, center).signum();
+            center + Vec2::from_angle(angle).rotate(current - center)
+        }
+        None => current + distance / prev.distance(next) * (next - prev),
+    }
+}
+
+/// Returns the pieces of `segments` covered by traveling `distance` from
+/// `start`, along with the centers of any arcs they are part of.
+///
 /// * `start` The starting point, which should be on the first segment
 pub fn traveled_segments(
     start: Vec2,
     distance: f32,
     segments: &[RoadSegment],
-) -> Vec<(Vec2, Vec2)> {
+) -> Vec<((Vec2, Vec2), Option<Vec2>)> {
     let mut to_go = distance;
-    let mut i = 0;
     let mut path = vec![];
     let mut current = start;
 
-    while i < segments.len() {
-        let prev = segments[i].points.0;
-        let next = segments[i].points.1;
-        let to_next = current.distance(next);
+    for segment in segments.iter() {
+        let to_next = distance_remaining(current, segment);
 
         if to_next < to_go {
-            path.push((current, next));
-            current = next;
+            path.push(((current, segment.points.1), segment.arc));
+            current = segment.points.1;
             to_go -= to_next;
-            i += 1;
         } else {
-            let segment_diff = next - prev;
-            let segment_length = prev.distance(next);
-
-            let projected = current + to_go / segment_length * segment_diff;
-
-            path.push((current, projected));
+            path.push(((current, advance(current, to_go, segment)), segment.arc));
 
             return path;
         }
@@ -135,33 +273,38 @@ pub fn traveled_segments(
     path
 }
 
+/// Returns the distance from `start` to the end of the last segment.
+///
+/// * `start` The starting point, which should be on the first segment
+pub fn path_length(start: Vec2, segments: &[RoadSegment]) -> f32 {
+    let Some(first) = segments.first() else {
+        return 0.0;
+    };
+
+    distance_remaining(start, first)
+        + segments[1..]
+            .iter()
+            .map(|segment| segment_length(segment.points, segment.arc))
+            .sum::<f32>()
+}
+
 /// * `start` The starting point, which should be on the first segment
 pub fn travel(start: Vec2, distance: f32, segments: &[RoadSegment]) -> (Vec2, usize) {
     let mut to_go = distance;
-    let mut i = 0;
     let mut current = start;
 
-    while i < segments.len() {
-        let prev = segments[i].points.0;
-        let next = segments[i].points.1;
-
-        let to_next = current.distance(next);
+    for (i, segment) in segments.iter().enumerate() {
+        let to_next = distance_remaining(current, segment);
 
         if to_next < to_go {
-            current = next;
+            current = segment.points.1;
             to_go -= to_next;
-            i += 1;
         } else {
-            let segment_diff = next - prev;
-            let segment_length = prev.distance(next);
-
-            let projected = current + to_go / segment_length * segment_diff;
-
-            return (projected, i);
+            return (advance(current, to_go, segment), i);
         }
     }
 
-    (segments.last().unwrap().points.1, i)
+    (segments.last().unwrap().points.1, segments.len())
 }
 
 #[cfg(test)]
@@ -169,6 +312,7 @@ mod tests {
     use approx::abs_diff_eq;
 
     use super::*;
+    use crate::test_util::segment;
 
     #[test]
     fn corner_angle_straight_line() {
@@ -184,4 +328,140 @@ mod tests {
         let angle = corner_angle(Vec2::new(0.0, 2.0), Vec2::new(0.0, 1.0), Vec2::ZERO);
         assert!(abs_diff_eq!(angle, std::f32::consts::PI));
     }
+
+    #[test]
+    fn round_corner_right_angle() {
+        let (start, end, center) = round_corner(
+            Vec2::ZERO,
+            Vec2::new(96.0, 0.0),
+            Vec2::new(96.0, 96.0),
+            48.0,
+        )
+        .unwrap();
+
+        assert_eq!(start, Vec2::new(48.0, 0.0));
+        assert_eq!(end, Vec2::new(96.0, 48.0));
+        assert_eq!(center, Vec2::new(48.0, 48.0));
+    }
+
+    #[test]
+    fn round_corner_shallow_angle() {
+        let (start, end, center) = round_corner(
+            Vec2::ZERO,
+            Vec2::new(96.0, 0.0),
+            Vec2::new(192.0, 96.0),
+            48.0,
+        )
+        .unwrap();
+
+        assert!(abs_diff_eq!(start.distance(center), 48.0, epsilon = 0.001));
+        assert!(abs_diff_eq!(end.distance(center), 48.0, epsilon = 0.001));
+        assert!(abs_diff_eq!(
+            arc_sweep(start, end, center),
+            std::f32::consts::FRAC_PI_4,
+            epsilon = 0.001
+        ));
+    }
+
+    #[test]
+    fn round_corner_no_room() {
+        assert!(round_corner(
+            Vec2::ZERO,
+            Vec2::new(48.0, 0.0),
+            Vec2::new(48.0, 48.0),
+            96.0
+        )
+        .is_none());
+
+        assert!(
+            round_corner(Vec2::ZERO, Vec2::new(48.0, 0.0), Vec2::new(96.0, 0.0), 48.0).is_none()
+        );
+    }
+
+    /// A road heading right that turns up through a quarter circle.
+    fn rounded_corner() -> Vec<RoadSegment> {
+        vec![
+            segment(Vec2::ZERO, Vec2::new(48.0, 0.0)),
+            RoadSegment {
+                arc: Some(Vec2::new(48.0, 48.0)),
+                ..segment(Vec2::new(48.0, 0.0), Vec2::new(96.0, 48.0))
+            },
+            segment(Vec2::new(96.0, 48.0), Vec2::new(96.0, 96.0)),
+        ]
+    }
+
+    #[test]
+    fn path_length_from_partway() {
+        let segments = [
+            segment(Vec2::ZERO, Vec2::new(48.0, 0.0)),
+            segment(Vec2::new(48.0, 0.0), Vec2::new(48.0, 48.0)),
+        ];
+
+        assert_eq!(path_length(Vec2::new(12.0, 0.0), &segments), 84.0);
+        assert_eq!(path_length(Vec2::ZERO, &segments[..0]), 0.0);
+
+        let quarter = 24.0 * PI;
+        assert!(abs_diff_eq!(
+            path_length(Vec2::new(12.0, 0.0), &rounded_corner()),
+            84.0 + quarter,
+            epsilon = 0.001
+        ));
+    }
+
+    #[test]
+    fn travel_around_arc() {
+        let segments = rounded_corner();
+        let quarter = 24.0 * PI;
+
+        // halfway around the arc
+        let (partway, i) = travel(Vec2::ZERO, 48.0 + quarter / 2.0, &segments);
+        assert_eq!(i, 1);
+        assert!(abs_diff_eq!(
+            partway.distance(Vec2::new(48.0, 48.0)),
+            48.0,
+            epsilon = 0.001
+        ));
+        assert!(abs_diff_eq!(
+            partway.x,
+            48.0 + 48.0 * (PI / 4.0).sin(),
+            epsilon = 0.001
+        ));
+
+        let (end, i) = travel(Vec2::ZERO, 96.0 + quarter + 1.0, &segments);
+        assert_eq!(i, segments.len());
+        assert_eq!(end, Vec2::new(96.0, 96.0));
+    }
+
+    #[test]
+    fn distance_along_traveled_arc() {
+        let segments = rounded_corner();
+        let quarter = 24.0 * PI;
+
+        let traveled = traveled_segments(Vec2::new(24.0, 0.0), 200.0, &segments);
+        assert_eq!(traveled.len(), 3);
+        assert_eq!(traveled[1].1, Some(Vec2::new(48.0, 48.0)));
+
+        let on_arc = Vec2::new(48.0, 48.0) + Vec2::from_angle(-PI / 4.0) * 48.0;
+        let dist = distance_on_path(on_arc, &traveled).unwrap();
+        assert!(abs_diff_eq!(dist, 24.0 + quarter / 2.0, epsilon = 0.01));
+
+        // the chord across the corner isn't on the road
+        assert!(distance_on_path(Vec2::new(72.0, 24.0), &traveled).is_none());
+    }
+
+    #[test]
+    fn joining_angle_follows_arcs() {
+        let segments = rounded_corner();
+
+        assert!(abs_diff_eq!(
+            joining_angle(&segments[0], &segments[1]),
+            PI,
+            epsilon = 0.001
+        ));
+        assert!(abs_diff_eq!(
+            joining_angle(&segments[1], &segments[2]),
+            PI,
+            epsilon = 0.001
+        ));
+    }
 }
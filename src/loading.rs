@@ -1,4 +1,4 @@
-use crate::{save::SaveFile, GameState, Handles, MainCamera};
+use crate::{camera::DEFAULT_TRANSLATION, save::SaveFile, GameState, Handles, MainCamera};
 use bevy::{asset::LoadState, prelude::*};
 use bevy_pipelines_ready::{PipelinesReady, PipelinesReadyPlugin};
 use bevy_prototype_lyon::prelude::*;
@@ -33,7 +33,7 @@ fn loading_setup(
 ) {
     commands.spawn((
         Camera2d,
-        Transform::from_translation(Vec3::new(0., -10., 0.)),
+        Transform::from_translation(DEFAULT_TRANSLATION.extend(0.0)),
         Msaa::Sample4,
         MainCamera,
     ));
@@ -68,6 +68,7 @@ fn loading_setup(
     ));
 
     handles.music = asset_server.load("music/galactic_odyssey_by_alkakrab.ogg");
+    handles.physics = asset_server.load("pixie.physics.ron");
 }
 
 fn loading_update(
@@ -105,6 +106,13 @@ fn loading_update(
         return;
     }
 
+    if !matches!(
+        asset_server.get_load_state(&handles.physics),
+        Some(LoadState::Loaded),
+    ) {
+        return;
+    }
+
     // Firefox's FPS seems to take a few frames to recover after pipelines are
     // compiled, resulting in weird audio artifacts.
     if *frames_since_pipelines_ready < 10 {
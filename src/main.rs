@@ -1,18 +1,36 @@
 // disable console on windows for release builds
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use std::time::Duration;
 #[cfg(feature = "debugdump")]
 use std::{fs::File, io::Write};
 
 use crate::{
-    level::{Level, Obstacle, Terminus},
+    camera::CameraPlugin,
+    deliveries::{
+        quotas_met, spawn_delivery_counter, spawn_progress_bar, Deliveries, DeliveriesPlugin,
+        QuotasMet,
+    },
+    export::ExportPlugin,
+    grid::{Grid, GridPlugin},
+    hotspots::{ExplosionLog, HotspotsPlugin},
+    level::{Level, LevelBounds, LevelPlugin, Obstacle, Terminus, TerminusKind},
+    level_reload::{misplaced_segments, LevelReloadPlugin},
+    lines::{arc_points, direction_at, midpoint, segment_length},
     loading::LoadingPlugin,
     net_ripping::NetRippingPlugin,
+    physics::{PixiePhysics, PixiePhysicsPlugin},
     pixie::{Pixie, PixieEmitter, PixieFlavor, PixiePlugin},
+    processors::{processor_cycles, ProcessorStates, ProcessorsPlugin},
+    road_class::RoadClass,
     road_drawing::{RoadDrawingPlugin, RoadDrawingState},
-    save::{BestScores, MusicVolume, SavePlugin, Solution, Solutions},
+    routes::{find_routes, junctions, segment_weight, JunctionKey},
+    run_history::RunHistoryPlugin,
+    save::{BestScores, BestSolutions, MusicVolume, SavePlugin, Solution, Solutions},
+    shared_roads::{shared_segments, SharedRoadsPlugin},
     sim::{SimulationPlugin, SimulationSettings, SimulationState, SimulationSteps},
+    solution_diff::SolutionDiffPlugin,
+    solution_slots::{SlotsButton, SolutionSlotsPlugin},
+    traffic::{spawn_traffic_control, TrafficControl, TrafficControlKind, TrafficPlugin},
     ui::{
         button,
         radio_button::{RadioButton, RadioButtonGroup, RadioButtonGroupRelation, RadioButtonSet},
@@ -21,8 +39,13 @@ use crate::{
 };
 
 use bevy::{
-    app::MainScheduleOrder, asset::AssetMetaCheck, ecs::schedule::ScheduleLabel,
-    platform::collections::HashMap, prelude::*, sprite::Anchor, window::CursorMoved,
+    app::MainScheduleOrder,
+    asset::AssetMetaCheck,
+    ecs::schedule::ScheduleLabel,
+    platform::collections::{HashMap, HashSet},
+    prelude::*,
+    sprite::Anchor,
+    window::CursorMoved,
 };
 
 use bevy_common_assets::ron::RonAssetPlugin;
@@ -31,22 +54,38 @@ use bevy_prototype_lyon::prelude::*;
 use itertools::Itertools;
 use net_ripping::NetRippingState;
 use petgraph::{
-    algo::astar,
     dot::{Config, Dot},
     stable_graph::{NodeIndex, StableUnGraph},
 };
 
+mod camera;
 mod collision;
+mod deliveries;
+mod export;
+mod grid;
+mod hotspots;
 mod layer;
 mod level;
+mod level_reload;
 mod lines;
 mod loading;
 mod net_ripping;
+mod physics;
 mod pixie;
+mod processors;
+mod road_class;
 mod road_drawing;
+mod routes;
+mod run_history;
 mod save;
+mod shared_roads;
 mod sim;
+mod solution_diff;
+mod solution_slots;
+#[cfg(test)]
+mod test_util;
 mod theme;
+mod traffic;
 mod ui;
 
 fn main() {
@@ -84,6 +123,7 @@ fn main() {
     app.add_plugins((
         EasingsPlugin::default(),
         RonAssetPlugin::<Level>::new(&["level.ron"]),
+        RonAssetPlugin::<PixiePhysics>::new(&["physics.ron"]),
     ));
     // Our Plugins
     app.add_plugins((
@@ -95,6 +135,22 @@ fn main() {
         LoadingPlugin,
         SavePlugin,
         UiPlugin,
+        HotspotsPlugin,
+        SharedRoadsPlugin,
+        ProcessorsPlugin,
+        DeliveriesPlugin,
+        TrafficPlugin,
+    ));
+    app.add_plugins((
+        PixiePhysicsPlugin,
+        LevelReloadPlugin,
+        ExportPlugin,
+        SolutionSlotsPlugin,
+        SolutionDiffPlugin,
+        RunHistoryPlugin,
+        CameraPlugin,
+        GridPlugin,
+        LevelPlugin,
     ));
 
     app.init_state::<GameState>();
@@ -150,6 +206,7 @@ fn main() {
             pixie_button_system,
             reset_button_system,
             speed_button_system,
+            routing_button_system,
             back_button_system,
         )
             .run_if(in_state(GameState::Playing)),
@@ -246,13 +303,14 @@ struct Handles {
     levels: Vec<Handle<Level>>,
     fonts: Vec<Handle<Font>>,
     music: Handle<AudioSource>,
+    physics: Handle<PixiePhysics>,
 }
 #[derive(Component)]
 struct MainCamera;
 #[derive(Component)]
 struct Cursor;
 #[derive(Component)]
-struct GridPoint;
+struct LevelName;
 #[derive(Component)]
 struct PixieCountText;
 #[derive(Component)]
@@ -267,14 +325,20 @@ struct ToolButton;
 #[derive(Component)]
 struct LayerButton(u32);
 #[derive(Component)]
+struct RoadClassButton(RoadClass);
+#[derive(Component)]
 struct NetRippingButton;
 #[derive(Component)]
+struct TrafficControlButton(TrafficControlKind);
+#[derive(Component)]
 struct PixieButton;
 #[derive(Component)]
 struct ResetButton;
 #[derive(Component)]
 struct SpeedButton;
 #[derive(Component)]
+struct RoutingButton;
+#[derive(Component)]
 struct BackButton;
 #[derive(Component)]
 struct PlayAreaNode;
@@ -286,10 +350,25 @@ pub struct PixieCount(u32);
 struct Cost(u32);
 #[derive(Resource, Default)]
 struct Score(Option<u32>);
-#[derive(Debug, Clone, Component, Reflect)]
+#[derive(Debug, Clone, PartialEq, Component, Reflect)]
 pub struct RoadSegment {
     points: (Vec2, Vec2),
     layer: u32,
+    /// One-way segments may only be traveled from `points.0` to `points.1`.
+    #[reflect(default)]
+    one_way: bool,
+    #[reflect(default)]
+    class: RoadClass,
+    /// The center of the circle this segment curves around, if it is an arc
+    /// rather than a straight line.
+    #[reflect(default)]
+    arc: Option<Vec2>,
+}
+impl RoadSegment {
+    /// The radius of the arc this segment follows, if it is one.
+    pub fn arc_radius(&self) -> Option<f32> {
+        self.arc.map(|center| center.distance(self.points.0))
+    }
 }
 
 #[derive(Component, Debug)]
@@ -301,7 +380,8 @@ struct SegmentGraphNodes(NodeIndex, NodeIndex);
 enum Tool {
     #[default]
     LineDrawing,
-    NetRipping
+    NetRipping,
+    TrafficControl(TrafficControlKind),
 }
 
 #[derive(Resource, Default)]
@@ -310,12 +390,29 @@ struct SelectedTool(Tool);
 #[derive(Resource, Default)]
 struct PathfindingState {
     valid: bool,
-    paths: Vec<(PixieFlavor, Entity, Vec<RoadSegment>)>,
-    invalid_nodes: Vec<Entity>,
+    /// For each flavor, emitting terminus, and collecting terminus, every
+    /// route pixies may take.
+    paths: Vec<(PixieFlavor, Entity, Entity, Vec<Vec<RoadSegment>>)>,
+    /// Terminus flavors that can't be connected to their counterpart.
+    invalid_ports: Vec<(Entity, TerminusPort)>,
+    /// Road segments that pixies of more than one color will travel along.
+    shared_segments: Vec<Entity>,
+    /// Points where pixies may switch to a less congested route.
+    junctions: HashSet<JunctionKey>,
+    /// Road segments that run through obstacles or terminuses because the
+    /// level changed after they were built.
+    misplaced_segments: Vec<Entity>,
+}
+
+/// One of the flavors that a terminus emits or collects.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum TerminusPort {
+    Emits(PixieFlavor),
+    Collects(PixieFlavor),
 }
 
 #[derive(Component)]
-struct TerminusIssueIndicator;
+struct TerminusIssueIndicator(TerminusPort);
 
 #[derive(Resource, Default)]
 struct RoadGraph {
@@ -333,7 +430,8 @@ struct MouseSnappedPos(Vec2);
 #[derive(Component)]
 enum Collider {
     Point(Vec2),
-    Segment((Vec2, Vec2)),
+    /// A straight segment, or an arc around the given center.
+    Segment((Vec2, Vec2), Option<Vec2>),
 }
 #[derive(Component)]
 struct ColliderLayer(u32);
@@ -344,6 +442,8 @@ const GRID_SIZE: f32 = 48.0;
 pub const BOTTOM_BAR_HEIGHT: f32 = 70.0;
 const LAYER_TWO_MULTIPLIER: f32 = 2.0;
 const LAYER_THREE_MULTIPLIER: f32 = 4.0;
+/// Rounded corners are fiddly to lay down, so they cost more per unit length.
+const ARC_MULTIPLIER: f32 = 1.5;
 
 // This system is designed for tool buttons text
 // it switches them to lime or white if pressed or not
@@ -370,7 +470,10 @@ fn tool_button_system(
     mut selected_tool: ResMut<SelectedTool>,
     mut road_state: ResMut<RoadDrawingState>,
     q_interaction_layer: Query<(&Interaction, &LayerButton), Changed<Interaction>>,
+    q_interaction_class: Query<(&Interaction, &RoadClassButton), Changed<Interaction>>,
     q_interaction_rip: Query<&Interaction, (Changed<Interaction>, With<NetRippingButton>)>,
+    q_interaction_traffic: Query<(&Interaction, &TrafficControlButton), Changed<Interaction>>,
+    mut q_layer_button: Query<(&LayerButton, &mut RadioButton)>,
 ) {
     for (_, layer_button) in q_interaction_layer
         .iter()
@@ -382,6 +485,24 @@ fn tool_button_system(
         }
     }
 
+    for (_, class_button) in q_interaction_class
+        .iter()
+        .filter(|(i, _)| **i == Interaction::Pressed)
+    {
+        road_state.class = class_button.0;
+        if !matches!(selected_tool.0, Tool::LineDrawing) {
+            selected_tool.0 = Tool::LineDrawing;
+
+            // switch the tool buttons back to the layer being drawn on
+            for (_, mut radio) in q_layer_button
+                .iter_mut()
+                .filter(|(layer_button, _)| layer_button.0 == road_state.layer)
+            {
+                radio.selected = true;
+            }
+        }
+    }
+
     for _ in q_interaction_rip
         .iter()
         .filter(|i| **i == Interaction::Pressed)
@@ -390,6 +511,13 @@ fn tool_button_system(
             selected_tool.0 = Tool::NetRipping;
         }
     }
+
+    for (_, traffic_button) in q_interaction_traffic
+        .iter()
+        .filter(|(i, _)| **i == Interaction::Pressed)
+    {
+        selected_tool.0 = Tool::TrafficControl(traffic_button.0);
+    }
 }
 
 // This one is an algo that finds path between terminuses
@@ -398,78 +526,135 @@ fn tool_button_system(
 fn pathfinding_system(
     graph: Res<RoadGraph>,
     mut pathfinding: ResMut<PathfindingState>,
+    levels: Res<Assets<Level>>,
+    selected_level: Res<SelectedLevel>,
+    handles: Res<Handles>,
     q_terminuses: Query<(Entity, &Terminus, &PointGraphNode)>,
     q_road_chunks: Query<&RoadSegment>,
+    q_segment_nodes: Query<(&RoadSegment, &SegmentGraphNodes)>,
+    q_segments: Query<(Entity, &RoadSegment)>,
 ) {
     if !graph.is_changed() {
         return;
     }
 
+    // one-way segments can't be traveled from their end node to their start
+    let forbidden = q_segment_nodes
+        .iter()
+        .filter(|(segment, _)| segment.one_way)
+        .map(|(_, nodes)| (nodes.1, nodes.0))
+        .collect::<HashSet<_>>();
+
     let mut ok = true;
     let mut paths = vec![];
+    let mut path_segments = vec![];
     let mut not_ok = vec![];
 
+    // flavors that are collected by a processor must pass through one on the
+    // way from their original terminus.
+    let processed = q_terminuses
+        .iter()
+        .filter(|(_, t, _)| t.is_processor())
+        .flat_map(|(_, t, _)| t.collects.iter().copied())
+        .collect::<HashSet<_>>();
+
     for (a_entity, a, a_node) in q_terminuses.iter() {
-        for (_, b, b_node) in q_terminuses.iter() {
+        for (b_entity, b, b_node) in q_terminuses.iter() {
+            // a terminus that collects the flavor it emits is not expected to
+            // be connected to itself.
+            if a_entity == b_entity {
+                continue;
+            }
+
             for flavor in a.emits.intersection(&b.collects) {
-                let path = astar(
-                    &graph.graph,
-                    a_node.0,
-                    |finish| finish == b_node.0,
-                    |e| *e.weight(),
-                    |_| 0.0,
-                );
+                if !a.is_processor() && !b.is_processor() && processed.contains(flavor) {
+                    continue;
+                }
 
-                if let Some(path) = path {
-                    let mut prev_end = graph
-                        .graph
-                        .node_weight(*path.1.first().unwrap())
-                        .and_then(|ent| q_terminuses.get(*ent).ok())
-                        .unwrap()
-                        .1
-                        .point;
-
-                    let segments = path
-                        .1
-                        .iter()
-                        .filter_map(|node| graph.graph.node_weight(*node))
-                        .dedup()
-                        .filter_map(|ent| q_road_chunks.get(*ent).ok());
-
-                    let mut world_path = vec![];
-
-                    for seg in segments {
-                        let flipped_seg = if seg.points.0 != prev_end {
-                            RoadSegment {
-                                points: (seg.points.1, seg.points.0),
-                                layer: seg.layer,
-                            }
-                        } else {
-                            seg.clone()
-                        };
+                let routes = find_routes(&graph.graph, a_node.0, b_node.0, &forbidden);
 
-                        prev_end = flipped_seg.points.1;
+                if routes.is_empty() {
+                    ok = false;
+                    not_ok.push((a_entity, TerminusPort::Emits(*flavor)));
+                    not_ok.push((b_entity, TerminusPort::Collects(*flavor)));
+                    continue;
+                }
 
-                        world_path.push(flipped_seg);
-                    }
+                let mut world_paths = vec![];
+
+                for (_, route) in routes.iter() {
+                    let (world_path, segment_entities) =
+                        route_segments(&graph.graph, route, a.point, &q_road_chunks);
 
                     if world_path.is_empty() {
-                        ok = false;
                         continue;
                     }
 
-                    paths.push((*flavor, a_entity, world_path));
-                } else {
+                    world_paths.push(world_path);
+                    path_segments.push((*flavor, segment_entities));
+                }
+
+                if world_paths.is_empty() {
                     ok = false;
-                    not_ok.push(a_entity);
+                    continue;
                 }
+
+                paths.push((*flavor, a_entity, b_entity, world_paths));
             }
         }
     }
 
+    // pixies could be passed between processors forever
+    let processor_links = paths
+        .iter()
+        .filter(|(_, a, b, _)| {
+            [a, b].iter().all(|entity| {
+                q_terminuses
+                    .get(**entity)
+                    .is_ok_and(|(_, t, _)| t.is_processor())
+            })
+        })
+        .map(|(_, a, b, _)| (*a, *b))
+        .collect::<Vec<_>>();
+    let cycles = processor_cycles(&processor_links);
+    for (flavor, a, b, _) in paths.iter() {
+        if cycles.contains(&(*a, *b)) {
+            ok = false;
+            not_ok.push((*a, TerminusPort::Emits(*flavor)));
+            not_ok.push((*b, TerminusPort::Collects(*flavor)));
+        }
+    }
+
+    pathfinding.shared_segments = shared_segments(&path_segments);
+    pathfinding.junctions = junctions(
+        &graph.graph,
+        q_segment_nodes
+            .iter()
+            .map(|(segment, nodes)| (segment, nodes.0, nodes.1)),
+    );
+
+    let level = handles
+        .levels
+        .get(selected_level.0 as usize - 1)
+        .and_then(|h| levels.get(h));
+
+    let forbid_shared_roads = level.is_some_and(|level| level.forbid_shared_roads);
+
+    if forbid_shared_roads && !pathfinding.shared_segments.is_empty() {
+        ok = false;
+    }
+
+    pathfinding.misplaced_segments = level
+        .map(|level| misplaced_segments(level, q_segments.iter()))
+        .unwrap_or_default();
+
+    if !pathfinding.misplaced_segments.is_empty() {
+        ok = false;
+    }
+
     if !ok || paths.is_empty() {
         pathfinding.valid = false;
-        pathfinding.invalid_nodes = not_ok;
+        pathfinding.invalid_ports = not_ok;
         return;
     }
 
@@ -477,6 +662,48 @@ fn pathfinding_system(
     pathfinding.valid = true;
 }
 
+/// Converts a path through the road graph into the road segments a pixie
+/// would travel along, each oriented in the direction of travel. Also returns
+/// the entities of those segments.
+fn route_segments(
+    graph: &StableUnGraph<Entity, f32>,
+    path: &[NodeIndex],
+    start: Vec2,
+    q_road_chunks: &Query<&RoadSegment>,
+) -> (Vec<RoadSegment>, Vec<Entity>) {
+    let mut prev_end = start;
+
+    let segments = path
+        .iter()
+        .filter_map(|node| graph.node_weight(*node))
+        .dedup()
+        .filter_map(|ent| q_road_chunks.get(*ent).ok().map(|seg| (*ent, seg)));
+
+    let mut world_path = vec![];
+    let mut segment_entities = vec![];
+
+    for (seg_entity, seg) in segments {
+        let flipped_seg = if seg.points.0 != prev_end {
+            RoadSegment {
+                points: (seg.points.1, seg.points.0),
+                layer: seg.layer,
+                one_way: seg.one_way,
+                class: seg.class,
+                arc: seg.arc,
+            }
+        } else {
+            seg.clone()
+        };
+
+        prev_end = flipped_seg.points.1;
+
+        world_path.push(flipped_seg);
+        segment_entities.push(seg_entity);
+    }
+
+    (world_path, segment_entities)
+}
+
 // This one just updates the text
 // we have based on the state of the game
 // (whether we're in simulator or not)
@@ -527,11 +754,15 @@ fn pixie_button_system(
     mut pixie_count: ResMut<PixieCount>,
     mut sim_state: ResMut<SimulationState>,
     mut road_state: ResMut<RoadDrawingState>,
+    mut explosion_log: ResMut<ExplosionLog>,
+    mut processor_states: ResMut<ProcessorStates>,
+    mut deliveries: ResMut<Deliveries>,
     pathfinding: Res<PathfindingState>,
     q_interaction: Query<&Interaction, (Changed<Interaction>, With<Button>, With<PixieButton>)>,
     q_emitters: Query<Entity, With<PixieEmitter>>,
     q_pixies: Query<Entity, With<Pixie>>,
-    mut q_indicator: Query<(&mut Visibility, &ChildOf), With<TerminusIssueIndicator>>,
+    q_terminuses: Query<&Terminus>,
+    mut q_indicator: Query<(&mut Visibility, &ChildOf, &TerminusIssueIndicator)>,
 ) {
     // do nothing while score dialog is shown
     if *sim_state == SimulationState::Finished {
@@ -551,8 +782,11 @@ fn pixie_button_system(
             *sim_state = SimulationState::NotStarted;
         } else {
             if !pathfinding.valid {
-                for (mut visibility, child_of) in q_indicator.iter_mut() {
-                    *visibility = if pathfinding.invalid_nodes.contains(&child_of.parent()) {
+                for (mut visibility, child_of, indicator) in q_indicator.iter_mut() {
+                    *visibility = if pathfinding
+                        .invalid_ports
+                        .contains(&(child_of.parent(), indicator.0))
+                    {
                         Visibility::Visible
                     } else {
                         Visibility::Hidden
@@ -562,40 +796,93 @@ fn pixie_button_system(
                 return;
             }
 
-            for (mut visible, _) in q_indicator.iter_mut() {
+            for (mut visible, _, _) in q_indicator.iter_mut() {
                 *visible = Visibility::Hidden;
             }
 
-            let duration = 0.4;
-            let total_pixies = 50;
+            // each flavor emitted by a terminus follows its own schedule. if it
+            // is collected by multiple terminuses, its pixies are dealt out to
+            // each of their emitters in turn.
+
+            let mut flavor_counts = HashMap::new();
+            let mut terminus_flavors: HashMap<Entity, Vec<PixieFlavor>> = HashMap::new();
+            for (flavor, start_entity, _, _) in pathfinding.paths.iter() {
+                *flavor_counts.entry((start_entity, flavor)).or_insert(0) += 1;
 
-            let mut counts = HashMap::new();
-            for (_, start_entity, _) in pathfinding.paths.iter() {
-                *counts.entry(start_entity).or_insert(0) += 1;
+                let flavors = terminus_flavors.entry(*start_entity).or_default();
+                if !flavors.contains(flavor) {
+                    flavors.push(*flavor);
+                }
+            }
+            for flavors in terminus_flavors.values_mut() {
+                flavors.sort_by_key(|f| (f.color, f.net));
             }
 
             let mut is = HashMap::new();
 
-            for (flavor, start_entity, world_path) in pathfinding.paths.iter() {
-                let i = is.entry(start_entity).or_insert(0);
+            for (flavor, start_entity, end_entity, world_paths) in pathfinding.paths.iter() {
+                let Ok(terminus) = q_terminuses.get(*start_entity) else {
+                    continue;
+                };
+
+                let schedule = terminus.schedule(flavor);
+
+                let i = is.entry((start_entity, flavor)).or_insert(0);
 
                 // unwrap: we just inserted these above
-                let count = counts.get(start_entity).unwrap();
-                let pixies = total_pixies / *count;
+                let stride = *flavor_counts.get(&(start_entity, flavor)).unwrap();
+                let flavors = terminus_flavors.get(start_entity).unwrap();
+
+                // processors start out empty, and are only given pixies to
+                // emit as they collect others.
+                if terminus.is_processor() {
+                    commands.spawn((
+                        PixieEmitter {
+                            flavor: *flavor,
+                            terminus: *start_entity,
+                            destination: *end_entity,
+                            paths: world_paths.clone(),
+                            next_path: 0,
+                            remaining: 0,
+                            schedule,
+                            offset: 0,
+                            stride: 1,
+                            emitted: 0,
+                            elapsed: 0.0,
+                        },
+                        DespawnOnExit(GameState::Playing),
+                    ));
+
+                    continue;
+                }
 
-                // if we have multiple pixies coming out of the same starting
-                // point, stagger their emitters evenly. this prevents some
-                // awkward bunching up at the start of the path.
+                // the number of pixies n in 0..count where n % stride == i
+                let pixies = if *i < schedule.count {
+                    (schedule.count - *i - 1) / stride + 1
+                } else {
+                    0
+                };
 
-                let mut timer = Timer::from_seconds(duration * *count as f32, TimerMode::Repeating);
-                timer.set_elapsed(Duration::from_secs_f32((*i + 1) as f32 * duration));
+                // if we have multiple flavors coming out of the same starting
+                // point, stagger them evenly. this prevents some awkward
+                // bunching up at the start of the path.
+
+                let flavor_index = flavors.iter().position(|f| f == flavor).unwrap_or(0);
+                let stagger = flavor_index as f32 * schedule.interval / flavors.len() as f32;
 
                 commands.spawn((
                     PixieEmitter {
                         flavor: *flavor,
-                        path: world_path.clone(),
+                        terminus: *start_entity,
+                        destination: *end_entity,
+                        paths: world_paths.clone(),
+                        next_path: 0,
                         remaining: pixies,
-                        timer,
+                        schedule,
+                        offset: *i,
+                        stride,
+                        emitted: 0,
+                        elapsed: -stagger,
                     },
                     DespawnOnExit(GameState::Playing),
                 ));
@@ -603,6 +890,10 @@ fn pixie_button_system(
                 *i += 1;
             }
 
+            explosion_log.0.clear();
+            processor_states.0.clear();
+            *deliveries = Deliveries::default();
+
             *sim_state = SimulationState::Running;
         }
 
@@ -621,6 +912,7 @@ fn reset_button_system(
     q_road_chunks: Query<Entity, With<RoadSegment>>,
     q_pixies: Query<Entity, With<Pixie>>,
     q_emitters: Query<Entity, With<PixieEmitter>>,
+    q_controls: Query<Entity, With<TrafficControl>>,
     q_terminuses: Query<Entity, With<Terminus>>,
     mut q_indicator: Query<&mut Visibility, With<TerminusIssueIndicator>>,
 ) {
@@ -634,6 +926,7 @@ fn reset_button_system(
             .iter()
             .chain(q_pixies.iter())
             .chain(q_emitters.iter())
+            .chain(q_controls.iter())
         {
             commands.entity(chunk).despawn();
         }
@@ -682,6 +975,28 @@ fn speed_button_system(
     }
 }
 
+// Switch between fixed and dynamic pixie routing
+fn routing_button_system(
+    q_interaction: Query<
+        (&Interaction, &Children),
+        (Changed<Interaction>, With<Button>, With<RoutingButton>),
+    >,
+    mut q_text: Query<&mut Text>,
+    mut simulation_settings: ResMut<SimulationSettings>,
+) {
+    for (_, children) in q_interaction
+        .iter()
+        .filter(|(i, _)| **i == Interaction::Pressed)
+    {
+        simulation_settings.routing = simulation_settings.routing.next();
+
+        let mut iter = q_text.iter_many_mut(children);
+        while let Some(mut text) = iter.fetch_next() {
+            text.0 = simulation_settings.routing.label();
+        }
+    }
+}
+
 fn snap_to_grid(position: Vec2, grid_size: f32) -> Vec2 {
     (position / grid_size).round() * grid_size
 }
@@ -737,6 +1052,11 @@ fn drawing_mode_change_system(
             road_state.drawing = false;
             road_state.segments = vec![];
         }
+        Tool::TrafficControl(_) => {
+            road_state.drawing = false;
+            road_state.segments = vec![];
+            ripping_state.reset();
+        }
     }
 }
 
@@ -744,6 +1064,9 @@ fn drawing_mode_change_system(
 // didn't dig too much
 // R -> for net ripping
 // Esc -> drop drawing state
+// Shift (held) -> draw one-way roads
+// C -> round corners into arcs
+// [ / ] -> shrink / grow rounded corners
 fn keyboard_system(
     keyboard_input: Res<ButtonInput<KeyCode>>,
     mut road_state: ResMut<RoadDrawingState>,
@@ -759,6 +1082,11 @@ fn keyboard_system(
         return;
     }
 
+    let one_way = keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    if road_state.one_way != one_way {
+        road_state.one_way = one_way;
+    }
+
     if keyboard_input.pressed(KeyCode::Digit1)
         || keyboard_input.pressed(KeyCode::Digit2)
         || keyboard_input.pressed(KeyCode::Digit3)
@@ -792,7 +1120,7 @@ fn keyboard_system(
             }
         }
     } else if keyboard_input.pressed(KeyCode::Escape) {
-        if matches!(selected_tool.0, Tool::NetRipping) {
+        if !matches!(selected_tool.0, Tool::LineDrawing) {
             selected_tool.0 = Tool::LineDrawing;
         } else {
             road_state.drawing = false;
@@ -808,6 +1136,12 @@ fn keyboard_system(
                 radio.selected = true;
             }
         }
+    } else if keyboard_input.just_pressed(KeyCode::KeyC) {
+        road_state.arcs = !road_state.arcs;
+    } else if keyboard_input.just_pressed(KeyCode::BracketLeft) {
+        road_state.arc_radius = road_state.arc_radius.saturating_sub(1).max(1);
+    } else if keyboard_input.just_pressed(KeyCode::BracketRight) {
+        road_state.arc_radius = (road_state.arc_radius + 1).min(RoadDrawingState::MAX_ARC_RADIUS);
     }
 }
 
@@ -817,23 +1151,39 @@ fn mouse_movement_system(
     mut cursor_moved_events: MessageReader<CursorMoved>,
     mut mouse: ResMut<MousePos>,
     mut mouse_snapped: ResMut<MouseSnappedPos>,
-    q_camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    handles: Res<Handles>,
+    levels: Res<Assets<Level>>,
+    selected_level: Res<SelectedLevel>,
+    q_camera: Query<(Ref<Camera>, Ref<GlobalTransform>), With<MainCamera>>,
 ) {
     let Ok((camera, camera_transform)) = q_camera.single() else {
         return;
     };
 
-    for event in cursor_moved_events.read() {
-        if let Ok(pos) = camera.viewport_to_world_2d(camera_transform, event.position) {
+    // the cursor may be over a different part of the world after the camera
+    // pans or zooms, even if it hasn't moved.
+    let mut window_positions = cursor_moved_events
+        .read()
+        .map(|event| event.position)
+        .collect::<Vec<_>>();
+    if window_positions.is_empty() && (camera.is_changed() || camera_transform.is_changed()) {
+        window_positions.push(mouse.window);
+    }
+
+    let bounds = selected_level_bounds(&handles, &levels, &selected_level);
+
+    for window_pos in window_positions {
+        if let Ok(pos) = camera.viewport_to_world_2d(&camera_transform, window_pos) {
             mouse.world = pos;
 
-            let new_snapped = snap_to_grid(mouse.world, GRID_SIZE);
+            // roads can't be drawn off the edge of the level
+            let new_snapped = bounds.clamp(snap_to_grid(mouse.world, GRID_SIZE));
             if mouse_snapped.bypass_change_detection().0 != new_snapped {
                 debug!("Cursor: {new_snapped}");
                 mouse_snapped.0 = new_snapped;
             }
 
-            mouse.window = event.position;
+            mouse.window = window_pos;
         }
     }
 }
@@ -861,11 +1211,11 @@ fn spawn_road_segment(
     graph: &mut RoadGraph,
     segment: RoadSegment,
 ) -> (Entity, NodeIndex, NodeIndex) {
-    let color = theme::FINISHED_ROAD[segment.layer as usize - 1];
+    let color = segment.class.color(segment.layer);
     let ent = commands
         .spawn((
-            ShapeBuilder::with(&shapes::Line(segment.points.0, segment.points.1))
-                .stroke((color, 2.0))
+            ShapeBuilder::with(&road_path(segment.points, segment.arc))
+                .stroke((color, segment.class.width()))
                 .build(),
             Transform::from_xyz(0.0, 0.0, layer::ROAD - segment.layer as f32),
             segment.clone(),
@@ -873,9 +1223,18 @@ fn spawn_road_segment(
         ))
         .with_children(|parent| {
             parent.spawn((
-                Collider::Segment(segment.points),
+                Collider::Segment(segment.points, segment.arc),
                 ColliderLayer(segment.layer),
             ));
+
+            if segment.one_way {
+                parent.spawn((
+                    ShapeBuilder::with(&one_way_arrow(segment.points, segment.arc))
+                        .stroke((color, 2.0))
+                        .build(),
+                    Transform::default(),
+                ));
+            }
         })
         .id();
 
@@ -885,7 +1244,7 @@ fn spawn_road_segment(
     graph.graph.add_edge(
         start_node,
         end_node,
-        (segment.points.0 - segment.points.1).length(),
+        segment_weight(segment.points, segment.arc, segment.layer, segment.class),
     );
     commands
         .entity(ent)
@@ -894,6 +1253,35 @@ fn spawn_road_segment(
     (ent, start_node, end_node)
 }
 
+/// Returns the line a road segment is drawn along, following its arc if it
+/// has one.
+fn road_path(points: (Vec2, Vec2), arc: Option<Vec2>) -> shapes::Polygon {
+    shapes::Polygon {
+        points: match arc {
+            Some(center) => arc_points(points, center),
+            None => vec![points.0, points.1],
+        },
+        closed: false,
+    }
+}
+
+/// Returns an arrow at the middle of a one-way road segment, pointing in the
+/// direction of travel.
+fn one_way_arrow(points: (Vec2, Vec2), arc: Option<Vec2>) -> shapes::Polygon {
+    let middle = midpoint(points, arc);
+    let direction = direction_at(points, arc, middle);
+    let normal = direction.perp();
+
+    shapes::Polygon {
+        points: vec![
+            middle - direction * 3.0 + normal * 5.0,
+            middle + direction * 4.0,
+            middle - direction * 3.0 - normal * 5.0,
+        ],
+        closed: false,
+    }
+}
+
 fn spawn_obstacle(commands: &mut Commands, obstacle: &Obstacle) {
     match obstacle {
         Obstacle::Rect(top_left, bottom_right) => {
@@ -913,34 +1301,9 @@ fn spawn_obstacle(commands: &mut Commands, obstacle: &Obstacle) {
                     obstacle.clone(),
                 ))
                 .with_children(|parent| {
-                    parent.spawn((
-                        Collider::Segment((
-                            Vec2::new(top_left.x, top_left.y),
-                            Vec2::new(bottom_right.x, top_left.y),
-                        )),
-                        ColliderLayer(0),
-                    ));
-                    parent.spawn((
-                        Collider::Segment((
-                            Vec2::new(bottom_right.x, top_left.y),
-                            Vec2::new(bottom_right.x, bottom_right.y),
-                        )),
-                        ColliderLayer(0),
-                    ));
-                    parent.spawn((
-                        Collider::Segment((
-                            Vec2::new(bottom_right.x, bottom_right.y),
-                            Vec2::new(top_left.x, bottom_right.y),
-                        )),
-                        ColliderLayer(0),
-                    ));
-                    parent.spawn((
-                        Collider::Segment((
-                            Vec2::new(top_left.x, bottom_right.y),
-                            Vec2::new(top_left.x, top_left.y),
-                        )),
-                        ColliderLayer(0),
-                    ));
+                    for edge in obstacle.edges() {
+                        parent.spawn((Collider::Segment(edge, None), ColliderLayer(0)));
+                    }
                 });
         }
     }
@@ -963,6 +1326,7 @@ fn spawn_name(
         TextColor(theme::LEVEL_NAME.into()),
         Anchor::TOP_LEFT,
         Transform::from_translation((name_position + Vec2::new(8., -8.)).extend(layer::GRID)),
+        LevelName,
         DespawnOnExit(GameState::Playing),
     ));
 }
@@ -976,15 +1340,29 @@ fn spawn_terminus(
     let label_offset = 22.0;
     let label_spacing = 22.0;
 
+    let shape = match terminus.kind {
+        TerminusKind::Normal => ShapeBuilder::with(&shapes::Circle {
+            radius: 5.5,
+            ..default()
+        }),
+        TerminusKind::Combiner => ShapeBuilder::with(&shapes::RegularPolygon {
+            sides: 4,
+            feature: shapes::RegularPolygonFeature::Radius(7.5),
+            ..default()
+        }),
+        TerminusKind::Splitter => ShapeBuilder::with(&shapes::RegularPolygon {
+            sides: 3,
+            feature: shapes::RegularPolygonFeature::Radius(8.5),
+            ..default()
+        }),
+    };
+
     let ent = commands
         .spawn((
-            ShapeBuilder::with(&shapes::Circle {
-                radius: 5.5,
-                ..default()
-            })
-            .fill(theme::BACKGROUND)
-            .stroke((theme::FINISHED_ROAD[0], 2.0))
-            .build(),
+            shape
+                .fill(theme::BACKGROUND)
+                .stroke((theme::FINISHED_ROAD[0], 2.0))
+                .build(),
             Transform::from_translation(terminus.point.extend(layer::TERMINUS)),
             terminus.clone(),
             DespawnOnExit(GameState::Playing),
@@ -992,15 +1370,36 @@ fn spawn_terminus(
         .with_children(|parent| {
             parent.spawn((Collider::Point(terminus.point), ColliderLayer(1)));
 
-            let mut i = 0;
+            if !terminus.collects.is_empty() && !terminus.is_processor() {
+                spawn_delivery_counter(
+                    parent,
+                    handles.fonts[0].clone(),
+                    Vec2::new(0.0, label_offset),
+                );
+            }
+
+            let mut emits = terminus.emits.iter().copied().collect::<Vec<_>>();
+            emits.sort_by_key(|f| (f.color, f.net));
+            let mut collects = terminus.collects.iter().copied().collect::<Vec<_>>();
+            collects.sort_by_key(|f| (f.color, f.net));
+
+            let ports = emits
+                .into_iter()
+                .map(TerminusPort::Emits)
+                .chain(collects.into_iter().map(TerminusPort::Collects));
 
-            for flavor in terminus.emits.iter() {
+            for (i, port) in ports.enumerate() {
                 let label_pos = Vec2::new(0.0, -label_offset + -(i as f32) * label_spacing);
 
+                let (flavor, direction) = match port {
+                    TerminusPort::Emits(flavor) => (flavor, "OUT"),
+                    TerminusPort::Collects(flavor) => (flavor, "IN"),
+                };
+
                 let label = if flavor.net > 0 {
-                    format!("OUT.{}", flavor.net + 1)
+                    format!("{}.{}", direction, flavor.net + 1)
                 } else {
-                    "OUT".to_string()
+                    direction.to_string()
                 };
 
                 parent.spawn((
@@ -1015,47 +1414,24 @@ fn spawn_terminus(
                     Transform::from_translation(label_pos.extend(layer::TERMINUS)),
                 ));
 
-                i += 1;
-            }
-
-            for flavor in terminus.collects.iter() {
-                let label_pos = Vec2::new(0.0, -label_offset + -(i as f32) * label_spacing);
-
-                let label = if flavor.net > 0 {
-                    format!("IN.{}", flavor.net + 1)
-                } else {
-                    "IN".to_string()
-                };
-
                 parent.spawn((
-                    Text2d::new(label),
-                    TextFont {
-                        font: handles.fonts[0].clone(),
-                        font_size: 25.0,
+                    ShapeBuilder::with(&shapes::Circle {
+                        radius: 5.5,
                         ..default()
-                    },
-                    TextColor(theme::PIXIE[flavor.color as usize].into()),
-                    TextLayout::new_with_justify(Justify::Center),
-                    Transform::from_translation(label_pos.extend(layer::TERMINUS)),
+                    })
+                    .fill(bevy::color::palettes::css::RED)
+                    .build(),
+                    Transform::from_xyz(-30.0, label_pos.y, layer::TERMINUS),
+                    Visibility::Hidden,
+                    TerminusIssueIndicator(port),
                 ));
 
-                i += 1;
+                if let TerminusPort::Collects(flavor) = port {
+                    if terminus.quotas.contains_key(&flavor) {
+                        spawn_progress_bar(parent, flavor, Vec2::new(24.0, label_pos.y));
+                    }
+                }
             }
-
-            // TODO above code supports multiple emitters/collectors, but below
-            // assumes a single emitter.
-
-            parent.spawn((
-                ShapeBuilder::with(&shapes::Circle {
-                    radius: 5.5,
-                    ..default()
-                })
-                .fill(bevy::color::palettes::css::RED)
-                .build(),
-                Transform::from_xyz(-30.0, -label_offset, layer::TERMINUS),
-                Visibility::Hidden,
-                TerminusIssueIndicator,
-            ));
         })
         .id();
 
@@ -1066,12 +1442,40 @@ fn spawn_terminus(
     (ent, node)
 }
 
+/// Returns the cost of a road segment, before it is divided by the grid size.
+fn segment_cost(points: (Vec2, Vec2), arc: Option<Vec2>, layer: u32, class: RoadClass) -> f32 {
+    let multiplier = if layer == 1 {
+        LAYER_TWO_MULTIPLIER
+    } else if layer == 2 {
+        LAYER_THREE_MULTIPLIER
+    } else {
+        1.0
+    };
+
+    let arc_multiplier = if arc.is_some() { ARC_MULTIPLIER } else { 1.0 };
+
+    segment_length(points, arc) * multiplier * arc_multiplier * class.cost_multiplier()
+}
+
+/// Returns the cost of building all of a solution's roads and traffic controls.
+fn solution_cost<'a>(
+    segments: impl IntoIterator<Item = &'a RoadSegment>,
+    controls: impl IntoIterator<Item = &'a TrafficControl>,
+) -> f32 {
+    let roads = segments
+        .into_iter()
+        .map(|s| segment_cost(s.points, s.arc, s.layer, s.class))
+        .sum::<f32>();
+
+    roads / GRID_SIZE + controls.into_iter().map(|c| c.kind.cost()).sum::<f32>()
+}
+
 fn update_cost_system(
     graph: Res<RoadGraph>,
     line_draw: Res<RoadDrawingState>,
     mut r_cost: ResMut<Cost>,
-    q_segments: Query<(&RoadSegment, &Children)>,
-    q_colliders: Query<&ColliderLayer>,
+    q_segments: Query<&RoadSegment>,
+    q_controls: Query<&TrafficControl>,
     mut q_cost: Query<Entity, With<CostText>>,
     mut writer: TextUiWriter,
 ) {
@@ -1079,44 +1483,15 @@ fn update_cost_system(
         return;
     }
 
-    let mut cost = 0.0;
-
-    for (segment, children) in q_segments.iter() {
-        let Some(child) = children.first() else {
-            continue;
-        };
-
-        let Ok(layer) = q_colliders.get(*child) else {
-            continue;
-        };
-
-        let multiplier = if layer.0 == 1 {
-            LAYER_TWO_MULTIPLIER
-        } else if layer.0 == 2 {
-            LAYER_THREE_MULTIPLIER
-        } else {
-            1.0
-        };
-
-        cost += (segment.points.0 - segment.points.1).length() * multiplier;
-    }
-
-    cost /= GRID_SIZE;
+    let cost = solution_cost(&q_segments, &q_controls);
     let cost_round = cost.ceil();
 
     r_cost.0 = cost as u32;
 
     let mut potential_cost = 0.0;
     if line_draw.valid {
-        for segment in line_draw.segments.iter() {
-            let multiplier = if line_draw.layer == 1 {
-                LAYER_TWO_MULTIPLIER
-            } else if line_draw.layer == 2 {
-                LAYER_THREE_MULTIPLIER
-            } else {
-                1.0
-            };
-            potential_cost += (segment.0 - segment.1).length() * multiplier;
+        for (segment, arc) in line_draw.planned_segments() {
+            potential_cost += segment_cost(segment, arc, line_draw.layer, line_draw.class);
         }
     }
 
@@ -1140,8 +1515,14 @@ fn update_score_system(
     sim_steps: Res<SimulationSteps>,
     mut score: ResMut<Score>,
     mut best_scores: ResMut<BestScores>,
+    mut best_solutions: ResMut<BestSolutions>,
+    mut quotas: ResMut<QuotasMet>,
     selected_level: Res<SelectedLevel>,
     cost: Res<Cost>,
+    deliveries: Res<Deliveries>,
+    q_terminuses: Query<(Entity, &Terminus)>,
+    q_segments: Query<&RoadSegment>,
+    q_controls: Query<&TrafficControl>,
 ) {
     if !sim_state.is_changed() {
         return;
@@ -1157,13 +1538,29 @@ fn update_score_system(
 
     score.0 = Some(val);
 
-    if let Some(best) = best_scores.0.get_mut(&selected_level.0) {
-        if *best < val {
-            *best = val;
-        }
-    } else {
-        best_scores.0.insert(selected_level.0, val);
+    quotas.0 = quotas_met(q_terminuses.iter(), &deliveries);
+
+    // a run that didn't deliver enough pixies doesn't count
+    if !quotas.0 {
+        return;
     }
+
+    if best_scores
+        .0
+        .get(&selected_level.0)
+        .is_some_and(|best| *best >= val)
+    {
+        return;
+    }
+
+    best_scores.0.insert(selected_level.0, val);
+    best_solutions.0.insert(
+        selected_level.0,
+        Solution {
+            segments: q_segments.iter().cloned().collect(),
+            controls: q_controls.iter().cloned().collect(),
+        },
+    );
 }
 
 fn update_score_text_system(
@@ -1199,6 +1596,7 @@ fn update_elapsed_text_system(
 
 fn save_solution_system(
     query: Query<&RoadSegment>,
+    q_controls: Query<&TrafficControl>,
     graph: Res<RoadGraph>,
     level: Res<SelectedLevel>,
     mut solutions: ResMut<Solutions>,
@@ -1207,12 +1605,21 @@ fn save_solution_system(
         return;
     }
 
-    // TODO this saves the prefs unnecessarily when
-    // the graph is modified after a particular level
-    // is loaded.
-
     let segments = query.iter().cloned().collect();
-    solutions.0.insert(level.0, Solution { segments });
+    let controls = q_controls.iter().cloned().collect();
+    let solution = Solution { segments, controls };
+
+    // the graph is also modified when a level is loaded, which shouldn't
+    // cause the prefs to be saved.
+    if solutions
+        .0
+        .get(&level.0)
+        .is_some_and(|s| s.matches(&solution))
+    {
+        return;
+    }
+
+    solutions.0.insert(level.0, solution);
 }
 
 fn reset_game(mut commands: Commands, mut graph: ResMut<RoadGraph>) {
@@ -1224,9 +1631,34 @@ fn reset_game(mut commands: Commands, mut graph: ResMut<RoadGraph>) {
     commands.insert_resource(NetRippingState::default());
     commands.insert_resource(SimulationState::default());
     commands.insert_resource(PathfindingState::default());
+    commands.insert_resource(ExplosionLog::default());
+    commands.insert_resource(Deliveries::default());
+    commands.insert_resource(QuotasMet::default());
     graph.graph.clear();
 }
 
+/// Whether the cursor is over the play area rather than some part of the UI.
+fn cursor_over_play_area(q_play_area: &Query<&Interaction, With<PlayAreaNode>>) -> bool {
+    q_play_area
+        .single()
+        .is_ok_and(|interaction| *interaction != Interaction::None)
+}
+
+/// The bounds of the selected level, or the default bounds if it hasn't
+/// loaded.
+fn selected_level_bounds(
+    handles: &Handles,
+    levels: &Assets<Level>,
+    selected_level: &SelectedLevel,
+) -> LevelBounds {
+    handles
+        .levels
+        .get(selected_level.0 as usize - 1)
+        .and_then(|h| levels.get(h))
+        .map(|level| level.bounds)
+        .unwrap_or_default()
+}
+
 // Main system that actually shows the level
 // A lot of calls from within this file.
 fn spawn_level(
@@ -1237,48 +1669,52 @@ fn spawn_level(
     handles: Res<Handles>,
     solutions: Res<Solutions>,
 ) {
-    // Build arena
-
-    for x in ((-25 * (GRID_SIZE as i32))..=25 * (GRID_SIZE as i32)).step_by(GRID_SIZE as usize) {
-        for y in (-15 * (GRID_SIZE as i32)..=15 * (GRID_SIZE as i32)).step_by(GRID_SIZE as usize) {
-            commands.spawn((
-                ShapeBuilder::with(&shapes::Circle {
-                    radius: 2.5,
-                    ..default()
-                })
-                .fill(theme::GRID)
-                .build(),
-                Transform::from_xyz(x as f32, y as f32, layer::GRID),
-                GridPoint,
-                DespawnOnExit(GameState::Playing),
-            ));
-        }
-    }
-
     // Build level
 
-    let mut connections: Vec<(Vec2, NodeIndex)> = vec![];
-
     let level = levels
         .get(&handles.levels[selected_level.0 as usize - 1])
         .unwrap();
 
+    build_level(
+        &mut commands,
+        &mut graph,
+        &handles,
+        selected_level.0,
+        level,
+        solutions.0.get(&selected_level.0),
+    );
+
+    // Build UI
+}
+
+/// Spawns everything that comes from the level file, and the player's solution
+/// to it, connecting them all together in the road graph.
+fn build_level(
+    commands: &mut Commands,
+    graph: &mut ResMut<RoadGraph>,
+    handles: &Res<Handles>,
+    number: u32,
+    level: &Level,
+    solution: Option<&Solution>,
+) {
+    let mut connections: Vec<(Vec2, NodeIndex)> = vec![];
+
     for t in level.terminuses.iter() {
-        let (_, node) = spawn_terminus(&mut commands, &mut graph, &handles, t);
+        let (_, node) = spawn_terminus(commands, graph, handles, t);
         connections.push((t.point, node));
     }
 
     for o in level.obstacles.iter() {
-        spawn_obstacle(&mut commands, o);
+        spawn_obstacle(commands, o);
     }
 
-    spawn_name(
-        &mut commands,
-        selected_level.0,
-        &handles,
-        &level.name,
-        &level.name_position,
-    );
+    spawn_name(commands, number, handles, &level.name, &level.name_position);
+
+    commands.spawn((
+        Grid(level.bounds),
+        Transform::from_xyz(0.0, 0.0, layer::GRID),
+        DespawnOnExit(GameState::Playing),
+    ));
 
     println!(
         "{:?}",
@@ -1287,9 +1723,9 @@ fn spawn_level(
 
     // Spawn previous solution to level
 
-    if let Some(solution) = solutions.0.get(&selected_level.0) {
+    if let Some(solution) = solution {
         for seg in solution.segments.iter() {
-            let (_, node_a, node_b) = spawn_road_segment(&mut commands, &mut graph, seg.clone());
+            let (_, node_a, node_b) = spawn_road_segment(commands, graph, seg.clone());
 
             for (point, node) in connections.iter() {
                 if *point == seg.points.0 {
@@ -1304,9 +1740,11 @@ fn spawn_level(
             connections.push((seg.points.0, node_a));
             connections.push((seg.points.1, node_b));
         }
-    }
 
-    // Build UI
+        for control in solution.controls.iter() {
+            spawn_traffic_control(commands, control.clone());
+        }
+    }
 }
 
 fn spawn_music(mut commands: Commands, handles: Res<Handles>, volume: Res<MusicVolume>) {
@@ -1333,6 +1771,7 @@ fn spawn_game_ui(
         .unwrap();
 
     let mut tool_button_ids = vec![];
+    let mut class_button_ids = vec![];
 
     commands
         .spawn((
@@ -1402,6 +1841,22 @@ fn spawn_game_ui(
                                 tool_button_ids.push(id);
                             }
 
+                            // Road class buttons
+
+                            for class in RoadClass::ALL {
+                                let id = parent
+                                    .spawn((
+                                        button(class.label(), handles.fonts[0].clone(), 70.0),
+                                        RoadClassButton(class),
+                                        ToolButton,
+                                        RadioButton {
+                                            selected: class == RoadClass::default(),
+                                        },
+                                    ))
+                                    .id();
+                                class_button_ids.push(id);
+                            }
+
                             let net_ripping_id = parent
                                 .spawn((
                                     button("R", handles.fonts[0].clone(), 50.0),
@@ -1412,6 +1867,21 @@ fn spawn_game_ui(
                                 .id();
 
                             tool_button_ids.push(net_ripping_id);
+
+                            for (label, kind) in [
+                                ("S", TrafficControlKind::Signal),
+                                ("Y", TrafficControlKind::Yield),
+                            ] {
+                                let id = parent
+                                    .spawn((
+                                        button(label, handles.fonts[0].clone(), 50.0),
+                                        TrafficControlButton(kind),
+                                        ToolButton,
+                                        RadioButton { selected: false },
+                                    ))
+                                    .id();
+                                tool_button_ids.push(id);
+                            }
                         });
 
                     // Container for score, etc
@@ -1517,6 +1987,11 @@ fn spawn_game_ui(
                             ..default()
                         })
                         .with_children(|parent| {
+                            parent.spawn((
+                                button("SLOTS", handles.fonts[0].clone(), 90.0),
+                                SlotsButton,
+                            ));
+
                             parent
                                 .spawn((
                                     Button,
@@ -1550,6 +2025,15 @@ fn spawn_game_ui(
                                 SpeedButton,
                             ));
 
+                            parent.spawn((
+                                button(
+                                    simulation_settings.routing.label(),
+                                    handles.fonts[0].clone(),
+                                    70.0,
+                                ),
+                                RoutingButton,
+                            ));
+
                             parent.spawn((
                                 button("RELEASE THE PIXIES", handles.fonts[0].clone(), 250.0),
                                 PixieButton,
@@ -1567,6 +2051,8 @@ fn spawn_game_ui(
                     align_items: AlignItems::Center,
                     ..default()
                 },
+                // lets clicks on the board be told apart from clicks on the UI
+                Interaction::default(),
                 PlayAreaNode,
             ));
         });
@@ -1582,6 +2068,18 @@ fn spawn_game_ui(
             .entity(*id)
             .insert(RadioButtonGroupRelation(tool_group_id));
     }
+
+    let class_group_id = commands
+        .spawn(RadioButtonGroup {
+            entities: class_button_ids.clone(),
+        })
+        .id();
+
+    for id in class_button_ids.iter() {
+        commands
+            .entity(*id)
+            .insert(RadioButtonGroupRelation(class_group_id));
+    }
 }
 
 fn set_music_volume_system(
@@ -8,8 +8,8 @@ use petgraph::{
 };
 
 use crate::{
-    collision::{point_segment_collision, PointCollision},
-    layer,
+    collision::{point_road_collision, PointCollision},
+    layer, road_path,
     sim::SimulationState,
     Collider, ColliderLayer, DrawingInteraction, DrawingMouseMovement, GameState, MouseSnappedPos,
     RoadGraph, RoadSegment, SegmentGraphNodes, SelectedTool, Tool,
@@ -34,7 +34,8 @@ impl Plugin for NetRippingPlugin {
 pub struct NetRippingState {
     pub entities: EntityHashSet,
     pub nodes: Vec<NodeIndex>,
-    pub segments: Vec<(Vec2, Vec2)>,
+    /// The segments that would be removed, along with the centers of any arcs.
+    pub segments: Vec<((Vec2, Vec2), Option<Vec2>)>,
 }
 
 impl NetRippingState {
@@ -76,7 +77,7 @@ fn net_ripping_mouse_movement_system(
     let Some((entity, _layer)) = q_colliders
         .iter()
         .filter_map(|(child_of, collider, layer)| {
-            let Collider::Segment(segment_points) = collider else {
+            let Collider::Segment(segment_points, arc) = collider else {
                 return None;
             };
 
@@ -85,7 +86,7 @@ fn net_ripping_mouse_movement_system(
             }
 
             if matches!(
-                point_segment_collision(mouse_snapped.0, segment_points.0, segment_points.1),
+                point_road_collision(mouse_snapped.0, *segment_points, *arc),
                 PointCollision::None
             ) {
                 return None;
@@ -109,7 +110,7 @@ fn net_ripping_mouse_movement_system(
         if let Some(net_entity) = graph.graph.node_weight(index) {
             if let Ok(seg) = q_road_segments.get(*net_entity) {
                 if ripping_state.entities.insert(*net_entity) {
-                    ripping_state.segments.push(seg.points);
+                    ripping_state.segments.push((seg.points, seg.arc));
                 }
 
                 ripping_state.nodes.push(index);
@@ -159,9 +160,9 @@ fn draw_net_ripping_system(
         commands.entity(ent).despawn();
     }
 
-    for (a, b) in ripping_state.segments.iter() {
+    for (points, arc) in ripping_state.segments.iter() {
         commands.spawn((
-            ShapeBuilder::with(&shapes::Line(*a, *b))
+            ShapeBuilder::with(&road_path(*points, *arc))
                 .stroke((bevy::color::palettes::css::RED, 2.0))
                 .build(),
             Transform::from_xyz(0.0, 0.0, layer::ROAD_OVERLAY),
@@ -0,0 +1,281 @@
+//! Tunable numbers that determine how pixies move and interact, loaded from
+//! `pixie.physics.ron` and optionally overridden by each level.
+
+use bevy::{prelude::*, reflect::TypePath};
+use serde::Deserialize;
+
+use crate::{level::Level, pixie::PIXIE_RADIUS, GameState, Handles, SelectedLevel, GRID_SIZE};
+
+pub struct PixiePhysicsPlugin;
+impl Plugin for PixiePhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PixiePhysics>();
+        app.add_systems(OnEnter(GameState::Playing), apply_level_physics);
+    }
+}
+
+/// The physics in effect for the current level. Values missing from
+/// `pixie.physics.ron` are taken from [`PixiePhysics::default`].
+///
+/// ```ron
+/// PixiePhysics(
+///     max_speed: 60.0,
+///     acceleration: 50.0,
+/// )
+/// ```
+#[derive(Resource, Asset, TypePath, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct PixiePhysics {
+    /// How far ahead along its path a pixie looks for other pixies.
+    pub vision_distance: f32,
+    /// A pixie slows down when it is this close to the pixie in front of it.
+    pub braking_distance: f32,
+    /// Pixies of different colors explode when they are this close together.
+    pub explosion_distance: f32,
+    /// The slowest a pixie will go when following another pixie.
+    pub min_speed: f32,
+    /// How much slower than the pixie in front a braking pixie will go.
+    pub braking_speed_difference: f32,
+    /// The fastest a pixie may travel along a standard road.
+    pub max_speed: f32,
+    /// A pixie's maximum speed when traveling through a 45 degree angle.
+    pub max_speed_45: f32,
+    /// A pixie's maximum speed when traveling through a 90 degree angle.
+    pub max_speed_90: f32,
+    /// A pixie's maximum speed when chasing a pixie of another color.
+    pub max_speed_attracted: f32,
+    /// How well pixies hold on around arcs. Wider arcs can be taken faster.
+    pub arc_grip: f32,
+    pub acceleration: f32,
+    pub deceleration: f32,
+    /// Pixies start slowing down for a corner when they are this close to it.
+    pub corner_debuff_activation_distance: f32,
+    /// Pixies accelerate slowly for this distance after a sharp corner.
+    pub corner_debuff_distance: f32,
+    /// A pixie's acceleration is divided by this after a 45 degree angle.
+    pub corner_debuff_45: f32,
+    /// A pixie's acceleration is divided by this after a 90 degree angle.
+    pub corner_debuff_90: f32,
+}
+impl Default for PixiePhysics {
+    fn default() -> Self {
+        Self {
+            vision_distance: PIXIE_RADIUS * 4.0,
+            braking_distance: PIXIE_RADIUS * 3.0,
+            explosion_distance: PIXIE_RADIUS * 0.5,
+            min_speed: 10.0,
+            braking_speed_difference: 10.0,
+            max_speed: 60.0,
+            max_speed_45: 10.0,
+            max_speed_90: 30.0,
+            max_speed_attracted: 120.0,
+            arc_grip: 27.0,
+            acceleration: 50.0,
+            deceleration: 50.0,
+            corner_debuff_activation_distance: GRID_SIZE,
+            corner_debuff_distance: 24.0,
+            corner_debuff_45: 8.0,
+            corner_debuff_90: 6.0,
+        }
+    }
+}
+impl PixiePhysics {
+    /// Returns these physics with any values set in `overrides` replaced.
+    pub fn with_overrides(&self, overrides: &PixiePhysicsOverrides) -> Self {
+        let o = overrides;
+
+        Self {
+            vision_distance: o.vision_distance.unwrap_or(self.vision_distance),
+            braking_distance: o.braking_distance.unwrap_or(self.braking_distance),
+            explosion_distance: o.explosion_distance.unwrap_or(self.explosion_distance),
+            min_speed: o.min_speed.unwrap_or(self.min_speed),
+            braking_speed_difference: o
+                .braking_speed_difference
+                .unwrap_or(self.braking_speed_difference),
+            max_speed: o.max_speed.unwrap_or(self.max_speed),
+            max_speed_45: o.max_speed_45.unwrap_or(self.max_speed_45),
+            max_speed_90: o.max_speed_90.unwrap_or(self.max_speed_90),
+            max_speed_attracted: o.max_speed_attracted.unwrap_or(self.max_speed_attracted),
+            arc_grip: o.arc_grip.unwrap_or(self.arc_grip),
+            acceleration: o.acceleration.unwrap_or(self.acceleration),
+            deceleration: o.deceleration.unwrap_or(self.deceleration),
+            corner_debuff_activation_distance: o
+                .corner_debuff_activation_distance
+                .unwrap_or(self.corner_debuff_activation_distance),
+            corner_debuff_distance: o
+                .corner_debuff_distance
+                .unwrap_or(self.corner_debuff_distance),
+            corner_debuff_45: o.corner_debuff_45.unwrap_or(self.corner_debuff_45),
+            corner_debuff_90: o.corner_debuff_90.unwrap_or(self.corner_debuff_90),
+        }
+    }
+
+    /// The fastest a pixie may travel around an arc of the given radius.
+    pub fn arc_max_speed(&self, radius: f32) -> f32 {
+        (self.arc_grip * radius).sqrt()
+    }
+}
+
+/// Values that a level uses in place of those in `pixie.physics.ron`.
+///
+/// ```ron
+/// physics: (
+///     acceleration: Some(10.0),
+///     deceleration: Some(5.0),
+/// ),
+/// ```
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct PixiePhysicsOverrides {
+    pub vision_distance: Option<f32>,
+    pub braking_distance: Option<f32>,
+    pub explosion_distance: Option<f32>,
+    pub min_speed: Option<f32>,
+    pub braking_speed_difference: Option<f32>,
+    pub max_speed: Option<f32>,
+    pub max_speed_45: Option<f32>,
+    pub max_speed_90: Option<f32>,
+    pub max_speed_attracted: Option<f32>,
+    pub arc_grip: Option<f32>,
+    pub acceleration: Option<f32>,
+    pub deceleration: Option<f32>,
+    pub corner_debuff_activation_distance: Option<f32>,
+    pub corner_debuff_distance: Option<f32>,
+    pub corner_debuff_45: Option<f32>,
+    pub corner_debuff_90: Option<f32>,
+}
+impl PixiePhysicsOverrides {
+    /// Returns a description of each override that would break the
+    /// simulation.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = vec![];
+
+        let values = [
+            ("vision_distance", self.vision_distance),
+            ("braking_distance", self.braking_distance),
+            ("explosion_distance", self.explosion_distance),
+            ("min_speed", self.min_speed),
+            ("braking_speed_difference", self.braking_speed_difference),
+            ("max_speed", self.max_speed),
+            ("max_speed_45", self.max_speed_45),
+            ("max_speed_90", self.max_speed_90),
+            ("max_speed_attracted", self.max_speed_attracted),
+            ("arc_grip", self.arc_grip),
+            ("acceleration", self.acceleration),
+            ("deceleration", self.deceleration),
+            (
+                "corner_debuff_activation_distance",
+                self.corner_debuff_activation_distance,
+            ),
+            ("corner_debuff_distance", self.corner_debuff_distance),
+        ];
+        for (name, value) in values {
+            if let Some(value) = value.filter(|v| *v < 0.0) {
+                problems.push(format!(
+                    "Physics override {name} is {value}, but can't be negative"
+                ));
+            }
+        }
+
+        // pixies' acceleration is divided by these after a corner
+        let debuffs = [
+            ("corner_debuff_45", self.corner_debuff_45),
+            ("corner_debuff_90", self.corner_debuff_90),
+        ];
+        for (name, value) in debuffs {
+            if let Some(value) = value.filter(|v| *v <= 0.0) {
+                problems.push(format!(
+                    "Physics override {name} is {value}, but must be above zero"
+                ));
+            }
+        }
+
+        problems
+    }
+}
+
+fn apply_level_physics(
+    mut physics: ResMut<PixiePhysics>,
+    assets: Res<Assets<PixiePhysics>>,
+    levels: Res<Assets<Level>>,
+    selected_level: Res<SelectedLevel>,
+    handles: Res<Handles>,
+) {
+    let level = handles
+        .levels
+        .get(selected_level.0 as usize - 1)
+        .and_then(|handle| levels.get(handle));
+
+    *physics = level_physics(&assets, &handles, level);
+}
+
+/// Returns the physics from `pixie.physics.ron`, with any values that `level`
+/// overrides replaced.
+pub fn level_physics(
+    assets: &Assets<PixiePhysics>,
+    handles: &Handles,
+    level: Option<&Level>,
+) -> PixiePhysics {
+    let base = assets.get(&handles.physics).cloned().unwrap_or_default();
+
+    match level {
+        Some(level) => base.with_overrides(&level.physics),
+        None => base,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::asset::ron;
+
+    use super::*;
+
+    #[test]
+    fn overrides_replace_only_set_values() {
+        let base = PixiePhysics::default();
+        let overrides = PixiePhysicsOverrides {
+            max_speed: Some(90.0),
+            deceleration: Some(5.0),
+            ..default()
+        };
+
+        let physics = base.with_overrides(&overrides);
+
+        assert_eq!(physics.max_speed, 90.0);
+        assert_eq!(physics.deceleration, 5.0);
+        assert_eq!(physics.acceleration, base.acceleration);
+        assert_eq!(physics.vision_distance, base.vision_distance);
+    }
+
+    #[test]
+    fn override_problems() {
+        assert!(PixiePhysicsOverrides::default().problems().is_empty());
+
+        let overrides = PixiePhysicsOverrides {
+            max_speed: Some(-1.0),
+            braking_distance: Some(0.0),
+            corner_debuff_45: Some(0.0),
+            corner_debuff_90: Some(2.0),
+            ..default()
+        };
+        assert_eq!(
+            overrides.problems(),
+            [
+                "Physics override max_speed is -1, but can't be negative",
+                "Physics override corner_debuff_45 is 0, but must be above zero",
+            ]
+        );
+    }
+
+    #[test]
+    fn asset_parses() {
+        assert!(ron::from_str::<PixiePhysics>(include_str!("../assets/pixie.physics.ron")).is_ok());
+    }
+
+    #[test]
+    fn no_overrides() {
+        let base = PixiePhysics::default();
+
+        assert_eq!(base.with_overrides(&PixiePhysicsOverrides::default()), base);
+    }
+}
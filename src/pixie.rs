@@ -1,12 +1,18 @@
 use rstar::{RTree, RTreeObject, AABB};
-use std::time::Duration;
 
 use crate::{
+    deliveries::Deliveries,
+    hotspots::{Explosion, ExplosionLog},
     layer,
-    lines::corner_angle,
-    lines::{distance_on_path, travel, traveled_segments},
-    sim::SIMULATION_TIMESTEP,
-    theme, GameState, PixieCount, RoadSegment, GRID_SIZE,
+    level::{EmitSchedule, Terminus},
+    lines::{
+        distance_on_path, distance_remaining, joining_angle, path_length, travel, traveled_segments,
+    },
+    physics::PixiePhysics,
+    processors::{feed, ProcessorStates},
+    routes::{faster_route, junction_key, segment_key, SegmentCongestion},
+    sim::{RoutingMode, SimulationSettings, SimulationSteps, SIMULATION_TIMESTEP},
+    theme, GameState, PathfindingState, PixieCount, RoadSegment,
 };
 
 use bevy::{
@@ -19,22 +25,12 @@ use rand::Rng;
 use serde::Deserialize;
 
 pub const PIXIE_RADIUS: f32 = 6.0;
-pub const PIXIE_VISION_DISTANCE: f32 = PIXIE_RADIUS * 4.0;
-pub const PIXIE_BRAKING_DISTANCE: f32 = PIXIE_RADIUS * 3.0;
-pub const PIXIE_EXPLOSION_DISTANCE: f32 = PIXIE_RADIUS * 0.5;
-pub const PIXIE_MIN_SPEED: f32 = 10.0;
-pub const PIXIE_MAX_SPEED: f32 = 60.0;
-/// A pixie's maximum speed when traveling through a 45 degree angle.
-pub const PIXIE_MAX_SPEED_45: f32 = 10.0;
-/// A pixie's maximum speed when traveling through a 90 degree angle.
-pub const PIXIE_MAX_SPEED_90: f32 = 30.0;
-pub const PIXIE_MAX_SPEED_ATTRACTED: f32 = 120.0;
-pub const CORNER_DEBUFF_ACTIVATION_DISTANCE: f32 = GRID_SIZE;
-pub const CORNER_DEBUFF_DISTANCE: f32 = 24.0;
 
 pub struct PixiePlugin;
 impl Plugin for PixiePlugin {
     fn build(&self, app: &mut App) {
+        app.init_resource::<SegmentCongestion>();
+        app.init_resource::<PixieArrivals>();
         app.add_systems(
             Update,
             move_fragments_system.run_if(in_state(GameState::Playing)),
@@ -70,23 +66,31 @@ pub struct Pixie {
     pub driving_state: DrivingState,
     pub corner_debuff_distance_remaining: f32,
     pub corner_debuff_acceleration: f32,
+    /// The emitter this pixie came from, which knows about alternative routes.
+    pub emitter: Option<Entity>,
+    /// The distance to a junction that this pixie must wait to enter.
+    pub hold: Option<f32>,
 }
 impl Default for Pixie {
     fn default() -> Self {
+        let physics = PixiePhysics::default();
+
         Self {
             flavor: PixieFlavor::default(),
             path: vec![],
             path_index: 0,
             next_corner_angle: None,
-            current_speed: PIXIE_MAX_SPEED,
-            acceleration: 50.0,
-            deceleration: 50.0,
+            current_speed: physics.max_speed,
+            acceleration: physics.acceleration,
+            deceleration: physics.deceleration,
             exploding: false,
 
             lead_pixie: None,
             driving_state: DrivingState::Cruising,
             corner_debuff_distance_remaining: 0.0,
             corner_debuff_acceleration: 0.0,
+            emitter: None,
+            hold: None,
         }
     }
 }
@@ -108,11 +112,33 @@ pub enum DrivingState {
 
 pub struct PixieEmitter {
     pub flavor: PixieFlavor,
-    pub path: Vec<RoadSegment>,
+    /// The terminus this emitter belongs to.
+    pub terminus: Entity,
+    /// The terminus that pixies from this emitter are headed towards.
+    pub destination: Entity,
+    /// Routes that pixies are sent along in turn. Always contains at least one
+    /// route.
+    pub paths: Vec<Vec<RoadSegment>>,
+    pub next_path: usize,
     pub remaining: u32,
-    pub timer: Timer,
+    /// The schedule for this flavor at this terminus. When a flavor is
+    /// collected by multiple terminuses, each of their emitters shares it.
+    pub schedule: EmitSchedule,
+    /// This emitter is responsible for every `stride`th pixie in the schedule,
+    /// starting with the `offset`th.
+    pub offset: u32,
+    pub stride: u32,
+    pub emitted: u32,
+    /// Seconds since the simulation started. May begin negative to stagger
+    /// pixies of different flavors leaving the same terminus.
+    pub elapsed: f32,
 }
 
+/// Pixies that reached the end of their path during the current tick, along
+/// with the point they arrived at.
+#[derive(Resource, Default)]
+pub struct PixieArrivals(pub Vec<(Vec2, PixieFlavor)>);
+
 #[derive(Copy, Clone, Default, Debug, Deserialize, PartialEq, Eq, Hash)]
 pub struct PixieFlavor {
     pub color: u32,
@@ -192,7 +218,18 @@ impl RTreeObject for PixiePoint {
 pub fn collide_pixies_system(
     query: Query<(Entity, &Transform), With<Pixie>>,
     mut pixie_query: Query<&mut Pixie>,
+    mut explosion_log: ResMut<ExplosionLog>,
+    mut congestion: ResMut<SegmentCongestion>,
+    sim_steps: Res<SimulationSteps>,
+    physics: Res<PixiePhysics>,
 ) {
+    congestion.0.clear();
+    for pixie in pixie_query.iter() {
+        if let Some(segment) = pixie.path.get(pixie.path_index) {
+            *congestion.0.entry(segment_key(segment)).or_insert(0) += 1;
+        }
+    }
+
     // rather than attempt to correctly maintain our spatial index when
     // pixies move and spawn and despawn, we're just going to create a
     // new index on every frame.
@@ -233,7 +270,7 @@ pub fn collide_pixies_system(
 
         let travel_segs = traveled_segments(
             t1.translation.truncate(),
-            PIXIE_VISION_DISTANCE,
+            physics.vision_distance,
             &p1.path[p1.path_index..],
         );
 
@@ -241,12 +278,12 @@ pub fn collide_pixies_system(
 
         let collision_rect = AABB::from_corners(
             [
-                t1.translation.x - PIXIE_VISION_DISTANCE,
-                t1.translation.y - PIXIE_VISION_DISTANCE,
+                t1.translation.x - physics.vision_distance,
+                t1.translation.y - physics.vision_distance,
             ],
             [
-                t1.translation.x + PIXIE_VISION_DISTANCE,
-                t1.translation.y + PIXIE_VISION_DISTANCE,
+                t1.translation.x + physics.vision_distance,
+                t1.translation.y + physics.vision_distance,
             ],
         );
 
@@ -266,11 +303,7 @@ pub fn collide_pixies_system(
                 continue;
             }
 
-            let dist = distance_on_path(
-                t1.translation.truncate(),
-                t2.translation.truncate(),
-                &travel_segs,
-            );
+            let dist = distance_on_path(t2.translation.truncate(), &travel_segs);
 
             if let Some(dist) = dist {
                 potential_cols.push((e2, p2.flavor, p2.current_speed, dist));
@@ -285,7 +318,19 @@ pub fn collide_pixies_system(
         // get preferential treatment when deciding who can be attracted to whom.
 
         if let Some((e2, flavor, current_speed, dist)) = potential_cols.first() {
-            if flavor.color != p1.flavor.color && *dist <= PIXIE_EXPLOSION_DISTANCE {
+            if flavor.color != p1.flavor.color && *dist <= physics.explosion_distance {
+                // both pixies in a pair will usually detect each other, so only
+                // record the first detection.
+                if !explosions.contains(&e1) && !explosions.contains(e2) {
+                    let (_, t2) = query.get(*e2).unwrap();
+
+                    explosion_log.0.push(Explosion {
+                        position: (t1.translation.truncate() + t2.translation.truncate()) / 2.0,
+                        tick: sim_steps.get_step(),
+                        flavors: (p1.flavor, *flavor),
+                    });
+                }
+
                 explosions.push(e1);
                 explosions.push(*e2);
                 continue;
@@ -339,19 +384,25 @@ pub fn collide_pixies_system(
 
 pub fn move_pixies_system(
     mut commands: Commands,
-    mut score: ResMut<PixieCount>,
+    mut arrivals: ResMut<PixieArrivals>,
+    settings: Res<SimulationSettings>,
+    congestion: Res<SegmentCongestion>,
+    physics: Res<PixiePhysics>,
+    pathfinding: Res<PathfindingState>,
     mut query: Query<(Entity, &mut Pixie, &mut Transform)>,
+    q_emitters: Query<&PixieEmitter>,
 ) {
     let delta = SIMULATION_TIMESTEP;
 
     for (entity, mut pixie, mut transform) in query.iter_mut() {
         if pixie.path_index > pixie.path.len() - 1 {
             commands.entity(entity).despawn();
-            score.0 += 1;
+            if let Some(segment) = pixie.path.last() {
+                arrivals.0.push((segment.points.1, pixie.flavor));
+            }
             continue;
         }
 
-        let next_waypoint = pixie.path[pixie.path_index].points.1;
         let prev_waypoint = pixie.path[pixie.path_index].points.0;
         let current_layer = pixie.path[pixie.path_index].layer;
         let next_layer = if let Some(seg) = pixie.path.get(pixie.path_index + 1) {
@@ -368,40 +419,69 @@ pub fn move_pixies_system(
         } else {
             current_layer
         };
-        let dist = transform.translation.truncate().distance(next_waypoint);
+        let dist = distance_remaining(
+            transform.translation.truncate(),
+            &pixie.path[pixie.path_index],
+        );
         let last_dist = transform.translation.truncate().distance(prev_waypoint);
 
         // determine speed limit and acceleration based on environmental factors
 
-        let mut speed_limit = PIXIE_MAX_SPEED;
+        let mut speed_limit =
+            physics.max_speed * pixie.path[pixie.path_index].class.speed_multiplier();
+
+        if let Some(radius) = pixie.path[pixie.path_index].arc_radius() {
+            speed_limit = speed_limit.min(physics.arc_max_speed(radius));
+        }
 
         if let Some(lead_pixie) = &pixie.lead_pixie {
-            if !lead_pixie.attractor && lead_pixie.distance < PIXIE_BRAKING_DISTANCE {
-                speed_limit = lead_pixie.speed - 10.0;
-                speed_limit = speed_limit.max(PIXIE_MIN_SPEED);
+            if !lead_pixie.attractor && lead_pixie.distance < physics.braking_distance {
+                speed_limit = lead_pixie.speed - physics.braking_speed_difference;
+                speed_limit = speed_limit.max(physics.min_speed);
             }
         }
-        if dist < CORNER_DEBUFF_ACTIVATION_DISTANCE {
+        if dist < physics.corner_debuff_activation_distance {
             // pixies must slow down as they approach sharp corners
 
             if let Some(angle) = pixie.next_corner_angle {
                 if angle <= 45.0 {
-                    speed_limit = speed_limit.min(PIXIE_MAX_SPEED_45);
-                    pixie.corner_debuff_distance_remaining = CORNER_DEBUFF_DISTANCE;
-                    pixie.corner_debuff_acceleration = pixie.acceleration / 8.0;
+                    speed_limit = speed_limit.min(physics.max_speed_45);
+                    pixie.corner_debuff_distance_remaining = physics.corner_debuff_distance;
+                    pixie.corner_debuff_acceleration =
+                        pixie.acceleration / physics.corner_debuff_45;
                 } else if angle <= 90.0 {
-                    speed_limit = speed_limit.min(PIXIE_MAX_SPEED_90);
-                    pixie.corner_debuff_distance_remaining = CORNER_DEBUFF_DISTANCE;
-                    pixie.corner_debuff_acceleration = pixie.acceleration / 6.0;
+                    speed_limit = speed_limit.min(physics.max_speed_90);
+                    pixie.corner_debuff_distance_remaining = physics.corner_debuff_distance;
+                    pixie.corner_debuff_acceleration =
+                        pixie.acceleration / physics.corner_debuff_90;
                 }
             }
+
+            // arcs are gentler. pixies just need to be going slowly enough to
+            // get around them, and can accelerate normally afterwards.
+
+            if let Some(radius) = pixie
+                .path
+                .get(pixie.path_index + 1)
+                .and_then(RoadSegment::arc_radius)
+            {
+                speed_limit = speed_limit.min(physics.arc_max_speed(radius));
+            }
         }
         if let Some(lead_pixie) = &pixie.lead_pixie {
             // pixies will drive very recklessly towards a pixie of another
             // flavor. this overrides other cornering and braking behaviors.
 
             if lead_pixie.attractor {
-                speed_limit = PIXIE_MAX_SPEED_ATTRACTED;
+                speed_limit = physics.max_speed_attracted;
+            }
+        }
+        if let Some(hold) = pixie.hold {
+            // pixies waiting at a junction treat it like a stopped lead pixie,
+            // even when attracted to a pixie on the other side.
+
+            if hold - PIXIE_RADIUS < physics.braking_distance {
+                speed_limit = 0.0;
             }
         }
 
@@ -431,16 +511,18 @@ pub fn move_pixies_system(
 
         // move the pixie
 
-        let step = pixie.current_speed * delta;
+        let mut step = pixie.current_speed * delta;
 
-        let (to, segments_traveled) = travel(
-            transform.translation.truncate(),
-            step,
-            &pixie.path[pixie.path_index..],
-        );
+        if let Some(hold) = pixie.hold {
+            let room = (hold - PIXIE_RADIUS).max(0.0);
+            if step > room {
+                step = room;
+                pixie.current_speed = room / delta;
+            }
+        }
 
-        transform.translation.x = to.x;
-        transform.translation.y = to.y;
+        let from = transform.translation.truncate();
+        let (mut to, segments_traveled) = travel(from, step, &pixie.path[pixie.path_index..]);
 
         if segments_traveled == 0 {
             // pixies traveling uphill should stay above the next road as they approach it.
@@ -453,22 +535,50 @@ pub fn move_pixies_system(
                 transform.translation.z = layer::PIXIE - current_layer as f32;
             }
         } else {
+            let passed = &pixie.path[pixie.path_index..pixie.path_index + segments_traveled];
+            let past_junction = step - path_length(from, passed);
+
             pixie.path_index += segments_traveled;
+
+            // pixies that just passed through a junction may decide that
+            // another route is less congested.
+            let at_junction = pixie.path.get(pixie.path_index).is_some_and(|segment| {
+                pathfinding
+                    .junctions
+                    .contains(&junction_key(segment.points.0, segment.layer))
+            });
+
+            if settings.routing == RoutingMode::Dynamic && at_junction {
+                if let Some(emitter) = pixie.emitter.and_then(|e| q_emitters.get(e).ok()) {
+                    if let Some((route, index)) =
+                        faster_route(&pixie.path[pixie.path_index..], &emitter.paths, &congestion)
+                    {
+                        let junction = emitter.paths[route][index].points.0;
+
+                        pixie.path = emitter.paths[route].clone();
+                        pixie.next_corner_angle = None;
+
+                        // carry on from the junction as far as the pixie
+                        // had already gone along its old route
+                        let (rerouted, traveled) =
+                            travel(junction, past_junction, &pixie.path[index..]);
+                        to = rerouted;
+                        pixie.path_index = index + traveled;
+                    }
+                }
+            }
         }
 
+        transform.translation.x = to.x;
+        transform.translation.y = to.y;
+
         if pixie.next_corner_angle.is_none() || step > dist {
             if let (Some(current_waypoint), Some(next_waypoint)) = (
                 pixie.path.get(pixie.path_index),
                 pixie.path.get(pixie.path_index + 1),
             ) {
-                pixie.next_corner_angle = Some(
-                    corner_angle(
-                        current_waypoint.points.0,
-                        next_waypoint.points.0,
-                        next_waypoint.points.1,
-                    )
-                    .to_degrees(),
-                );
+                pixie.next_corner_angle =
+                    Some(joining_angle(current_waypoint, next_waypoint).to_degrees());
             } else {
                 pixie.next_corner_angle = Some(180.0);
             }
@@ -481,47 +591,117 @@ pub fn move_pixies_system(
     }
 }
 
-pub fn emit_pixies_system(mut q_emitters: Query<&mut PixieEmitter>, mut commands: Commands) {
-    for mut emitter in q_emitters.iter_mut() {
-        if emitter.remaining == 0 {
+/// Scores pixies that arrived at a terminus collecting their flavor, and
+/// passes pixies that arrived at processors along to the emitters that
+/// processor feeds.
+pub fn arrive_pixies_system(
+    mut arrivals: ResMut<PixieArrivals>,
+    mut score: ResMut<PixieCount>,
+    mut deliveries: ResMut<Deliveries>,
+    mut processor_states: ResMut<ProcessorStates>,
+    q_terminuses: Query<(Entity, &Terminus)>,
+    mut q_emitters: Query<&mut PixieEmitter>,
+) {
+    for (point, flavor) in arrivals.0.drain(..) {
+        let Some((destination, terminus)) = q_terminuses.iter().find(|(_, t)| t.point == point)
+        else {
             continue;
+        };
+
+        // routes only lead to terminuses collecting the pixie's flavor, but
+        // a pixie that ends up somewhere it isn't wanted still doesn't count.
+        // this includes pixies of the right color but the wrong net. full
+        // terminuses reject pixies in `deliver`.
+        if !terminus.collects.contains(&flavor) {
+            deliveries.reject(destination);
+            continue;
+        }
+
+        if !terminus.is_processor() {
+            if deliveries.deliver(destination, terminus, flavor) {
+                score.0 += 1;
+            }
+            continue;
+        }
+
+        let state = processor_states.0.entry(destination).or_default();
+
+        for output in feed(terminus, state, flavor) {
+            let mut emitters = q_emitters
+                .iter_mut()
+                .filter(|emitter| emitter.terminus == destination && emitter.flavor == output)
+                .collect::<Vec<_>>();
+
+            if emitters.is_empty() {
+                continue;
+            }
+
+            // alternate between the terminuses collecting this flavor
+            emitters.sort_by_key(|emitter| emitter.destination);
+
+            let emitted = state.emitted.entry(output).or_insert(0);
+            let index = *emitted % emitters.len();
+            emitters[index].remaining += 1;
+            *emitted += 1;
         }
+    }
+}
 
-        emitter
-            .timer
-            .tick(Duration::from_secs_f32(SIMULATION_TIMESTEP));
+pub fn emit_pixies_system(
+    mut q_emitters: Query<(Entity, &mut PixieEmitter)>,
+    physics: Res<PixiePhysics>,
+    mut commands: Commands,
+) {
+    let shape = shapes::RegularPolygon {
+        sides: 6,
+        feature: shapes::RegularPolygonFeature::Radius(PIXIE_RADIUS),
+        ..shapes::RegularPolygon::default()
+    };
 
-        if !emitter.timer.is_finished() {
+    for (entity, mut emitter) in q_emitters.iter_mut() {
+        if emitter.remaining == 0 {
             continue;
         }
 
-        let shape = shapes::RegularPolygon {
-            sides: 6,
-            feature: shapes::RegularPolygonFeature::Radius(PIXIE_RADIUS),
-            ..shapes::RegularPolygon::default()
-        };
+        emitter.elapsed += SIMULATION_TIMESTEP;
+
+        // schedules may call for more than one pixie per tick
+        while emitter.remaining > 0 {
+            let n = emitter.offset + emitter.emitted * emitter.stride;
+            let emit_time = emitter.schedule.emit_time(n);
+            if emitter.elapsed < emit_time {
+                break;
+            }
+
+            let path = emitter.paths[emitter.next_path % emitter.paths.len()].clone();
+
+            // pixies that were due earlier in this tick get a head start, so
+            // that they don't all leave from the same spot.
+            let late = (emitter.elapsed - emit_time).min(SIMULATION_TIMESTEP);
+            let (position, path_index) = travel(path[0].points.0, late * physics.max_speed, &path);
+            let road_layer = path[path_index.min(path.len() - 1)].layer;
 
-        let first_segment = emitter.path.first().unwrap();
-
-        commands.spawn((
-            ShapeBuilder::with(&shape)
-                .fill(theme::PIXIE[(emitter.flavor.color) as usize])
-                .build(),
-            Transform::from_translation(
-                first_segment
-                    .points
-                    .0
-                    .extend(layer::PIXIE - first_segment.layer as f32),
-            ),
-            Pixie {
-                flavor: emitter.flavor,
-                path: emitter.path.clone(),
-                path_index: 0,
-                ..default()
-            },
-            DespawnOnExit(GameState::Playing),
-        ));
-
-        emitter.remaining -= 1;
+            commands.spawn((
+                ShapeBuilder::with(&shape)
+                    .fill(theme::PIXIE[(emitter.flavor.color) as usize])
+                    .build(),
+                Transform::from_translation(position.extend(layer::PIXIE - road_layer as f32)),
+                Pixie {
+                    flavor: emitter.flavor,
+                    path,
+                    path_index,
+                    emitter: Some(entity),
+                    current_speed: physics.max_speed,
+                    acceleration: physics.acceleration,
+                    deceleration: physics.deceleration,
+                    ..default()
+                },
+                DespawnOnExit(GameState::Playing),
+            ));
+
+            emitter.next_path += 1;
+            emitter.emitted += 1;
+            emitter.remaining -= 1;
+        }
     }
 }
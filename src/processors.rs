@@ -0,0 +1,161 @@
+//! Terminuses that emit pixies in response to collecting others, rather than
+//! on a schedule.
+
+use bevy::{platform::collections::HashMap, prelude::*};
+use petgraph::{algo::tarjan_scc, graphmap::DiGraphMap};
+
+use crate::{
+    level::{Terminus, TerminusKind},
+    pixie::PixieFlavor,
+};
+
+pub struct ProcessorsPlugin;
+impl Plugin for ProcessorsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ProcessorStates>();
+    }
+}
+
+#[derive(Default, Debug)]
+pub struct ProcessorState {
+    /// Pixies collected but not yet consumed, by flavor.
+    pub buffered: HashMap<PixieFlavor, u32>,
+    /// The number of pixies emitted so far, by flavor. Used to alternate
+    /// between destinations.
+    pub emitted: HashMap<PixieFlavor, usize>,
+}
+
+/// The state of each processor terminus during the current run.
+#[derive(Resource, Default)]
+pub struct ProcessorStates(pub HashMap<Entity, ProcessorState>);
+
+/// Records a pixie arriving at a processor terminus, returning the flavors of
+/// any pixies that the processor should now emit.
+pub fn feed(
+    terminus: &Terminus,
+    state: &mut ProcessorState,
+    flavor: PixieFlavor,
+) -> Vec<PixieFlavor> {
+    if !terminus.collects.contains(&flavor) {
+        return vec![];
+    }
+
+    match terminus.kind {
+        TerminusKind::Normal => vec![],
+        TerminusKind::Splitter => {
+            if terminus.emits.contains(&flavor) {
+                vec![flavor]
+            } else {
+                vec![]
+            }
+        }
+        TerminusKind::Combiner => {
+            *state.buffered.entry(flavor).or_insert(0) += 1;
+
+            let ready = terminus
+                .collects
+                .iter()
+                .all(|f| state.buffered.get(f).is_some_and(|count| *count > 0));
+
+            if !ready {
+                return vec![];
+            }
+
+            for f in terminus.collects.iter() {
+                if let Some(count) = state.buffered.get_mut(f) {
+                    *count -= 1;
+                }
+            }
+
+            let mut outputs = terminus.emits.iter().copied().collect::<Vec<_>>();
+            outputs.sort_by_key(|f| (f.color, f.net));
+            outputs
+        }
+    }
+}
+
+/// Given pairs of processors where the first sends pixies to the second,
+/// returns the pairs that are part of a loop. Pixies could be passed around a
+/// loop forever, so the simulation would never finish.
+pub fn processor_cycles(links: &[(Entity, Entity)]) -> Vec<(Entity, Entity)> {
+    let graph = DiGraphMap::<Entity, ()>::from_edges(links.iter().copied());
+
+    let mut components = HashMap::new();
+    for (i, component) in tarjan_scc(&graph).into_iter().enumerate() {
+        if component.len() < 2 {
+            continue;
+        }
+
+        for entity in component {
+            components.insert(entity, i);
+        }
+    }
+
+    links
+        .iter()
+        .filter(|(a, b)| {
+            components
+                .get(a)
+                .is_some_and(|c| components.get(b) == Some(c))
+        })
+        .copied()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::flavor;
+
+    #[test]
+    fn combiner_waits_for_each_input() {
+        let terminus = Terminus {
+            collects: [flavor(0, 0), flavor(1, 0)].into_iter().collect(),
+            emits: [flavor(2, 0)].into_iter().collect(),
+            kind: TerminusKind::Combiner,
+            ..default()
+        };
+        let mut state = ProcessorState::default();
+
+        assert!(feed(&terminus, &mut state, flavor(0, 0)).is_empty());
+        assert!(feed(&terminus, &mut state, flavor(0, 0)).is_empty());
+        assert_eq!(
+            feed(&terminus, &mut state, flavor(1, 0)),
+            vec![flavor(2, 0)]
+        );
+        assert_eq!(
+            feed(&terminus, &mut state, flavor(1, 0)),
+            vec![flavor(2, 0)]
+        );
+        assert!(feed(&terminus, &mut state, flavor(1, 0)).is_empty());
+    }
+
+    #[test]
+    fn processor_cycles_between_splitters() {
+        let entity = |index| Entity::from_raw_u32(index).unwrap();
+        let (a, b, c) = (entity(0), entity(1), entity(2));
+
+        assert!(processor_cycles(&[(a, b), (b, c)]).is_empty());
+        assert_eq!(
+            processor_cycles(&[(a, b), (b, a), (b, c)]),
+            vec![(a, b), (b, a)]
+        );
+    }
+
+    #[test]
+    fn splitter_passes_through() {
+        let terminus = Terminus {
+            collects: [flavor(0, 0)].into_iter().collect(),
+            emits: [flavor(0, 0)].into_iter().collect(),
+            kind: TerminusKind::Splitter,
+            ..default()
+        };
+        let mut state = ProcessorState::default();
+
+        assert_eq!(
+            feed(&terminus, &mut state, flavor(0, 0)),
+            vec![flavor(0, 0)]
+        );
+        assert!(feed(&terminus, &mut state, flavor(1, 0)).is_empty());
+    }
+}
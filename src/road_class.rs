@@ -0,0 +1,64 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::theme;
+
+/// The kind of trace a road segment is built from, which determines how fast
+/// pixies may travel along it and how much it costs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub enum RoadClass {
+    #[default]
+    Standard,
+    /// Cheap, but pixies must take it slowly.
+    Slow,
+    /// Expensive, but pixies can really get going.
+    Fast,
+}
+impl RoadClass {
+    pub const ALL: [RoadClass; 3] = [RoadClass::Slow, RoadClass::Standard, RoadClass::Fast];
+
+    /// Multiplies the fastest a pixie may travel along a road of this class.
+    pub fn speed_multiplier(&self) -> f32 {
+        match self {
+            Self::Standard => 1.0,
+            Self::Slow => 0.6,
+            Self::Fast => 1.5,
+        }
+    }
+
+    /// Multiplies the cost of a road of this class, on top of any multiplier
+    /// for its layer.
+    pub fn cost_multiplier(&self) -> f32 {
+        match self {
+            Self::Standard => 1.0,
+            Self::Slow => 0.5,
+            Self::Fast => 2.0,
+        }
+    }
+
+    pub fn color(&self, layer: u32) -> Srgba {
+        let i = layer as usize - 1;
+
+        match self {
+            Self::Standard => theme::FINISHED_ROAD[i],
+            Self::Slow => theme::SLOW_ROAD[i],
+            Self::Fast => theme::FAST_ROAD[i],
+        }
+    }
+
+    pub fn width(&self) -> f32 {
+        match self {
+            Self::Standard => 2.0,
+            Self::Slow => 1.0,
+            Self::Fast => 3.5,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Standard => "STD",
+            Self::Slow => "SLOW",
+            Self::Fast => "FAST",
+        }
+    }
+}
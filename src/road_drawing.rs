@@ -6,14 +6,17 @@ use petgraph::{
 };
 
 use crate::{
-    collision::{point_segment_collision, segment_collision, PointCollision, SegmentCollision},
-    layer,
+    collision::{point_road_collision, road_collision, PointCollision, SegmentCollision},
+    cursor_over_play_area, layer,
     level::Obstacle,
-    lines::{possible_lines, Axis},
+    lines::{possible_lines, round_corner, Axis},
+    one_way_arrow,
+    road_class::RoadClass,
+    road_path,
     sim::SimulationState,
     spawn_road_segment, theme, Collider, ColliderLayer, DrawingInteraction, DrawingMouseMovement,
-    GameState, MousePos, MouseSnappedPos, PointGraphNode, RoadGraph, RoadSegment,
-    SegmentGraphNodes, SelectedTool, Tool, BOTTOM_BAR_HEIGHT,
+    GameState, MouseSnappedPos, PlayAreaNode, PointGraphNode, RoadGraph, RoadSegment,
+    SegmentGraphNodes, SelectedTool, Tool, GRID_SIZE,
 };
 
 pub struct RoadDrawingPlugin;
@@ -44,11 +47,20 @@ pub struct RoadDrawingState {
     end: Vec2,
     pub valid: bool,
     stop: bool,
-    pub segments: Vec<(Vec2, Vec2)>,
+    /// The segments being drawn, along with the center of each arc.
+    pub segments: Vec<((Vec2, Vec2), Option<Vec2>)>,
     adds: Vec<AddSegment>,
     axis_preference: Option<Axis>,
     pub layer: u32,
     prev_layer: u32,
+    /// Whether new roads may only be traveled in the direction they are drawn.
+    pub one_way: bool,
+    pub class: RoadClass,
+    /// Whether corners in new roads are rounded off into arcs.
+    pub arcs: bool,
+    /// The radius of rounded corners, in grid units.
+    pub arc_radius: u32,
+    prev_corner_radius: Option<f32>,
 }
 impl Default for RoadDrawingState {
     fn default() -> Self {
@@ -63,15 +75,47 @@ impl Default for RoadDrawingState {
             axis_preference: None,
             layer: 1,
             prev_layer: 1,
+            one_way: false,
+            class: RoadClass::default(),
+            arcs: false,
+            arc_radius: 1,
+            prev_corner_radius: None,
         }
     }
 }
+impl RoadDrawingState {
+    pub const MAX_ARC_RADIUS: u32 = 3;
+
+    /// Abandons the road currently being drawn, keeping the player's choice of
+    /// layer, class and so on.
+    pub fn cancel(&mut self) {
+        self.drawing = false;
+        self.stop = false;
+        self.segments = vec![];
+        self.adds = vec![];
+    }
+
+    /// The radius of the arcs that corners are rounded into, if they are.
+    pub fn corner_radius(&self) -> Option<f32> {
+        self.arcs.then(|| self.arc_radius as f32 * GRID_SIZE)
+    }
+
+    /// The segments that will be added when the player clicks, along with the
+    /// center of each arc.
+    pub fn planned_segments(&self) -> impl Iterator<Item = ((Vec2, Vec2), Option<Vec2>)> + '_ {
+        self.adds
+            .iter()
+            .filter(|_| self.drawing)
+            .map(|add| (add.points, add.arc))
+    }
+}
 #[derive(Component)]
 struct DrawingLine;
 
 #[derive(Clone, Debug)]
 struct AddSegment {
     points: (Vec2, Vec2),
+    arc: Option<Vec2>,
     connections: (Vec<SegmentConnection>, Vec<SegmentConnection>),
 }
 #[derive(Clone, Debug)]
@@ -85,7 +129,6 @@ enum SegmentConnection {
 fn drawing_mouse_click_system(
     mut commands: Commands,
     mouse_input: ResMut<ButtonInput<MouseButton>>,
-    mouse: Res<MousePos>,
     mouse_snapped: Res<MouseSnappedPos>,
     selected_tool: ResMut<SelectedTool>,
     mut road_state: ResMut<RoadDrawingState>,
@@ -94,13 +137,9 @@ fn drawing_mouse_click_system(
     q_point_nodes: Query<&PointGraphNode>,
     q_segment_nodes: Query<&SegmentGraphNodes>,
     q_road_segments: Query<&RoadSegment>,
-    q_window: Query<&Window>,
+    q_play_area: Query<&Interaction, With<PlayAreaNode>>,
 ) {
-    let Ok(window) = q_window.single() else {
-        return;
-    };
-
-    if mouse.window.y > window.resolution.height() - BOTTOM_BAR_HEIGHT {
+    if !cursor_over_play_area(&q_play_area) {
         return;
     }
 
@@ -142,20 +181,46 @@ fn drawing_mouse_click_system(
 
     for add in road_state.adds.iter() {
         // SegmentConnection::TryExtend is only valid if extending the
-        // target segment would not break any existing connections.
+        // target segment would not break any existing connections, and the
+        // extended segment would still lead in a single direction.
+
+        let compatible = |connection: &SegmentConnection, point: Vec2, is_start: bool| {
+            let SegmentConnection::TryExtend(entity) = connection else {
+                return false;
+            };
+            let Ok(segment) = q_road_segments.get(*entity) else {
+                return false;
+            };
+
+            if segment.class != road_state.class {
+                return false;
+            }
+
+            if !segment.one_way && !road_state.one_way {
+                return true;
+            }
+
+            segment.one_way
+                && road_state.one_way
+                && if is_start {
+                    segment.points.1 == point
+                } else {
+                    segment.points.0 == point
+                }
+        };
 
         let valid_extension_a = add.connections.0.len() == 1
             && add
                 .connections
                 .0
                 .iter()
-                .all(|c| matches!(c, SegmentConnection::TryExtend(_)));
+                .all(|c| compatible(c, add.points.0, true));
         let valid_extension_b = add.connections.1.len() == 1
             && add
                 .connections
                 .1
                 .iter()
-                .all(|c| matches!(c, SegmentConnection::TryExtend(_)));
+                .all(|c| compatible(c, add.points.1, false));
 
         let mut points = add.points;
 
@@ -188,6 +253,9 @@ fn drawing_mouse_click_system(
             RoadSegment {
                 points,
                 layer: road_state.layer,
+                one_way: road_state.one_way,
+                class: road_state.class,
+                arc: add.arc,
             },
         );
 
@@ -286,6 +354,9 @@ fn drawing_mouse_click_system(
                             RoadSegment {
                                 points: (segment.points.0, *point),
                                 layer: segment.layer,
+                                one_way: segment.one_way,
+                                class: segment.class,
+                                arc: segment.arc,
                             },
                         );
 
@@ -302,6 +373,9 @@ fn drawing_mouse_click_system(
                             RoadSegment {
                                 points: (*point, segment.points.1),
                                 layer: segment.layer,
+                                one_way: segment.one_way,
+                                class: segment.class,
+                                arc: segment.arc,
                             },
                         );
 
@@ -362,8 +436,8 @@ fn not_drawing_mouse_movement_system(
     let valid = !q_colliders
         .iter()
         .any(|(child_of, collider, _layer)| match collider {
-            Collider::Segment(segment) => {
-                match point_segment_collision(mouse_snapped.0, segment.0, segment.1) {
+            Collider::Segment(segment, arc) => {
+                match point_road_collision(mouse_snapped.0, *segment, *arc) {
                     PointCollision::None => false,
                     _ => q_obstacles.get(child_of.parent()).is_ok(),
                 }
@@ -395,12 +469,16 @@ fn drawing_mouse_movement_system(
         return;
     }
 
-    if mouse_snapped.0 == road_state.end && road_state.layer == road_state.prev_layer {
+    if mouse_snapped.0 == road_state.end
+        && road_state.layer == road_state.prev_layer
+        && road_state.corner_radius() == road_state.prev_corner_radius
+    {
         return;
     }
 
     road_state.end = mouse_snapped.0;
     road_state.prev_layer = road_state.layer;
+    road_state.prev_corner_radius = road_state.corner_radius();
 
     // line drawing can be coerced to follow one axis or another by moving the mouse to a
     // position that is a straight line from the starting point in that axis.
@@ -417,11 +495,18 @@ fn drawing_mouse_movement_system(
         road_state.valid = true;
     }
 
+    let corner_radius = road_state.corner_radius();
     let possible = possible_lines(
         road_state.start,
         mouse_snapped.0,
         road_state.axis_preference,
-    );
+    )
+    .into_iter()
+    .map(|segments| match corner_radius {
+        Some(radius) => with_rounded_corner(segments, radius),
+        None => segments.into_iter().map(|s| (s, None)).collect(),
+    })
+    .collect::<Vec<_>>();
 
     // groan
     let mut filtered_adds = vec![];
@@ -433,20 +518,20 @@ fn drawing_mouse_movement_system(
         let mut ok = true;
         let mut stop = false;
 
-        for (segment_i, (a, b)) in possibility.iter().enumerate() {
+        for (segment_i, ((a, b), arc)) in possibility.iter().enumerate() {
             let mut connections = (vec![], vec![]);
 
             let mut split_layers: (HashSet<u32>, HashSet<u32>) =
                 (HashSet::default(), HashSet::default());
 
-            if segment_i == 1 {
+            if segment_i > 0 {
                 connections.0.push(SegmentConnection::Previous);
             }
 
             for (child_of, collider, layer) in q_colliders.iter() {
                 match collider {
-                    Collider::Segment(s) => {
-                        let collision = segment_collision(s.0, s.1, *a, *b);
+                    Collider::Segment(s, s_arc) => {
+                        let collision = road_collision(*s, *s_arc, (*a, *b), *arc);
 
                         // If there's no collision, there's no problem.
                         if matches!(collision, SegmentCollision::None) {
@@ -556,7 +641,7 @@ fn drawing_mouse_movement_system(
                         }
                     }
                     // The only point colliders that exist right now are for termini
-                    Collider::Point(p) => match point_segment_collision(*p, *a, *b) {
+                    Collider::Point(p) => match point_road_collision(*p, (*a, *b), *arc) {
                         // Don't allow the midpoint of the line to connect to a terminus.
                         PointCollision::Middle => {
                             ok = false;
@@ -594,6 +679,7 @@ fn drawing_mouse_movement_system(
 
             adds.push(AddSegment {
                 points: (*a, *b),
+                arc: *arc,
                 connections,
             });
         }
@@ -621,6 +707,31 @@ fn drawing_mouse_movement_system(
     }
 }
 
+/// Replaces the corner in a two segment polyline with an arc, pairing each
+/// segment with the center of its arc if it is one. The corner is left alone
+/// if the arc doesn't fit.
+fn with_rounded_corner(
+    segments: Vec<(Vec2, Vec2)>,
+    radius: f32,
+) -> Vec<((Vec2, Vec2), Option<Vec2>)> {
+    let &[(a, b), (_, c)] = segments.as_slice() else {
+        return segments.into_iter().map(|s| (s, None)).collect();
+    };
+
+    let Some((start, end, center)) = round_corner(a, b, c, radius) else {
+        return segments.into_iter().map(|s| (s, None)).collect();
+    };
+
+    [
+        ((a, start), None),
+        ((start, end), Some(center)),
+        ((end, c), None),
+    ]
+    .into_iter()
+    .filter(|((a, b), _)| a != b)
+    .collect()
+}
+
 fn draw_drawing_system(
     mut commands: Commands,
     road_drawing: Res<RoadDrawingState>,
@@ -641,15 +752,26 @@ fn draw_drawing_system(
             bevy::color::palettes::css::RED
         };
 
-        for (a, b) in road_drawing.segments.iter() {
+        for (points, arc) in road_drawing.segments.iter() {
             commands.spawn((
-                ShapeBuilder::with(&shapes::Line(*a, *b))
+                ShapeBuilder::with(&road_path(*points, *arc))
                     .stroke((color, 2.0))
                     .build(),
                 Transform::from_xyz(0.0, 0.0, layer::ROAD_OVERLAY),
                 DrawingLine,
                 DespawnOnExit(GameState::Playing),
             ));
+
+            if road_drawing.one_way {
+                commands.spawn((
+                    ShapeBuilder::with(&one_way_arrow(*points, *arc))
+                        .stroke((color, 2.0))
+                        .build(),
+                    Transform::from_xyz(0.0, 0.0, layer::ROAD_OVERLAY),
+                    DrawingLine,
+                    DespawnOnExit(GameState::Playing),
+                ));
+            }
         }
     }
 }
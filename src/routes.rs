@@ -0,0 +1,419 @@
+use bevy::{
+    platform::collections::{HashMap, HashSet},
+    prelude::*,
+};
+use itertools::Itertools;
+use petgraph::{
+    algo::astar,
+    stable_graph::{NodeIndex, StableUnGraph},
+    visit::{EdgeFiltered, EdgeRef},
+};
+
+use crate::{lines::segment_length, pixie::PIXIE_RADIUS, road_class::RoadClass, RoadSegment};
+
+/// The maximum number of distinct routes pixies will be split between.
+pub const MAX_ROUTES: usize = 3;
+/// Alternative routes longer than the shortest route by more than this factor
+/// are not used.
+pub const MAX_ROUTE_DETOUR: f32 = 1.5;
+/// Segments used by a route are made this much more expensive before searching
+/// for the next route, nudging the search towards parallel roads.
+const ROUTE_PENALTY: f32 = 4.0;
+/// Roads on lower layers are considered this much longer per layer, so that
+/// pixies prefer to stay near the surface when routes are otherwise equal.
+const LOWER_LAYER_WEIGHT: f32 = 0.05;
+/// Each pixie traveling along a segment makes it seem this much longer to
+/// pixies that are considering rerouting.
+const CONGESTION_DISTANCE: f32 = PIXIE_RADIUS * 4.0;
+/// Pixies only switch to a route that seems shorter than their current route
+/// by at least this factor.
+const REROUTE_THRESHOLD: f32 = 0.9;
+
+/// Identifies a road segment regardless of the direction it is traveled in.
+pub type SegmentKey = ((i32, i32), (i32, i32), u32);
+/// Identifies the point on a layer where road segments meet.
+pub type JunctionKey = ((i32, i32), u32);
+
+/// The number of pixies currently traveling along each road segment.
+#[derive(Resource, Default)]
+pub struct SegmentCongestion(pub HashMap<SegmentKey, u32>);
+impl SegmentCongestion {
+    pub fn get(&self, segment: &RoadSegment) -> u32 {
+        self.0.get(&segment_key(segment)).copied().unwrap_or(0)
+    }
+}
+
+pub fn segment_key(segment: &RoadSegment) -> SegmentKey {
+    let a = (segment.points.0.x as i32, segment.points.0.y as i32);
+    let b = (segment.points.1.x as i32, segment.points.1.y as i32);
+
+    (a.min(b), a.max(b), segment.layer)
+}
+
+pub fn junction_key(point: Vec2, layer: u32) -> JunctionKey {
+    ((point.x as i32, point.y as i32), layer)
+}
+
+/// Returns the points where more than two road segments meet, which are the
+/// only places where pixies have a choice of where to go next.
+pub fn junctions<'a>(
+    graph: &StableUnGraph<Entity, f32>,
+    segments: impl Iterator<Item = (&'a RoadSegment, NodeIndex, NodeIndex)>,
+) -> HashSet<JunctionKey> {
+    segments
+        .flat_map(|(segment, start, end)| {
+            [(segment.points.0, start), (segment.points.1, end)]
+                .into_iter()
+                .filter(|(_, node)| graph.edges(*node).count() > 2)
+                .map(|(point, _)| junction_key(point, segment.layer))
+        })
+        .collect()
+}
+
+/// Returns the weight of the graph edge representing a road segment. Faster
+/// roads are considered shorter than slower roads of the same length.
+pub fn segment_weight(
+    points: (Vec2, Vec2),
+    arc: Option<Vec2>,
+    layer: u32,
+    class: RoadClass,
+) -> f32 {
+    segment_length(points, arc) * (1.0 + LOWER_LAYER_WEIGHT * layer.saturating_sub(1) as f32)
+        / class.speed_multiplier()
+}
+
+/// Finds up to [`MAX_ROUTES`] distinct routes between `start` and `goal`,
+/// shortest first.
+///
+/// Routes are considered distinct if they visit a different sequence of
+/// entities. Moving between the graph nodes at a single junction does not make
+/// a route distinct.
+///
+/// Routes never travel directly from the first to the second node of any pair
+/// in `forbidden`, which is how one-way roads are represented.
+pub fn find_routes(
+    graph: &StableUnGraph<Entity, f32>,
+    start: NodeIndex,
+    goal: NodeIndex,
+    forbidden: &HashSet<(NodeIndex, NodeIndex)>,
+) -> Vec<(f32, Vec<NodeIndex>)> {
+    let mut penalized = graph.clone();
+    let mut routes: Vec<(f32, Vec<NodeIndex>)> = vec![];
+    let mut seen: Vec<Vec<Entity>> = vec![];
+
+    for _ in 0..MAX_ROUTES * 2 {
+        // edges of an undirected graph are always visited from their source
+        let filtered = EdgeFiltered::from_fn(&penalized, |edge| {
+            !forbidden.contains(&(edge.source(), edge.target()))
+        });
+
+        let Some((_, path)) = astar(
+            &filtered,
+            start,
+            |finish| finish == goal,
+            |e| *e.weight(),
+            |_| 0.0,
+        ) else {
+            break;
+        };
+
+        for (a, b) in path.iter().tuple_windows() {
+            // only penalize edges that travel along a segment, not the
+            // connections between segments.
+            if penalized.node_weight(*a) != penalized.node_weight(*b) {
+                continue;
+            }
+
+            if let Some(weight) = penalized
+                .find_edge(*a, *b)
+                .and_then(|edge| penalized.edge_weight_mut(edge))
+            {
+                *weight *= ROUTE_PENALTY;
+            }
+        }
+
+        let entities = path
+            .iter()
+            .filter_map(|node| graph.node_weight(*node))
+            .copied()
+            .dedup()
+            .collect::<Vec<_>>();
+
+        if seen.contains(&entities) {
+            continue;
+        }
+        seen.push(entities);
+
+        let cost = path_cost(graph, &path);
+
+        if let Some((shortest, _)) = routes.first() {
+            if cost > shortest * MAX_ROUTE_DETOUR {
+                continue;
+            }
+        }
+
+        routes.push((cost, path));
+
+        if routes.len() >= MAX_ROUTES {
+            break;
+        }
+    }
+
+    routes
+}
+
+/// Returns the length of a route, treating segments with pixies on them as
+/// longer than they really are.
+pub fn congested_length(segments: &[RoadSegment], congestion: &SegmentCongestion) -> f32 {
+    segments
+        .iter()
+        .map(|segment| {
+            segment_length(segment.points, segment.arc)
+                + congestion.get(segment) as f32 * CONGESTION_DISTANCE
+        })
+        .sum()
+}
+
+/// Given the remainder of a pixie's route, starting at a junction, looks for a
+/// less congested way to the same destination among `routes`.
+///
+/// Returns the index of the better route and the index of the segment in that
+/// route that starts at the junction.
+pub fn faster_route(
+    remaining: &[RoadSegment],
+    routes: &[Vec<RoadSegment>],
+    congestion: &SegmentCongestion,
+) -> Option<(usize, usize)> {
+    let junction = remaining.first()?.points.0;
+    let current_length = congested_length(remaining, congestion);
+
+    routes
+        .iter()
+        .enumerate()
+        .filter_map(|(route_index, route)| {
+            let segment_index = route.iter().position(|seg| seg.points.0 == junction)?;
+            let alternative = &route[segment_index..];
+
+            if alternative == remaining {
+                return None;
+            }
+
+            Some((
+                route_index,
+                segment_index,
+                congested_length(alternative, congestion),
+            ))
+        })
+        .filter(|(_, _, length)| *length < current_length * REROUTE_THRESHOLD)
+        .min_by(|a, b| a.2.total_cmp(&b.2))
+        .map(|(route_index, segment_index, _)| (route_index, segment_index))
+}
+
+fn path_cost(graph: &StableUnGraph<Entity, f32>, path: &[NodeIndex]) -> f32 {
+    path.iter()
+        .tuple_windows()
+        .filter_map(|(a, b)| graph.find_edge(*a, *b))
+        .filter_map(|edge| graph.edge_weight(edge))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{self, entity};
+
+    /// Adds a segment to the graph, returning its start and end nodes.
+    fn segment(
+        graph: &mut StableUnGraph<Entity, f32>,
+        index: u32,
+        length: f32,
+    ) -> (NodeIndex, NodeIndex) {
+        let a = graph.add_node(entity(index));
+        let b = graph.add_node(entity(index));
+        graph.add_edge(a, b, length);
+        (a, b)
+    }
+
+    #[test]
+    fn find_routes_parallel() {
+        let mut graph = StableUnGraph::default();
+        let start = graph.add_node(entity(0));
+        let goal = graph.add_node(entity(1));
+
+        let (a1, a2) = segment(&mut graph, 2, 10.0);
+        let (b1, b2) = segment(&mut graph, 3, 12.0);
+
+        graph.add_edge(start, a1, 0.0);
+        graph.add_edge(a2, goal, 0.0);
+        graph.add_edge(start, b1, 0.0);
+        graph.add_edge(b2, goal, 0.0);
+
+        let routes = find_routes(&graph, start, goal, &HashSet::new());
+
+        assert_eq!(routes.len(), 2);
+        assert_eq!(routes[0].0, 10.0);
+        assert_eq!(routes[1].0, 12.0);
+    }
+
+    #[test]
+    fn find_routes_single() {
+        let mut graph = StableUnGraph::default();
+        let start = graph.add_node(entity(0));
+        let goal = graph.add_node(entity(1));
+
+        let (a1, a2) = segment(&mut graph, 2, 10.0);
+
+        graph.add_edge(start, a1, 0.0);
+        graph.add_edge(a2, goal, 0.0);
+
+        assert_eq!(find_routes(&graph, start, goal, &HashSet::new()).len(), 1);
+    }
+
+    #[test]
+    fn find_routes_detour_too_long() {
+        let mut graph = StableUnGraph::default();
+        let start = graph.add_node(entity(0));
+        let goal = graph.add_node(entity(1));
+
+        let (a1, a2) = segment(&mut graph, 2, 10.0);
+        let (b1, b2) = segment(&mut graph, 3, 100.0);
+
+        graph.add_edge(start, a1, 0.0);
+        graph.add_edge(a2, goal, 0.0);
+        graph.add_edge(start, b1, 0.0);
+        graph.add_edge(b2, goal, 0.0);
+
+        assert_eq!(find_routes(&graph, start, goal, &HashSet::new()).len(), 1);
+    }
+
+    #[test]
+    fn find_routes_one_way() {
+        let mut graph = StableUnGraph::default();
+        let start = graph.add_node(entity(0));
+        let goal = graph.add_node(entity(1));
+
+        let (a1, a2) = segment(&mut graph, 2, 10.0);
+        let (b1, b2) = segment(&mut graph, 3, 12.0);
+
+        graph.add_edge(start, a1, 0.0);
+        graph.add_edge(a2, goal, 0.0);
+        graph.add_edge(start, b1, 0.0);
+        graph.add_edge(b2, goal, 0.0);
+
+        // the shorter segment only leads towards the start
+        let forbidden = [(a1, a2)].into_iter().collect();
+        let routes = find_routes(&graph, start, goal, &forbidden);
+
+        assert_eq!(routes.len(), 1);
+        assert_eq!(routes[0].0, 12.0);
+
+        // pointing it the other way opens it up. petgraph visits undirected
+        // edges from whichever end the search is at, so this would fail if
+        // the orientation of the pair were ignored.
+        let forbidden = [(a2, a1)].into_iter().collect();
+        let routes = find_routes(&graph, start, goal, &forbidden);
+
+        assert_eq!(routes.len(), 2);
+        assert_eq!(routes[0].0, 10.0);
+        assert_eq!(routes[0].1, vec![start, a1, a2, goal]);
+    }
+
+    #[test]
+    fn find_routes_none() {
+        let mut graph = StableUnGraph::default();
+        let start = graph.add_node(entity(0));
+        let goal = graph.add_node(entity(1));
+
+        assert!(find_routes(&graph, start, goal, &HashSet::new()).is_empty());
+    }
+
+    fn road(points: &[(f32, f32)]) -> Vec<RoadSegment> {
+        points
+            .iter()
+            .tuple_windows()
+            .map(|(&a, &b)| test_util::segment(a, b))
+            .collect()
+    }
+
+    #[test]
+    fn faster_route_avoids_congestion() {
+        let top = road(&[(0.0, 0.0), (48.0, 48.0), (96.0, 48.0), (144.0, 0.0)]);
+        let bottom = road(&[(0.0, 0.0), (48.0, -48.0), (96.0, -48.0), (144.0, 0.0)]);
+        let routes = vec![top.clone(), bottom];
+
+        let mut congestion = SegmentCongestion::default();
+        assert_eq!(faster_route(&top, &routes, &congestion), None);
+
+        congestion.0.insert(segment_key(&top[1]), 5);
+        assert_eq!(faster_route(&top, &routes, &congestion), Some((1, 0)));
+    }
+
+    #[test]
+    fn faster_route_from_later_junction() {
+        let top = road(&[(-48.0, 0.0), (0.0, 0.0), (48.0, 48.0), (96.0, 0.0)]);
+        let bottom = road(&[(-48.0, 0.0), (0.0, 0.0), (48.0, -48.0), (96.0, 0.0)]);
+        let routes = vec![top.clone(), bottom];
+
+        let mut congestion = SegmentCongestion::default();
+        congestion.0.insert(segment_key(&top[1]), 5);
+
+        assert_eq!(faster_route(&top[1..], &routes, &congestion), Some((1, 1)));
+    }
+
+    #[test]
+    fn junctions_need_three_roads() {
+        let mut graph = StableUnGraph::default();
+
+        let (a1, a2) = segment(&mut graph, 0, 10.0);
+        let (b1, b2) = segment(&mut graph, 1, 10.0);
+        let (c1, c2) = segment(&mut graph, 2, 10.0);
+
+        // a and b continue into each other, and c joins them at their ends
+        graph.add_edge(a2, b1, 0.0);
+        let line = road(&[(0.0, 0.0), (48.0, 0.0), (96.0, 0.0)]);
+        let branch = road(&[(48.0, 0.0), (48.0, 48.0)]);
+        let segments = [(&line[0], a1, a2), (&line[1], b1, b2), (&branch[0], c1, c2)];
+
+        assert!(junctions(&graph, segments.into_iter()).is_empty());
+
+        graph.add_edge(c1, a2, 0.0);
+        graph.add_edge(c1, b1, 0.0);
+
+        let junctions = junctions(&graph, segments.into_iter());
+        assert_eq!(junctions.len(), 1);
+        assert!(junctions.contains(&junction_key(Vec2::new(48.0, 0.0), 1)));
+    }
+
+    #[test]
+    fn segment_key_direction() {
+        let forward = road(&[(0.0, 0.0), (48.0, 48.0)]);
+        let backward = road(&[(48.0, 48.0), (0.0, 0.0)]);
+
+        assert_eq!(segment_key(&forward[0]), segment_key(&backward[0]));
+    }
+
+    #[test]
+    fn segment_weight_layers() {
+        let points = (Vec2::ZERO, Vec2::new(10.0, 0.0));
+
+        assert_eq!(segment_weight(points, None, 1, RoadClass::Standard), 10.0);
+        assert!(
+            segment_weight(points, None, 2, RoadClass::Standard)
+                > segment_weight(points, None, 1, RoadClass::Standard)
+        );
+    }
+
+    #[test]
+    fn segment_weight_classes() {
+        let points = (Vec2::ZERO, Vec2::new(10.0, 0.0));
+
+        assert!(
+            segment_weight(points, None, 1, RoadClass::Fast)
+                < segment_weight(points, None, 1, RoadClass::Standard)
+        );
+        assert!(
+            segment_weight(points, None, 1, RoadClass::Slow)
+                > segment_weight(points, None, 1, RoadClass::Standard)
+        );
+    }
+}
@@ -0,0 +1,465 @@
+//! A record of every completed run of each level, so that players can see
+//! their progress and which changes helped.
+//!
+//! The score blends cost, time and delivered pixies into one number, so the
+//! best run along each of those axes is kept too, along with the runs that
+//! make up the Pareto front of all three.
+
+use bevy::{platform::collections::HashMap, prelude::*};
+
+use crate::{
+    deliveries::QuotasMet,
+    sim::{SimulationState, SimulationSteps},
+    traffic::TrafficControl,
+    update_score_system, AfterUpdate, Cost, PixieCount, RoadSegment, Score, ScoreCalc,
+    SelectedLevel,
+};
+
+/// The number of runs kept for each level. The oldest runs are forgotten
+/// first.
+pub const MAX_RUNS_PER_LEVEL: usize = 200;
+
+pub struct RunHistoryPlugin;
+impl Plugin for RunHistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            AfterUpdate,
+            record_run_system
+                .after(update_score_system)
+                .in_set(ScoreCalc),
+        );
+    }
+}
+
+/// Each level's completed runs, oldest first.
+#[derive(Resource, Clone, Debug, Default, Reflect)]
+pub struct RunHistory(pub HashMap<u32, Vec<RunRecord>>);
+
+#[derive(Clone, Debug, PartialEq, Reflect)]
+pub struct RunRecord {
+    /// Counts up from 1 for each level.
+    pub run: u32,
+    /// Seconds since the Unix epoch, or 0 if the time wasn't available.
+    pub timestamp: u64,
+    pub score: u32,
+    pub cost: u32,
+    /// Simulated seconds until the last pixie was delivered.
+    pub elapsed: f32,
+    pub delivered: u32,
+    pub quotas_met: bool,
+    /// Identifies the layout that was run. Runs of the same roads have the
+    /// same hash.
+    pub solution_hash: u64,
+}
+
+/// Each level's best runs along each axis. These outlive the runs that are
+/// forgotten from [`RunHistory`].
+#[derive(Resource, Clone, Debug, Default, Reflect)]
+pub struct RunBests(pub HashMap<u32, AxisBests>);
+
+/// The best runs that met their quotas along each axis.
+#[derive(Clone, Debug, Default, PartialEq, Reflect)]
+pub struct AxisBests {
+    pub cheapest: Option<RunRecord>,
+    pub fastest: Option<RunRecord>,
+    pub most_delivered: Option<RunRecord>,
+}
+
+/// The columns a run history can be sorted by.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RunColumn {
+    #[default]
+    Run,
+    Score,
+    Cost,
+    Elapsed,
+    Delivered,
+}
+
+impl RunHistory {
+    /// Adds a run to a level's history, numbering it and forgetting the oldest
+    /// runs if there are too many. Returns the numbered run.
+    pub fn push(&mut self, level: u32, mut record: RunRecord) -> &RunRecord {
+        let runs = self.0.entry(level).or_default();
+
+        record.run = runs.last().map_or(1, |last| last.run + 1);
+        runs.push(record);
+
+        if runs.len() > MAX_RUNS_PER_LEVEL {
+            runs.drain(..runs.len() - MAX_RUNS_PER_LEVEL);
+        }
+
+        runs.last().unwrap()
+    }
+}
+
+impl RunRecord {
+    /// Whether both records describe the same run, even if they were numbered
+    /// differently.
+    pub fn is_same_run(&self, other: &RunRecord) -> bool {
+        RunRecord {
+            run: other.run,
+            ..self.clone()
+        } == *other
+    }
+}
+
+impl AxisBests {
+    /// Keeps `record` along each axis that it beats the best run so far on.
+    pub fn record(&mut self, record: &RunRecord) {
+        // a run that didn't deliver enough pixies doesn't count
+        if !record.quotas_met {
+            return;
+        }
+
+        if self.cheapest.as_ref().is_none_or(|b| record.cost < b.cost) {
+            self.cheapest = Some(record.clone());
+        }
+        if self
+            .fastest
+            .as_ref()
+            .is_none_or(|b| record.elapsed < b.elapsed)
+        {
+            self.fastest = Some(record.clone());
+        }
+        if self
+            .most_delivered
+            .as_ref()
+            .is_none_or(|b| record.delivered > b.delivered)
+        {
+            self.most_delivered = Some(record.clone());
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &RunRecord> {
+        [&self.cheapest, &self.fastest, &self.most_delivered]
+            .into_iter()
+            .flatten()
+    }
+}
+
+/// Whether `a` is at least as good as `b` on cost, time and delivered pixies,
+/// and better on at least one of them.
+fn dominates(a: &RunRecord, b: &RunRecord) -> bool {
+    a.cost <= b.cost
+        && a.elapsed <= b.elapsed
+        && a.delivered >= b.delivered
+        && (a.cost < b.cost || a.elapsed < b.elapsed || a.delivered > b.delivered)
+}
+
+/// Returns the runs that met their quotas and that no other such run beats
+/// on cost, time and delivered pixies at once, cheapest first. Of several runs
+/// that tie on all three, only the earliest is returned.
+pub fn pareto_front<'a>(runs: impl IntoIterator<Item = &'a RunRecord>) -> Vec<&'a RunRecord> {
+    let working = runs
+        .into_iter()
+        .filter(|r| r.quotas_met)
+        .collect::<Vec<_>>();
+
+    let mut front = working
+        .iter()
+        .filter(|r| {
+            !working.iter().any(|o| {
+                dominates(o, r)
+                    || (o.run < r.run
+                        && o.cost == r.cost
+                        && o.elapsed == r.elapsed
+                        && o.delivered == r.delivered)
+            })
+        })
+        .copied()
+        .collect::<Vec<_>>();
+
+    front.sort_by(|a, b| a.cost.cmp(&b.cost).then(a.elapsed.total_cmp(&b.elapsed)));
+
+    front
+}
+
+/// Returns `runs` sorted by `column`.
+pub fn sorted_runs(runs: &[RunRecord], column: RunColumn, descending: bool) -> Vec<&RunRecord> {
+    let mut sorted = runs.iter().collect::<Vec<_>>();
+
+    sorted.sort_by(|a, b| {
+        let ordering = match column {
+            RunColumn::Run => a.run.cmp(&b.run),
+            RunColumn::Score => a.score.cmp(&b.score),
+            RunColumn::Cost => a.cost.cmp(&b.cost),
+            RunColumn::Elapsed => a.elapsed.total_cmp(&b.elapsed),
+            RunColumn::Delivered => a.delivered.cmp(&b.delivered),
+        };
+
+        if descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+
+    sorted
+}
+
+/// Returns a hash of a layout's roads and traffic controls that doesn't depend
+/// on the order they were built in, and that is the same on every platform.
+pub fn solution_hash<'a>(
+    segments: impl IntoIterator<Item = &'a RoadSegment>,
+    controls: impl IntoIterator<Item = &'a TrafficControl>,
+) -> u64 {
+    let segments = segments.into_iter().map(|s| {
+        // two-way roads may have been drawn in either direction
+        let (a, b) = if !s.one_way && (s.points.1.x, s.points.1.y) < (s.points.0.x, s.points.0.y) {
+            (s.points.1, s.points.0)
+        } else {
+            s.points
+        };
+
+        fnv1a([
+            a.x.to_bits() as u64,
+            a.y.to_bits() as u64,
+            b.x.to_bits() as u64,
+            b.y.to_bits() as u64,
+            s.layer as u64,
+            s.one_way as u64,
+            s.class as u64,
+            s.arc.map_or(0, |c| c.x.to_bits() as u64 + 1),
+            s.arc.map_or(0, |c| c.y.to_bits() as u64 + 1),
+        ])
+    });
+    let controls = controls.into_iter().map(|c| {
+        fnv1a([
+            c.kind as u64,
+            c.point.x.to_bits() as u64,
+            c.point.y.to_bits() as u64,
+            c.layer as u64,
+        ])
+    });
+
+    segments
+        .chain(controls)
+        .fold(0, |hash: u64, item| hash.wrapping_add(item))
+}
+
+fn fnv1a(values: impl IntoIterator<Item = u64>) -> u64 {
+    values
+        .into_iter()
+        .flat_map(u64::to_le_bytes)
+        .fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        })
+}
+
+/// Formats a timestamp as a UTC date and time.
+pub fn format_timestamp(timestamp: u64) -> String {
+    if timestamp == 0 {
+        return "-".to_string();
+    }
+
+    let days = (timestamp / 86400) as i64;
+    let minutes = (timestamp % 86400) / 60;
+
+    // https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year}-{month:02}-{day:02} {:02}:{:02}",
+        minutes / 60,
+        minutes % 60
+    )
+}
+
+fn now() -> u64 {
+    // `SystemTime` panics in the browser, so ask JavaScript instead.
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs())
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        (js_sys::Date::now() / 1000.0) as u64
+    }
+}
+
+fn record_run_system(
+    sim_state: Res<SimulationState>,
+    sim_steps: Res<SimulationSteps>,
+    score: Res<Score>,
+    quotas: Res<QuotasMet>,
+    cost: Res<Cost>,
+    pixie_count: Res<PixieCount>,
+    selected_level: Res<SelectedLevel>,
+    mut history: ResMut<RunHistory>,
+    mut bests: ResMut<RunBests>,
+    q_segments: Query<&RoadSegment>,
+    q_controls: Query<&TrafficControl>,
+) {
+    if !sim_state.is_changed() || *sim_state != SimulationState::Finished {
+        return;
+    }
+
+    let Some(score) = score.0 else {
+        return;
+    };
+
+    let record = history.push(
+        selected_level.0,
+        RunRecord {
+            run: 0,
+            timestamp: now(),
+            score,
+            cost: cost.0,
+            elapsed: sim_steps.get_elapsed_f32(),
+            delivered: pixie_count.0,
+            quotas_met: quotas.0,
+            solution_hash: solution_hash(&q_segments, &q_controls),
+        },
+    );
+
+    bests.0.entry(selected_level.0).or_default().record(record);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::segment;
+
+    fn record(score: u32, elapsed: f32) -> RunRecord {
+        RunRecord {
+            run: 0,
+            timestamp: 0,
+            score,
+            cost: 10,
+            elapsed,
+            delivered: 50,
+            quotas_met: true,
+            solution_hash: 0,
+        }
+    }
+
+    fn run(run: u32, cost: u32, elapsed: f32, delivered: u32) -> RunRecord {
+        RunRecord {
+            run,
+            cost,
+            delivered,
+            ..record(0, elapsed)
+        }
+    }
+
+    #[test]
+    fn push_numbers_and_trims() {
+        let mut history = RunHistory::default();
+
+        for i in 0..MAX_RUNS_PER_LEVEL + 5 {
+            history.push(1, record(i as u32, 1.0));
+        }
+        history.push(2, record(0, 1.0));
+
+        let runs = &history.0[&1];
+        assert_eq!(runs.len(), MAX_RUNS_PER_LEVEL);
+        assert_eq!(runs[0].run, 6);
+        assert_eq!(runs.last().unwrap().run, MAX_RUNS_PER_LEVEL as u32 + 5);
+        assert_eq!(history.0[&2][0].run, 1);
+    }
+
+    #[test]
+    fn sort_by_column() {
+        let mut history = RunHistory::default();
+        history.push(1, record(30, 9.0));
+        history.push(1, record(50, 7.0));
+        history.push(1, record(40, 8.0));
+        let runs = &history.0[&1];
+
+        let by_score = sorted_runs(runs, RunColumn::Score, true);
+        assert_eq!(
+            by_score.iter().map(|r| r.run).collect::<Vec<_>>(),
+            vec![2, 3, 1]
+        );
+
+        let by_elapsed = sorted_runs(runs, RunColumn::Elapsed, false);
+        assert_eq!(
+            by_elapsed.iter().map(|r| r.run).collect::<Vec<_>>(),
+            vec![2, 3, 1]
+        );
+
+        let by_run = sorted_runs(runs, RunColumn::Run, true);
+        assert_eq!(
+            by_run.iter().map(|r| r.run).collect::<Vec<_>>(),
+            vec![3, 2, 1]
+        );
+    }
+
+    #[test]
+    fn axis_bests() {
+        let mut bests = AxisBests::default();
+        bests.record(&run(1, 20, 10.0, 50));
+        bests.record(&run(2, 10, 12.0, 50));
+        bests.record(&run(3, 30, 8.0, 60));
+        bests.record(&RunRecord {
+            quotas_met: false,
+            ..run(4, 5, 5.0, 70)
+        });
+
+        assert_eq!(bests.cheapest.as_ref().map(|r| r.run), Some(2));
+        assert_eq!(bests.fastest.as_ref().map(|r| r.run), Some(3));
+        assert_eq!(bests.most_delivered.as_ref().map(|r| r.run), Some(3));
+    }
+
+    #[test]
+    fn pareto_front_of_runs() {
+        let runs = [
+            run(1, 20, 10.0, 50),
+            // beaten by run 1 on everything
+            run(2, 25, 11.0, 50),
+            run(3, 10, 12.0, 50),
+            run(4, 30, 8.0, 50),
+            // slower and more expensive, but delivers more
+            run(5, 40, 12.0, 60),
+            // the same as run 1
+            run(6, 20, 10.0, 50),
+            RunRecord {
+                quotas_met: false,
+                ..run(7, 5, 5.0, 70)
+            },
+        ];
+
+        let front = pareto_front(&runs);
+
+        assert_eq!(
+            front.iter().map(|r| r.run).collect::<Vec<_>>(),
+            vec![3, 1, 4, 5]
+        );
+    }
+
+    #[test]
+    fn hash_ignores_order_and_direction() {
+        let a = [
+            segment((0.0, 0.0), (48.0, 0.0)),
+            segment((48.0, 0.0), (48.0, 48.0)),
+        ];
+        let b = [
+            segment((48.0, 48.0), (48.0, 0.0)),
+            segment((0.0, 0.0), (48.0, 0.0)),
+        ];
+        let c = [segment((0.0, 0.0), (48.0, 0.0))];
+        let controls: [TrafficControl; 0] = [];
+
+        assert_eq!(solution_hash(&a, &controls), solution_hash(&b, &controls));
+        assert_ne!(solution_hash(&a, &controls), solution_hash(&c, &controls));
+    }
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(format_timestamp(0), "-");
+        assert_eq!(format_timestamp(86400), "1970-01-02 00:00");
+        assert_eq!(format_timestamp(1_700_000_000), "2023-11-14 22:13");
+        assert_eq!(format_timestamp(951_782_400), "2000-02-29 00:00");
+    }
+}
@@ -1,18 +1,81 @@
-use crate::RoadSegment;
+use std::collections::{BTreeMap, HashSet};
 
-use bevy::{audio::Volume, platform::collections::HashMap, prelude::*};
+use crate::{
+    lines::is_arc,
+    road_class::RoadClass,
+    run_history::{RunBests, RunHistory},
+    solution_slots::SolutionSlots,
+    traffic::{TrafficControl, TrafficControlKind},
+    RoadSegment,
+};
+
+use bevy::{
+    asset::ron::{self, value::RawValue},
+    audio::Volume,
+    platform::collections::HashMap,
+    prelude::*,
+    reflect::{
+        serde::{
+            DeserializeWithRegistry, ReflectDeserializeWithRegistry, TypedReflectDeserializer,
+        },
+        DynamicStruct, TypeInfo, TypeRegistry, Typed,
+    },
+};
 use bevy_simple_prefs::{Prefs, PrefsPlugin};
+use serde::{
+    de::{DeserializeOwned, DeserializeSeed, Error as _, MapAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
+
+/// Upgrades save data written by older versions of the game. `MIGRATIONS[n]`
+/// upgrades version `n` data to version `n + 1`.
+///
+/// Migrations work on the raw RON of each field of the save file before it's
+/// deserialized, so they can read layouts that the current types can't. When
+/// the save file's layout or meaning changes, append a migration here rather
+/// than editing an old one. Fields that are only added should also be marked
+/// `#[serde(default)]` or `#[reflect(default)]` so that older files still
+/// deserialize.
+const MIGRATIONS: &[Migration] = &[migrate_unversioned, migrate_arc_chords];
+/// The version of save data written by this version of the game.
+pub const SAVE_VERSION: u32 = MIGRATIONS.len() as u32;
+
+type Migration = fn(&mut SaveContents) -> ron::Result<()>;
 
 #[derive(Prefs, Reflect, Default)]
+#[reflect(DeserializeWithRegistry)]
 pub struct SaveFile {
+    #[reflect(default)]
+    version: SaveVersion,
     scores: BestScores,
     solutions: Solutions,
     music_volume: MusicVolume,
+    #[reflect(default)]
+    slots: SolutionSlots,
+    #[reflect(default)]
+    best_solutions: BestSolutions,
+    #[reflect(default)]
+    runs: RunHistory,
+    #[reflect(default)]
+    run_bests: RunBests,
+}
+/// The version of the loaded save data. Files written before save data was
+/// versioned have no version and are treated as version 0, but new save data
+/// starts out at [`SAVE_VERSION`].
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub struct SaveVersion(pub u32);
+impl Default for SaveVersion {
+    fn default() -> Self {
+        Self(SAVE_VERSION)
+    }
 }
 #[derive(Resource, Clone, Debug, Default, Reflect)]
 pub struct BestScores(pub HashMap<u32, u32>);
 #[derive(Resource, Clone, Debug, Default, Reflect)]
 pub struct Solutions(pub HashMap<u32, Solution>);
+/// The solutions that earned each of the [`BestScores`].
+#[derive(Resource, Clone, Debug, Default, Reflect)]
+pub struct BestSolutions(pub HashMap<u32, Solution>);
 
 #[derive(Resource, Reflect, Clone, Copy, Eq, PartialEq, Debug)]
 pub struct MusicVolume(pub u8);
@@ -37,9 +100,487 @@ impl MusicVolume {
         self.0 == 0
     }
 }
-#[derive(Clone, Debug, Default, Reflect)]
+/// A level's solution. This is stored as a [`StoredSolution`] so that
+/// [`RoadSegment`] can change without breaking existing save files.
+#[derive(Clone, Debug, Default, PartialEq, Reflect, Serialize, Deserialize)]
+#[reflect(Serialize, Deserialize)]
+#[serde(from = "StoredSolution", into = "StoredSolution")]
 pub struct Solution {
     pub segments: Vec<RoadSegment>,
+    #[reflect(default)]
+    pub controls: Vec<TrafficControl>,
+}
+
+impl Solution {
+    /// Whether both solutions have the same roads and traffic controls, in
+    /// any order.
+    pub fn matches(&self, other: &Solution) -> bool {
+        self.segments.len() == other.segments.len()
+            && self.controls.len() == other.controls.len()
+            && self.segments.iter().all(|s| other.segments.contains(s))
+            && self.controls.iter().all(|c| other.controls.contains(c))
+    }
+}
+
+/// The layout of a solution in the save file. This may only change along with
+/// [`SAVE_VERSION`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct StoredSolution {
+    segments: Vec<StoredSegment>,
+    #[serde(default)]
+    controls: Vec<StoredControl>,
+}
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct StoredSegment {
+    points: (Vec2, Vec2),
+    layer: u32,
+    #[serde(default)]
+    one_way: bool,
+    #[serde(default)]
+    class: RoadClass,
+    #[serde(default)]
+    arc: Option<Vec2>,
+}
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct StoredControl {
+    kind: TrafficControlKind,
+    point: Vec2,
+    layer: u32,
+}
+
+impl From<StoredSolution> for Solution {
+    fn from(stored: StoredSolution) -> Self {
+        Self {
+            segments: stored
+                .segments
+                .into_iter()
+                .map(|s| RoadSegment {
+                    points: s.points,
+                    layer: s.layer,
+                    one_way: s.one_way,
+                    class: s.class,
+                    arc: s.arc,
+                })
+                .collect(),
+            controls: stored
+                .controls
+                .into_iter()
+                .map(|c| TrafficControl {
+                    kind: c.kind,
+                    point: c.point,
+                    layer: c.layer,
+                })
+                .collect(),
+        }
+    }
+}
+impl From<Solution> for StoredSolution {
+    fn from(solution: Solution) -> Self {
+        Self {
+            segments: solution
+                .segments
+                .into_iter()
+                .map(|s| StoredSegment {
+                    points: s.points,
+                    layer: s.layer,
+                    one_way: s.one_way,
+                    class: s.class,
+                    arc: s.arc,
+                })
+                .collect(),
+            controls: solution
+                .controls
+                .into_iter()
+                .map(|c| StoredControl {
+                    kind: c.kind,
+                    point: c.point,
+                    layer: c.layer,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl<'de> DeserializeWithRegistry<'de> for SaveFile {
+    fn deserialize<D>(deserializer: D, registry: &TypeRegistry) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mut contents = deserializer.deserialize_struct("SaveFile", &[], SaveContentsVisitor)?;
+
+        let version = contents.version().map_err(D::Error::custom)?;
+        match migrate(version, &mut contents) {
+            Ok(()) if version < SAVE_VERSION => {
+                info!("Migrated save data from version {version} to {SAVE_VERSION}");
+            }
+            Ok(()) => {}
+            Err(MigrationError::Newer(newer)) => {
+                // read what we can, but keep the version in case the player
+                // goes back to the newer version
+                warn!("Save data is from a newer version of the game ({newer} > {SAVE_VERSION})");
+            }
+            Err(MigrationError::Ron(e)) => {
+                return Err(D::Error::custom(format!(
+                    "failed to migrate save data from version {version}: {e}"
+                )));
+            }
+        }
+
+        let TypeInfo::Struct(info) = SaveFile::type_info() else {
+            unreachable!("SaveFile is a struct");
+        };
+
+        let mut fields = DynamicStruct::default();
+        for field in info.iter() {
+            let Some(raw) = contents.0.get(field.name()) else {
+                continue;
+            };
+            let Some(registration) = registry.get(field.type_id()) else {
+                continue;
+            };
+
+            let value = ron::Deserializer::from_str(raw).and_then(|mut deserializer| {
+                TypedReflectDeserializer::new(registration, registry)
+                    .deserialize(&mut deserializer)
+                    .map_err(|e| deserializer.span_error(e))
+            });
+
+            // a field that can't be read is reset rather than losing the rest
+            // of the save file along with it
+            match value {
+                Ok(value) => fields.insert_boxed(field.name(), value),
+                Err(e) => warn!("Failed to read {} from save data: {e}", field.name()),
+            }
+        }
+
+        let mut save = SaveFile::default();
+        save.try_apply(&fields).map_err(D::Error::custom)?;
+
+        Ok(save)
+    }
+}
+
+/// The fields of a save file as raw RON, keyed by name, before they're
+/// deserialized. Migrations upgrade these so that they can be read as the
+/// fields of the current [`SaveFile`].
+#[derive(Debug, Default)]
+pub struct SaveContents(pub BTreeMap<String, String>);
+
+impl SaveContents {
+    /// The version of the save data. Files written before save data was
+    /// versioned have no version and are treated as version 0.
+    fn version(&self) -> Result<u32, ron::error::SpannedError> {
+        match self.0.get("version") {
+            Some(raw) => Ok(ron::from_str::<SaveVersion>(raw)?.0),
+            None => Ok(0),
+        }
+    }
+
+    /// Rewrites a field, if the file has one, by reading it as `T` and writing
+    /// what `f` returns in its place.
+    fn update<T, U>(&mut self, field: &str, f: impl FnOnce(T) -> U) -> ron::Result<()>
+    where
+        T: DeserializeOwned,
+        U: Serialize,
+    {
+        let Some(raw) = self.0.get_mut(field) else {
+            return Ok(());
+        };
+
+        // each field of the save file is a newtype resource
+        let Newtype(value) = ron::from_str(raw).map_err(|e| e.code)?;
+        *raw = ron::to_string(&Newtype(f(value)))?;
+
+        Ok(())
+    }
+}
+
+struct SaveContentsVisitor;
+impl<'de> Visitor<'de> for SaveContentsVisitor {
+    type Value = SaveContents;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("save data")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut contents = SaveContents::default();
+
+        while let Some(field) = map.next_key::<String>()? {
+            let raw = map.next_value::<Box<RawValue>>()?;
+            contents.0.insert(field, raw.get_ron().to_string());
+        }
+
+        Ok(contents)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Newtype<T>(T);
+
+#[derive(Debug)]
+pub enum MigrationError {
+    /// The save data is from a newer version of the game.
+    Newer(u32),
+    Ron(ron::Error),
+}
+
+impl From<ron::Error> for MigrationError {
+    fn from(e: ron::Error) -> Self {
+        Self::Ron(e)
+    }
+}
+
+/// Upgrades `contents` from `version` to [`SAVE_VERSION`].
+pub fn migrate(version: u32, contents: &mut SaveContents) -> Result<(), MigrationError> {
+    migrate_with(MIGRATIONS, version, contents)
+}
+
+fn migrate_with(
+    migrations: &[Migration],
+    version: u32,
+    contents: &mut SaveContents,
+) -> Result<(), MigrationError> {
+    let Some(pending) = migrations.get(version as usize..) else {
+        return Err(MigrationError::Newer(version));
+    };
+
+    for migration in pending {
+        migration(contents)?;
+    }
+
+    if !pending.is_empty() {
+        let version = Newtype(migrations.len() as u32);
+        contents
+            .0
+            .insert("version".to_string(), ron::to_string(&version)?);
+    }
+
+    Ok(())
+}
+
+/// Save files written before versioning have the same layout as version 1.
+fn migrate_unversioned(_contents: &mut SaveContents) -> ron::Result<()> {
+    Ok(())
+}
+
+/// Version 1 drew a rounded corner as a run of short straight segments, each
+/// marked with the corner's `arc_radius`. Version 2 draws it as one segment
+/// that stores the center of its arc.
+fn migrate_arc_chords(contents: &mut SaveContents) -> ron::Result<()> {
+    let join = |solutions: BTreeMap<u32, v1::Solution>| {
+        solutions
+            .into_iter()
+            .map(|(level, solution)| (level, solution.join_chords()))
+            .collect::<BTreeMap<_, _>>()
+    };
+
+    contents.update("solutions", join)?;
+    contents.update("best_solutions", join)?;
+    contents.update("slots", |slots: BTreeMap<u32, v1::LevelSlots>| {
+        slots
+            .into_iter()
+            .map(|(level, slots)| (level, slots.join_chords()))
+            .collect::<BTreeMap<_, _>>()
+    })
+}
+
+/// The parts of the version 1 save file layout that have changed since.
+mod v1 {
+    use itertools::Itertools;
+
+    use super::*;
+
+    #[derive(Deserialize)]
+    pub struct Solution {
+        segments: Vec<Segment>,
+        #[serde(default)]
+        controls: Vec<StoredControl>,
+    }
+    #[derive(Deserialize)]
+    struct Segment {
+        points: (Vec2, Vec2),
+        layer: u32,
+        #[serde(default)]
+        one_way: bool,
+        #[serde(default)]
+        class: RoadClass,
+        #[serde(default)]
+        arc_radius: Option<f32>,
+        /// Only present if the file was already written in a later version
+        /// without its version being recorded.
+        #[serde(default)]
+        arc: Option<Vec2>,
+    }
+    #[derive(Deserialize)]
+    pub struct LevelSlots {
+        active: Option<usize>,
+        slots: Vec<SolutionSlot>,
+    }
+    #[derive(Deserialize)]
+    struct SolutionSlot {
+        name: String,
+        solution: Solution,
+        score: Option<u32>,
+    }
+    #[derive(Serialize)]
+    pub struct JoinedLevelSlots {
+        active: Option<usize>,
+        slots: Vec<JoinedSolutionSlot>,
+    }
+    #[derive(Serialize)]
+    struct JoinedSolutionSlot {
+        name: String,
+        solution: super::Solution,
+        score: Option<u32>,
+    }
+
+    impl LevelSlots {
+        pub fn join_chords(self) -> JoinedLevelSlots {
+            JoinedLevelSlots {
+                active: self.active,
+                slots: self
+                    .slots
+                    .into_iter()
+                    .map(|slot| JoinedSolutionSlot {
+                        name: slot.name,
+                        solution: slot.solution.join_chords(),
+                        score: slot.score,
+                    })
+                    .collect(),
+            }
+        }
+    }
+
+    impl Segment {
+        fn road(&self, points: (Vec2, Vec2), arc: Option<Vec2>) -> RoadSegment {
+            RoadSegment {
+                points,
+                layer: self.layer,
+                one_way: self.one_way,
+                class: self.class,
+                arc,
+            }
+        }
+
+        fn continues(&self, other: &Segment) -> bool {
+            self.layer == other.layer
+                && self.one_way == other.one_way
+                && self.class == other.class
+                && self.arc_radius == other.arc_radius
+        }
+    }
+
+    impl Solution {
+        /// Replaces each run of arc chords with a single arc. Chords that
+        /// don't make up an arc are kept as straight segments.
+        pub fn join_chords(self) -> super::Solution {
+            let touching = |point: Vec2| {
+                self.segments
+                    .iter()
+                    .enumerate()
+                    .filter(move |(_, s)| {
+                        s.points.0.distance(point) < 0.01 || s.points.1.distance(point) < 0.01
+                    })
+                    .map(|(i, _)| i)
+            };
+
+            // follows the run of chords that continues from `end` of `from`,
+            // stopping at junctions
+            let next = |from: usize, end: Vec2, forwards: bool, joined: &HashSet<usize>| {
+                let (a, b) = touching(end).collect_tuple()?;
+                let i = if a == from { b } else { a };
+                let segment = &self.segments[i];
+
+                if joined.contains(&i) || !segment.continues(&self.segments[from]) {
+                    return None;
+                }
+
+                let (start, other) = if segment.points.0.distance(end) < 0.01 {
+                    (segment.points.0, segment.points.1)
+                } else {
+                    (segment.points.1, segment.points.0)
+                };
+
+                // one-way chords must keep their direction
+                if segment.one_way && (start == segment.points.0) != forwards {
+                    return None;
+                }
+
+                Some((i, other))
+            };
+
+            let mut joined = HashSet::new();
+            let mut segments = vec![];
+
+            for (i, segment) in self.segments.iter().enumerate() {
+                if joined.contains(&i) {
+                    continue;
+                }
+                joined.insert(i);
+
+                let Some(radius) = segment.arc_radius else {
+                    segments.push(segment.road(segment.points, segment.arc));
+                    continue;
+                };
+
+                let mut run = vec![segment.points.0, segment.points.1];
+                let (mut last, mut end) = (i, segment.points.1);
+                while let Some((j, other)) = next(last, end, true, &joined) {
+                    joined.insert(j);
+                    run.push(other);
+                    (last, end) = (j, other);
+                }
+                let (mut last, mut start) = (i, segment.points.0);
+                while let Some((j, other)) = next(last, start, false, &joined) {
+                    joined.insert(j);
+                    run.insert(0, other);
+                    (last, start) = (j, other);
+                }
+
+                let points = (run[0], run[run.len() - 1]);
+                let arc =
+                    circumcenter(run[0], run[run.len() / 2], run[run.len() - 1]).filter(|center| {
+                        run.len() > 2
+                            && is_arc(points, *center)
+                            && run
+                                .iter()
+                                .all(|p| (p.distance(*center) - radius).abs() < 0.1)
+                    });
+
+                match arc {
+                    Some(center) => segments.push(segment.road(points, Some(center))),
+                    None => segments.extend(
+                        run.iter()
+                            .tuple_windows()
+                            .map(|(a, b)| segment.road((*a, *b), None)),
+                    ),
+                }
+            }
+
+            super::Solution {
+                segments,
+                controls: self
+                    .controls
+                    .into_iter()
+                    .map(|c| TrafficControl {
+                        kind: c.kind,
+                        point: c.point,
+                        layer: c.layer,
+                    })
+                    .collect(),
+            }
+        }
+    }
+
+    /// The center of the circle through three points.
+    fn circumcenter(a: Vec2, b: Vec2, c: Vec2) -> Option<Vec2> {
+        let (ab, ac) = (b - a, c - a);
+        let d = 2.0 * ab.perp_dot(ac);
+
+        (d.abs() > f32::EPSILON)
+            .then(|| a + (ab.perp() * ac.length_squared() - ac.perp() * ab.length_squared()) / d)
+    }
 }
 
 pub struct SavePlugin;
@@ -48,3 +589,174 @@ impl Plugin for SavePlugin {
         app.add_plugins(PrefsPlugin::<SaveFile>::default());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::{asset::ron, reflect::serde::TypedReflectSerializer};
+
+    #[test]
+    fn reads_unversioned_solutions() {
+        // the layout written when `Solution` was reflected field by field
+        let solution: Solution = ron::from_str(
+            "(segments: [(points: ((0.0, 0.0), (48.0, 0.0)), layer: 1)], controls: [])",
+        )
+        .unwrap();
+
+        assert_eq!(solution.segments.len(), 1);
+        assert_eq!(solution.segments[0].points.1, Vec2::new(48.0, 0.0));
+        assert_eq!(solution.segments[0].class, RoadClass::Standard);
+    }
+
+    #[test]
+    fn solution_round_trip() {
+        let solution = Solution {
+            segments: vec![RoadSegment {
+                points: (Vec2::ZERO, Vec2::new(48.0, 48.0)),
+                layer: 2,
+                one_way: true,
+                class: RoadClass::Fast,
+                arc: Some(Vec2::new(0.0, 48.0)),
+            }],
+            controls: vec![TrafficControl {
+                kind: TrafficControlKind::Yield,
+                point: Vec2::ZERO,
+                layer: 2,
+            }],
+        };
+
+        let text = ron::to_string(&solution).unwrap();
+        assert_eq!(ron::from_str::<Solution>(&text).unwrap(), solution);
+    }
+
+    #[test]
+    fn migrations_run_in_order_from_version() {
+        fn a(contents: &mut SaveContents) -> ron::Result<()> {
+            contents.0.entry("log".to_string()).or_default().push('a');
+            Ok(())
+        }
+        fn b(contents: &mut SaveContents) -> ron::Result<()> {
+            contents.0.entry("log".to_string()).or_default().push('b');
+            Ok(())
+        }
+        let migrations: &[Migration] = &[a, b];
+
+        let mut contents = SaveContents::default();
+        migrate_with(migrations, 0, &mut contents).unwrap();
+        assert_eq!(contents.0["log"], "ab");
+        assert_eq!(contents.version().unwrap(), 2);
+
+        let mut contents = SaveContents::default();
+        migrate_with(migrations, 1, &mut contents).unwrap();
+        assert_eq!(contents.0["log"], "b");
+
+        let mut contents = SaveContents::default();
+        migrate_with(migrations, 2, &mut contents).unwrap();
+        assert!(!contents.0.contains_key("log"));
+
+        assert!(matches!(
+            migrate_with(migrations, 3, &mut contents),
+            Err(MigrationError::Newer(3))
+        ));
+    }
+
+    #[test]
+    fn joins_arc_chords() {
+        // a version 1 rounded corner around the origin, between straight legs
+        let chord = |a: f32, b: f32| {
+            let point = |degrees: f32| Vec2::from_angle(degrees.to_radians()) * 96.0;
+            format!(
+                "(points: (({}, {}), ({}, {})), layer: 1, arc_radius: Some(96.0))",
+                point(a).x,
+                point(a).y,
+                point(b).x,
+                point(b).y
+            )
+        };
+        let mut contents = SaveContents::default();
+        contents.0.insert(
+            "solutions".to_string(),
+            format!(
+                "({{1: (segments: [(points: ((96.0, -96.0), (96.0, 0.0)), layer: 1), {}, {}, {}], controls: [])}})",
+                chord(60.0, 90.0),
+                chord(0.0, 30.0),
+                chord(30.0, 60.0),
+            ),
+        );
+
+        migrate_arc_chords(&mut contents).unwrap();
+
+        let Newtype(solutions): Newtype<BTreeMap<u32, Solution>> =
+            ron::from_str(&contents.0["solutions"]).unwrap();
+        let segments = &solutions[&1].segments;
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].arc, None);
+
+        let arc = &segments[1];
+        assert!(arc.points.0.abs_diff_eq(Vec2::new(96.0, 0.0), 0.01));
+        assert!(arc.points.1.abs_diff_eq(Vec2::new(0.0, 96.0), 0.01));
+        assert!(arc.arc.unwrap().abs_diff_eq(Vec2::ZERO, 0.01));
+    }
+
+    #[test]
+    fn reads_save_file_fields_after_migrating() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<SaveFile>();
+
+        // an unversioned file with a field that no longer exists
+        let text = "(scores: ({1: 10}), music_volume: (20), removed: [1, 2])";
+        let registration = registry.get(std::any::TypeId::of::<SaveFile>()).unwrap();
+        let value = TypedReflectDeserializer::new(registration, &registry)
+            .deserialize(&mut ron::Deserializer::from_str(text).unwrap())
+            .unwrap();
+        let save = SaveFile::from_reflect(value.as_ref()).unwrap();
+
+        assert_eq!(save.version, SaveVersion(SAVE_VERSION));
+        assert_eq!(save.scores.0.get(&1), Some(&10));
+        assert_eq!(save.music_volume, MusicVolume(20));
+        assert!(save.solutions.0.is_empty());
+    }
+
+    #[test]
+    fn new_save_data_round_trips_with_arcs() {
+        let mut registry = TypeRegistry::default();
+        registry.register::<SaveFile>();
+        let registration = registry.get(std::any::TypeId::of::<SaveFile>()).unwrap();
+
+        let arc = RoadSegment {
+            points: (Vec2::new(96.0, 0.0), Vec2::new(0.0, 96.0)),
+            layer: 1,
+            one_way: false,
+            class: RoadClass::Standard,
+            arc: Some(Vec2::ZERO),
+        };
+        let mut save = SaveFile::default();
+        save.solutions.0.insert(
+            1,
+            Solution {
+                segments: vec![arc.clone()],
+                controls: vec![],
+            },
+        );
+        assert_eq!(save.version, SaveVersion(SAVE_VERSION));
+
+        let text = ron::to_string(&TypedReflectSerializer::new(&save, &registry)).unwrap();
+        let value = TypedReflectDeserializer::new(registration, &registry)
+            .deserialize(&mut ron::Deserializer::from_str(&text).unwrap())
+            .unwrap();
+        let loaded = SaveFile::from_reflect(value.as_ref()).unwrap();
+
+        assert_eq!(loaded.version, SaveVersion(SAVE_VERSION));
+        assert_eq!(loaded.solutions.0[&1].segments, vec![arc.clone()]);
+
+        // a file that lost its version is migrated again, but keeps its arcs
+        let unversioned = text.replacen(&format!("version:({SAVE_VERSION}),"), "", 1);
+        assert_ne!(unversioned, text);
+        let value = TypedReflectDeserializer::new(registration, &registry)
+            .deserialize(&mut ron::Deserializer::from_str(&unversioned).unwrap())
+            .unwrap();
+        let loaded = SaveFile::from_reflect(value.as_ref()).unwrap();
+
+        assert_eq!(loaded.solutions.0[&1].segments, vec![arc]);
+    }
+}
@@ -0,0 +1,120 @@
+use bevy::{
+    platform::collections::{HashMap, HashSet},
+    prelude::*,
+};
+use bevy_prototype_lyon::prelude::*;
+
+use crate::{
+    layer, lines::midpoint, pixie::PixieFlavor, road_path, sim::SimulationState, theme,
+    AfterUpdate, GameState, PathfindingState, RoadSegment, ScoreCalc,
+};
+
+pub struct SharedRoadsPlugin;
+impl Plugin for SharedRoadsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            AfterUpdate,
+            shared_road_indicator_system
+                .after(ScoreCalc)
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+/// Marks a road segment that pixies of more than one color will travel along.
+#[derive(Component)]
+struct SharedRoadIndicator;
+
+/// Given the road segment entities that make up each path, returns the
+/// segments that are traveled by pixies of more than one color.
+pub fn shared_segments(paths: &[(PixieFlavor, Vec<Entity>)]) -> Vec<Entity> {
+    let mut colors: HashMap<Entity, HashSet<u32>> = HashMap::new();
+
+    for (flavor, segments) in paths.iter() {
+        for segment in segments.iter() {
+            colors.entry(*segment).or_default().insert(flavor.color);
+        }
+    }
+
+    let mut shared = colors
+        .into_iter()
+        .filter(|(_, colors)| colors.len() > 1)
+        .map(|(segment, _)| segment)
+        .collect::<Vec<_>>();
+    shared.sort();
+
+    shared
+}
+
+fn shared_road_indicator_system(
+    mut commands: Commands,
+    pathfinding: Res<PathfindingState>,
+    sim_state: Res<SimulationState>,
+    q_segments: Query<&RoadSegment>,
+    q_indicators: Query<Entity, With<SharedRoadIndicator>>,
+) {
+    if !pathfinding.is_changed() && !sim_state.is_changed() {
+        return;
+    }
+
+    for entity in q_indicators.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    // warnings are only useful while the player is still building
+    if *sim_state != SimulationState::NotStarted {
+        return;
+    }
+
+    for segment in q_segments.iter_many(&pathfinding.shared_segments) {
+        let middle = midpoint(segment.points, segment.arc);
+
+        commands.spawn((
+            ShapeBuilder::with(&road_path(segment.points, segment.arc))
+                .stroke((theme::SHARED_ROAD_WARNING, 4.0))
+                .build(),
+            Transform::from_xyz(0.0, 0.0, layer::ROAD_OVERLAY),
+            SharedRoadIndicator,
+            DespawnOnExit(GameState::Playing),
+        ));
+
+        commands.spawn((
+            ShapeBuilder::with(&shapes::Circle {
+                radius: 5.5,
+                ..default()
+            })
+            .fill(theme::SHARED_ROAD_WARNING)
+            .build(),
+            Transform::from_translation(middle.extend(layer::ROAD_OVERLAY)),
+            SharedRoadIndicator,
+            DespawnOnExit(GameState::Playing),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{entity, flavor};
+
+    #[test]
+    fn shared_segments_different_colors() {
+        let shared = shared_segments(&[
+            (flavor(0, 0), vec![entity(1), entity(2), entity(3)]),
+            (flavor(1, 0), vec![entity(4), entity(2), entity(5)]),
+        ]);
+
+        assert_eq!(shared, vec![entity(2)]);
+    }
+
+    #[test]
+    fn shared_segments_same_color() {
+        // different nets of the same color can share roads without exploding
+        let shared = shared_segments(&[
+            (flavor(0, 0), vec![entity(1), entity(2)]),
+            (flavor(0, 1), vec![entity(2), entity(3)]),
+        ]);
+
+        assert!(shared.is_empty());
+    }
+}
@@ -2,10 +2,11 @@ use std::time::Duration;
 
 use crate::{
     pixie::{
-        collide_pixies_system, emit_pixies_system, explode_pixies_system, move_pixies_system,
-        Pixie, PixieEmitter,
+        arrive_pixies_system, collide_pixies_system, emit_pixies_system, explode_pixies_system,
+        move_pixies_system, Pixie, PixieEmitter,
     },
     pixie_button_system,
+    traffic::traffic_control_system,
 };
 use bevy::{ecs::schedule::ScheduleLabel, prelude::*};
 
@@ -18,7 +19,9 @@ impl Plugin for SimulationPlugin {
         schedule.add_systems(
             (
                 collide_pixies_system,
+                traffic_control_system,
                 move_pixies_system,
+                arrive_pixies_system,
                 emit_pixies_system,
                 explode_pixies_system,
                 update_sim_state_system,
@@ -97,6 +100,10 @@ impl SimulationSteps {
     pub fn get_elapsed_f32(&self) -> f32 {
         self.step as f32 * SIMULATION_TIMESTEP
     }
+
+    pub fn get_step(&self) -> u32 {
+        self.step
+    }
 }
 
 #[derive(Clone, Copy, Default)]
@@ -126,9 +133,33 @@ impl SimulationSpeed {
         }
     }
 }
+/// Whether pixies stick to the route they were emitted with, or switch to a
+/// less congested route at junctions.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum RoutingMode {
+    #[default]
+    Fixed,
+    Dynamic,
+}
+
+impl RoutingMode {
+    pub fn label(&self) -> String {
+        match self {
+            Self::Fixed => "FIX".to_string(),
+            Self::Dynamic => "DYN".to_string(),
+        }
+    }
+    pub fn next(&self) -> Self {
+        match self {
+            RoutingMode::Fixed => RoutingMode::Dynamic,
+            RoutingMode::Dynamic => RoutingMode::Fixed,
+        }
+    }
+}
 #[derive(Resource, Default)]
 pub struct SimulationSettings {
     pub speed: SimulationSpeed,
+    pub routing: RoutingMode,
 }
 
 fn run_simulation(world: &mut World) {
@@ -0,0 +1,447 @@
+//! Compares the current roads with another solution for the same level,
+//! either the best solution or one of the level's slots.
+//!
+//! Roads that were added since the other solution, roads that were removed,
+//! and roads that are in both are drawn over the level in different colors,
+//! along with the difference in cost and score.
+
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+
+use crate::{
+    deliveries::QuotasMet,
+    layer, road_path,
+    save::{BestScores, BestSolutions, Solution, Solutions},
+    sim::SimulationState,
+    solution_cost,
+    solution_slots::SolutionSlots,
+    theme, AfterUpdate, GameState, Handles, RoadSegment, Score, ScoreCalc, SelectedLevel,
+};
+
+pub struct SolutionDiffPlugin;
+impl Plugin for SolutionDiffPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SolutionDiffState>();
+        app.add_systems(OnEnter(GameState::Playing), (reset_diff, spawn_diff_text));
+        app.add_systems(
+            Update,
+            diff_button_system.run_if(in_state(GameState::Playing)),
+        );
+        app.add_systems(
+            AfterUpdate,
+            draw_diff_system
+                .after(ScoreCalc)
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+/// The solution that the current roads are being compared with, if any.
+#[derive(Resource, Default)]
+pub struct SolutionDiffState {
+    pub baseline: Option<DiffBaseline>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DiffBaseline {
+    /// The solution that earned the level's best score.
+    Best,
+    /// The slot with this name.
+    Slot(String),
+}
+
+/// Compares the current roads with a solution, or stops comparing if they
+/// are already being compared with it.
+#[derive(Component)]
+pub struct DiffButton(pub DiffBaseline);
+#[derive(Component)]
+struct DiffOverlay;
+#[derive(Component)]
+struct DiffText;
+
+/// The road segments of one solution sorted by whether another solution has
+/// them too.
+///
+/// Straight roads are compared by the ground they cover, so a road that was
+/// redrawn as several pieces, or merged from several pieces, is unchanged.
+/// Where a road is only partly covered, just the uncovered part is added or
+/// removed.
+#[derive(Debug, Default, PartialEq)]
+pub struct SolutionDiff {
+    /// Segments that are only in the newer solution.
+    pub added: Vec<RoadSegment>,
+    /// Segments that are only in the older solution.
+    pub removed: Vec<RoadSegment>,
+    pub unchanged: Vec<RoadSegment>,
+}
+
+/// Returns the differences between the roads of `baseline` and `current`.
+pub fn diff_solutions(baseline: &Solution, current: &Solution) -> SolutionDiff {
+    let mut diff = SolutionDiff::default();
+
+    for segment in &current.segments {
+        let (covered, uncovered) = split_covered(segment, &baseline.segments);
+        diff.unchanged.extend(covered);
+        diff.added.extend(uncovered);
+    }
+
+    for segment in &baseline.segments {
+        let (_, uncovered) = split_covered(segment, &current.segments);
+        diff.removed.extend(uncovered);
+    }
+
+    diff
+}
+
+/// Splits `segment` into the pieces that roads in `others` also cover and the
+/// pieces they don't.
+///
+/// Arcs are only covered by an identical arc. Straight roads are covered by
+/// any collinear straight roads of the same kind, so collinear runs are
+/// compared as a whole no matter how they were split into segments.
+fn split_covered(
+    segment: &RoadSegment,
+    others: &[RoadSegment],
+) -> (Vec<RoadSegment>, Vec<RoadSegment>) {
+    let whole = vec![segment.clone()];
+
+    if segment.arc.is_some() || segment.points.0 == segment.points.1 {
+        return if others.iter().any(|o| same_road(o, segment)) {
+            (whole, vec![])
+        } else {
+            (vec![], whole)
+        };
+    }
+
+    let (a, b) = segment.points;
+    let diff = b - a;
+    let len2 = diff.length_squared();
+    let along = |p: Vec2| (p - a).dot(diff) / len2;
+
+    // the parts of `segment` covered by each of the others, as distances
+    // along it from 0 at its start to 1 at its end, paired with the point
+    // at that distance. coordinates are snapped to the grid, so collinear
+    // points can be compared without an epsilon.
+    let mut spans: Vec<((f32, Vec2), (f32, Vec2))> = others
+        .iter()
+        .filter(|o| {
+            o.arc.is_none()
+                && o.layer == segment.layer
+                && o.one_way == segment.one_way
+                && o.class == segment.class
+                && diff.perp_dot(o.points.0 - a) == 0.0
+                && diff.perp_dot(o.points.1 - a) == 0.0
+        })
+        .filter_map(|o| {
+            let mut start = (along(o.points.0), o.points.0);
+            let mut end = (along(o.points.1), o.points.1);
+            if start.0 > end.0 {
+                if segment.one_way {
+                    return None;
+                }
+                std::mem::swap(&mut start, &mut end);
+            }
+            if start.0 <= 0.0 {
+                start = (0.0, a);
+            }
+            if end.0 >= 1.0 {
+                end = (1.0, b);
+            }
+            (start.0 < end.0).then_some((start, end))
+        })
+        .collect();
+    spans.sort_by(|x, y| x.0 .0.total_cmp(&y.0 .0));
+
+    let piece = |from: Vec2, to: Vec2| RoadSegment {
+        points: (from, to),
+        ..segment.clone()
+    };
+
+    let mut covered = vec![];
+    let mut uncovered = vec![];
+    let mut reached = (0.0, a);
+    for (start, end) in spans {
+        if end.0 <= reached.0 {
+            continue;
+        }
+        if start.0 > reached.0 {
+            uncovered.push(piece(reached.1, start.1));
+            covered.push(piece(start.1, end.1));
+        } else if let Some(last) = covered.last_mut().filter(|l| l.points.1 == reached.1) {
+            last.points.1 = end.1;
+        } else {
+            covered.push(piece(reached.1, end.1));
+        }
+        reached = end;
+    }
+    if reached.0 < 1.0 {
+        uncovered.push(piece(reached.1, b));
+    }
+
+    if uncovered.is_empty() {
+        (whole, uncovered)
+    } else if covered.is_empty() {
+        (covered, whole)
+    } else {
+        (covered, uncovered)
+    }
+}
+
+/// Whether two segments are the same road. Two-way roads may have been drawn
+/// in either direction.
+fn same_road(a: &RoadSegment, b: &RoadSegment) -> bool {
+    let same_points = a.points == b.points
+        || (!a.one_way && a.points.0 == b.points.1 && a.points.1 == b.points.0);
+
+    same_points
+        && a.layer == b.layer
+        && a.one_way == b.one_way
+        && a.class == b.class
+        && a.arc == b.arc
+}
+
+fn reset_diff(mut state: ResMut<SolutionDiffState>) {
+    state.baseline = None;
+}
+
+fn spawn_diff_text(mut commands: Commands, handles: Res<Handles>) {
+    commands.spawn((
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        },
+        Text::default(),
+        TextFont {
+            font: handles.fonts[0].clone(),
+            font_size: 25.0,
+            ..default()
+        },
+        TextColor(theme::UI_LABEL.into()),
+        DiffText,
+        DespawnOnExit(GameState::Playing),
+    ));
+}
+
+fn diff_button_system(
+    query: Query<(&Interaction, &DiffButton), Changed<Interaction>>,
+    mut state: ResMut<SolutionDiffState>,
+) {
+    for (_, button) in query.iter().filter(|(i, _)| **i == Interaction::Pressed) {
+        if state.baseline.as_ref() == Some(&button.0) {
+            state.baseline = None;
+        } else {
+            state.baseline = Some(button.0.clone());
+        }
+    }
+}
+
+fn draw_diff_system(
+    mut commands: Commands,
+    state: Res<SolutionDiffState>,
+    solutions: Res<Solutions>,
+    slots: Res<SolutionSlots>,
+    best_solutions: Res<BestSolutions>,
+    best_scores: Res<BestScores>,
+    score: Res<Score>,
+    quotas: Res<QuotasMet>,
+    sim_state: Res<SimulationState>,
+    selected_level: Res<SelectedLevel>,
+    q_overlays: Query<Entity, With<DiffOverlay>>,
+    mut q_text: Query<&mut Text, With<DiffText>>,
+) {
+    if !state.is_changed()
+        && !solutions.is_changed()
+        && !slots.is_changed()
+        && !best_solutions.is_changed()
+        && !score.is_changed()
+    {
+        return;
+    }
+
+    for entity in q_overlays.iter() {
+        commands.entity(entity).despawn();
+    }
+    for mut text in q_text.iter_mut() {
+        text.0.clear();
+    }
+
+    let level_slots = slots.0.get(&selected_level.0);
+
+    let (name, baseline, baseline_score) = match &state.baseline {
+        Some(DiffBaseline::Best) => (
+            "★".to_string(),
+            best_solutions.0.get(&selected_level.0),
+            best_scores.0.get(&selected_level.0).copied(),
+        ),
+        Some(DiffBaseline::Slot(name)) => {
+            let slot = level_slots.and_then(|l| l.slots.iter().find(|s| s.name == *name));
+            (
+                name.clone(),
+                slot.map(|s| &s.solution),
+                slot.and_then(|s| s.score),
+            )
+        }
+        None => return,
+    };
+    let Some(baseline) = baseline else {
+        return;
+    };
+
+    let current = solutions
+        .0
+        .get(&selected_level.0)
+        .cloned()
+        .unwrap_or_default();
+
+    // the score of the current roads is only known after a run, or if the
+    // active slot remembers it.
+    let current_score = if *sim_state == SimulationState::Finished && quotas.0 {
+        score.0
+    } else {
+        level_slots
+            .and_then(|l| l.active_slot())
+            .and_then(|s| s.score)
+    };
+
+    let diff = diff_solutions(baseline, &current);
+
+    let overlays: [(&Vec<RoadSegment>, Srgba, f32); 3] = [
+        (&diff.unchanged, theme::DIFF_UNCHANGED, 2.0),
+        (&diff.removed, theme::DIFF_REMOVED, 4.0),
+        (&diff.added, theme::DIFF_ADDED, 4.0),
+    ];
+
+    for (segments, color, width) in overlays {
+        for segment in segments {
+            commands.spawn((
+                ShapeBuilder::with(&road_path(segment.points, segment.arc))
+                    .stroke((color, width))
+                    .build(),
+                Transform::from_xyz(0.0, 0.0, layer::ROAD_OVERLAY),
+                DiffOverlay,
+                DespawnOnExit(GameState::Playing),
+            ));
+        }
+    }
+
+    let cost_delta = solution_cost(&current.segments, &current.controls).ceil()
+        - solution_cost(&baseline.segments, &baseline.controls).ceil();
+    let score_delta = match (current_score, baseline_score) {
+        (Some(current), Some(baseline)) => format!("{:+}", current as i64 - baseline as i64),
+        _ => "?".to_string(),
+    };
+
+    for mut text in q_text.iter_mut() {
+        text.0 = format!(
+            "vs {name}: {} added, {} removed, §{cost_delta:+}, Æ{score_delta}",
+            diff.added.len(),
+            diff.removed.len(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{segment, solution};
+
+    fn one_way(a: (f32, f32), b: (f32, f32)) -> RoadSegment {
+        RoadSegment {
+            one_way: true,
+            ..segment(a, b)
+        }
+    }
+
+    #[test]
+    fn added_removed_unchanged() {
+        let baseline = solution(vec![
+            segment((0.0, 0.0), (48.0, 0.0)),
+            segment((48.0, 0.0), (96.0, 0.0)),
+        ]);
+        let current = solution(vec![
+            segment((0.0, 0.0), (48.0, 0.0)),
+            segment((48.0, 0.0), (48.0, 48.0)),
+        ]);
+
+        let diff = diff_solutions(&baseline, &current);
+
+        assert_eq!(diff.unchanged, vec![segment((0.0, 0.0), (48.0, 0.0))]);
+        assert_eq!(diff.added, vec![segment((48.0, 0.0), (48.0, 48.0))]);
+        assert_eq!(diff.removed, vec![segment((48.0, 0.0), (96.0, 0.0))]);
+    }
+
+    #[test]
+    fn direction_only_matters_for_one_way_roads() {
+        let baseline = solution(vec![
+            segment((0.0, 0.0), (48.0, 0.0)),
+            one_way((48.0, 0.0), (96.0, 0.0)),
+        ]);
+        let current = solution(vec![
+            segment((48.0, 0.0), (0.0, 0.0)),
+            one_way((96.0, 0.0), (48.0, 0.0)),
+        ]);
+
+        let diff = diff_solutions(&baseline, &current);
+
+        assert_eq!(diff.unchanged.len(), 1);
+        assert_eq!(diff.added, vec![one_way((96.0, 0.0), (48.0, 0.0))]);
+        assert_eq!(diff.removed, vec![one_way((48.0, 0.0), (96.0, 0.0))]);
+    }
+
+    #[test]
+    fn split_roads_are_unchanged() {
+        let baseline = solution(vec![segment((0.0, 0.0), (96.0, 0.0))]);
+        let current = solution(vec![
+            segment((48.0, 0.0), (96.0, 0.0)),
+            segment((48.0, 0.0), (0.0, 0.0)),
+        ]);
+
+        let diff = diff_solutions(&baseline, &current);
+        assert_eq!(diff.unchanged.len(), 2);
+        assert!(diff.added.is_empty());
+        assert!(diff.removed.is_empty());
+
+        let diff = diff_solutions(&current, &baseline);
+        assert_eq!(diff.unchanged, vec![segment((0.0, 0.0), (96.0, 0.0))]);
+        assert!(diff.added.is_empty());
+        assert!(diff.removed.is_empty());
+    }
+
+    #[test]
+    fn only_uncovered_parts_change() {
+        let baseline = solution(vec![
+            segment((0.0, 0.0), (96.0, 0.0)),
+            RoadSegment {
+                layer: 2,
+                ..segment((96.0, 0.0), (192.0, 0.0))
+            },
+        ]);
+        let current = solution(vec![
+            segment((48.0, 0.0), (144.0, 0.0)),
+            segment((144.0, 0.0), (192.0, 0.0)),
+        ]);
+
+        let diff = diff_solutions(&baseline, &current);
+
+        assert_eq!(diff.unchanged, vec![segment((48.0, 0.0), (96.0, 0.0))]);
+        assert_eq!(
+            diff.added,
+            vec![
+                segment((96.0, 0.0), (144.0, 0.0)),
+                segment((144.0, 0.0), (192.0, 0.0)),
+            ]
+        );
+        assert_eq!(
+            diff.removed,
+            vec![
+                segment((0.0, 0.0), (48.0, 0.0)),
+                RoadSegment {
+                    layer: 2,
+                    ..segment((96.0, 0.0), (192.0, 0.0))
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,583 @@
+//! Named solution slots, so that a working solution can be kept safe while
+//! trying something else.
+//!
+//! The active slot follows the player's edits. "SAVE AS" copies the current
+//! roads into a new slot and makes it active, and loading a slot swaps its
+//! roads in. The solution that set the level's best score can be loaded from
+//! here too. Roads that aren't in any slot are kept in a new one before
+//! something is loaded over them.
+
+use bevy::{ecs::spawn::SpawnableList, platform::collections::HashMap, prelude::*};
+
+use crate::{
+    deliveries::QuotasMet,
+    level_reload::LevelRebuild,
+    save::{BestScores, BestSolutions, Solution, Solutions},
+    save_solution_system,
+    sim::SimulationState,
+    solution_diff::{DiffBaseline, DiffButton},
+    theme,
+    ui::button,
+    update_score_system, AfterUpdate, GameState, Handles, Score, ScoreCalc, SelectedLevel,
+    BOTTOM_BAR_HEIGHT,
+};
+
+pub struct SolutionSlotsPlugin;
+impl Plugin for SolutionSlotsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Playing), spawn_slots_panel);
+        app.add_systems(
+            Update,
+            (slots_button_system, slot_button_system, slot_list_system)
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        );
+        app.add_systems(
+            AfterUpdate,
+            (
+                follow_edits_system.after(save_solution_system),
+                record_score_system.after(update_score_system),
+            )
+                .in_set(ScoreCalc),
+        );
+    }
+}
+
+/// Each level's named solution slots.
+#[derive(Resource, Clone, Debug, Default, Reflect)]
+pub struct SolutionSlots(pub HashMap<u32, LevelSlots>);
+
+#[derive(Clone, Debug, Default, Reflect)]
+pub struct LevelSlots {
+    /// The slot that the player's edits are saved to, if any.
+    pub active: Option<usize>,
+    pub slots: Vec<SolutionSlot>,
+}
+
+#[derive(Clone, Debug, Reflect)]
+pub struct SolutionSlot {
+    pub name: String,
+    pub solution: Solution,
+    /// The score of the last successful run of this slot's roads.
+    pub score: Option<u32>,
+}
+
+impl LevelSlots {
+    pub fn active_slot(&self) -> Option<&SolutionSlot> {
+        self.slots.get(self.active?)
+    }
+
+    /// The first letter not already used as a slot name.
+    pub fn next_name(&self) -> String {
+        ('A'..='Z')
+            .map(String::from)
+            .chain((27..).map(|n| n.to_string()))
+            .find(|name| self.slots.iter().all(|slot| slot.name != *name))
+            .unwrap()
+    }
+
+    /// Stores `solution` in a new slot and makes it the active slot.
+    pub fn save_as(&mut self, solution: Solution, score: Option<u32>) -> usize {
+        self.slots.push(SolutionSlot {
+            name: self.next_name(),
+            solution,
+            score,
+        });
+        self.active = Some(self.slots.len() - 1);
+
+        self.slots.len() - 1
+    }
+
+    /// Copies slot `index` into a new slot just after it, and makes the copy
+    /// the active slot.
+    pub fn duplicate(&mut self, index: usize) -> Option<usize> {
+        let mut copy = self.slots.get(index)?.clone();
+        copy.name = self.next_name();
+
+        self.slots.insert(index + 1, copy);
+        self.active = Some(index + 1);
+
+        Some(index + 1)
+    }
+
+    /// Removes slot `index`. If it was active, edits stop being saved to a
+    /// slot until another is loaded or created.
+    pub fn delete(&mut self, index: usize) {
+        if index >= self.slots.len() {
+            return;
+        }
+
+        self.slots.remove(index);
+
+        self.active = match self.active {
+            Some(active) if active == index => None,
+            Some(active) if active > index => Some(active - 1),
+            active => active,
+        };
+    }
+
+    /// Makes slot `index` the active slot, returning its solution. The
+    /// `current` roads it replaces are set aside first, so that they aren't
+    /// lost.
+    pub fn load(&mut self, index: usize, current: &Solution) -> Option<Solution> {
+        let solution = self.slots.get(index)?.solution.clone();
+        self.set_aside(current, &solution);
+        self.active = Some(index);
+
+        Some(solution)
+    }
+
+    /// Stops saving edits to the active slot because `current` is about to be
+    /// replaced by `replacement`. Unless a slot already holds the same roads,
+    /// `current` is kept in a new slot first so that it isn't lost. Returns the
+    /// index of the new slot, if one was made.
+    pub fn set_aside(&mut self, current: &Solution, replacement: &Solution) -> Option<usize> {
+        let kept = (current.segments.is_empty() && current.controls.is_empty())
+            || current.matches(replacement)
+            || self.slots.iter().any(|slot| slot.solution.matches(current));
+
+        let index = (!kept).then(|| self.save_as(current.clone(), None));
+        self.active = None;
+
+        index
+    }
+
+    /// Saves `solution` to the active slot. Its score no longer applies if the
+    /// roads changed.
+    pub fn follow(&mut self, solution: &Solution) {
+        let Some(slot) = self.active.and_then(|i| self.slots.get_mut(i)) else {
+            return;
+        };
+
+        if slot.solution.matches(solution) {
+            return;
+        }
+
+        slot.solution = solution.clone();
+        slot.score = None;
+    }
+}
+
+#[derive(Component)]
+pub struct SlotsButton;
+/// The list of slots that pops up above the bottom bar.
+#[derive(Component)]
+struct SlotsPanel;
+#[derive(Component)]
+struct SaveAsButton;
+#[derive(Component)]
+struct LoadBestButton;
+#[derive(Component)]
+struct SlotButton(usize, SlotAction);
+#[derive(Clone, Copy)]
+enum SlotAction {
+    Load,
+    Duplicate,
+    Delete,
+}
+
+fn spawn_slots_panel(mut commands: Commands) {
+    commands.spawn((
+        Name::new("SlotsPanel"),
+        Node {
+            display: Display::None,
+            position_type: PositionType::Absolute,
+            right: Val::Px(10.0),
+            bottom: Val::Px(BOTTOM_BAR_HEIGHT + 10.0),
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(10.0),
+            padding: UiRect::all(Val::Px(10.0)),
+            ..default()
+        },
+        BackgroundColor(theme::UI_PANEL_BACKGROUND.into()),
+        SlotsPanel,
+        DespawnOnExit(GameState::Playing),
+    ));
+}
+
+fn slots_button_system(
+    query: Query<&Interaction, (Changed<Interaction>, With<SlotsButton>)>,
+    mut q_panel: Query<&mut Node, With<SlotsPanel>>,
+) {
+    for _ in query.iter().filter(|i| **i == Interaction::Pressed) {
+        for mut node in &mut q_panel {
+            node.display = match node.display {
+                Display::None => Display::Flex,
+                _ => Display::None,
+            };
+        }
+    }
+}
+
+fn slot_button_system(
+    q_save_as: Query<&Interaction, (Changed<Interaction>, With<SaveAsButton>)>,
+    q_slots: Query<(&Interaction, &SlotButton), Changed<Interaction>>,
+    q_load_best: Query<&Interaction, (Changed<Interaction>, With<LoadBestButton>)>,
+    mut slots: ResMut<SolutionSlots>,
+    mut rebuild: ResMut<LevelRebuild>,
+    solutions: Res<Solutions>,
+    best_solutions: Res<BestSolutions>,
+    selected_level: Res<SelectedLevel>,
+    sim_state: Res<SimulationState>,
+    score: Res<Score>,
+    quotas: Res<QuotasMet>,
+) {
+    for _ in q_save_as.iter().filter(|i| **i == Interaction::Pressed) {
+        let solution = solutions
+            .0
+            .get(&selected_level.0)
+            .cloned()
+            .unwrap_or_default();
+        let score = score
+            .0
+            .filter(|_| *sim_state == SimulationState::Finished && quotas.0);
+
+        slots
+            .0
+            .entry(selected_level.0)
+            .or_default()
+            .save_as(solution, score);
+    }
+
+    for _ in q_load_best.iter().filter(|i| **i == Interaction::Pressed) {
+        if *sim_state != SimulationState::NotStarted {
+            warn!("Reset the simulation before loading a solution");
+            continue;
+        }
+
+        let Some(best) = best_solutions.0.get(&selected_level.0) else {
+            continue;
+        };
+
+        if let Some(current) = solutions.0.get(&selected_level.0) {
+            slots
+                .0
+                .entry(selected_level.0)
+                .or_default()
+                .set_aside(current, best);
+        }
+
+        rebuild.request(Some(best.clone()));
+    }
+
+    for (_, button) in q_slots.iter().filter(|(i, _)| **i == Interaction::Pressed) {
+        let level_slots = slots.0.entry(selected_level.0).or_default();
+
+        match button.1 {
+            SlotAction::Load => {
+                if *sim_state != SimulationState::NotStarted {
+                    warn!("Reset the simulation before loading a solution");
+                    continue;
+                }
+
+                let current = solutions
+                    .0
+                    .get(&selected_level.0)
+                    .cloned()
+                    .unwrap_or_default();
+                if let Some(solution) = level_slots.load(button.0, &current) {
+                    rebuild.request(Some(solution));
+                }
+            }
+            SlotAction::Duplicate => {
+                level_slots.duplicate(button.0);
+            }
+            SlotAction::Delete => level_slots.delete(button.0),
+        }
+    }
+}
+
+fn slot_list_system(
+    mut commands: Commands,
+    slots: Res<SolutionSlots>,
+    best_scores: Res<BestScores>,
+    best_solutions: Res<BestSolutions>,
+    selected_level: Res<SelectedLevel>,
+    handles: Res<Handles>,
+    q_panel: Query<(Entity, Ref<SlotsPanel>)>,
+) {
+    for (entity, panel) in &q_panel {
+        if !panel.is_added() && !slots.is_changed() && !best_solutions.is_changed() {
+            continue;
+        }
+
+        commands.entity(entity).despawn_children();
+
+        let font = &handles.fonts[0];
+        let level_slots = slots.0.get(&selected_level.0);
+
+        commands.entity(entity).with_child((
+            Node {
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Stretch,
+                height: Val::Px(50.0),
+                ..default()
+            },
+            Children::spawn(Spawn((
+                SaveAsButton,
+                button("SAVE AS", font.clone(), 250.0),
+            ))),
+        ));
+
+        if best_solutions.0.contains_key(&selected_level.0) {
+            commands.entity(entity).with_child(slot_row(
+                font,
+                "★".to_string(),
+                false,
+                best_scores.0.get(&selected_level.0).copied(),
+                (
+                    Spawn((LoadBestButton, button("LOAD", font.clone(), 80.0))),
+                    Spawn((
+                        DiffButton(DiffBaseline::Best),
+                        button("DIFF", font.clone(), 70.0),
+                    )),
+                ),
+            ));
+        }
+
+        for (index, slot) in level_slots.iter().flat_map(|l| l.slots.iter().enumerate()) {
+            let active = level_slots.is_some_and(|l| l.active == Some(index));
+
+            commands.entity(entity).with_child(slot_row(
+                font,
+                slot.name.clone(),
+                active,
+                slot.score,
+                (
+                    Spawn((
+                        SlotButton(index, SlotAction::Load),
+                        button("LOAD", font.clone(), 80.0),
+                    )),
+                    Spawn((
+                        SlotButton(index, SlotAction::Duplicate),
+                        button("DUP", font.clone(), 70.0),
+                    )),
+                    Spawn((
+                        SlotButton(index, SlotAction::Delete),
+                        button("DEL", font.clone(), 70.0),
+                    )),
+                    Spawn((
+                        DiffButton(DiffBaseline::Slot(slot.name.clone())),
+                        button("DIFF", font.clone(), 70.0),
+                    )),
+                ),
+            ));
+        }
+    }
+}
+
+/// A row in the slots panel with a name, a score and some buttons.
+fn slot_row<L: SpawnableList<ChildOf> + Send + Sync + 'static>(
+    font: &Handle<Font>,
+    name: String,
+    active: bool,
+    score: Option<u32>,
+    buttons: L,
+) -> impl Bundle {
+    let score = score.map(|s| format!("Æ{s}")).unwrap_or_default();
+
+    (
+        Node {
+            flex_direction: FlexDirection::Row,
+            align_items: AlignItems::Stretch,
+            column_gap: Val::Px(10.0),
+            height: Val::Px(50.0),
+            ..default()
+        },
+        Children::spawn((
+            Spawn((
+                Node {
+                    width: Val::Px(50.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                Children::spawn(Spawn((
+                    Text::new(name),
+                    TextFont {
+                        font: font.clone(),
+                        font_size: 25.0,
+                        ..default()
+                    },
+                    TextColor(if active {
+                        theme::UI_LABEL.into()
+                    } else {
+                        theme::UI_LABEL_MUTED.into()
+                    }),
+                ))),
+            )),
+            Spawn((
+                Node {
+                    width: Val::Px(80.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                Children::spawn(Spawn((
+                    Text::new(score),
+                    TextFont {
+                        font: font.clone(),
+                        font_size: 25.0,
+                        ..default()
+                    },
+                    TextColor(theme::FINISHED_ROAD[1].into()),
+                ))),
+            )),
+            buttons,
+        )),
+    )
+}
+
+fn follow_edits_system(
+    solutions: Res<Solutions>,
+    selected_level: Res<SelectedLevel>,
+    mut slots: ResMut<SolutionSlots>,
+) {
+    if !solutions.is_changed() {
+        return;
+    }
+
+    let Some(solution) = solutions.0.get(&selected_level.0) else {
+        return;
+    };
+
+    // only touch the slots when something changed, so that they aren't saved
+    // to disk for nothing.
+    let unchanged = slots
+        .0
+        .get(&selected_level.0)
+        .and_then(LevelSlots::active_slot)
+        .is_none_or(|slot| slot.solution.matches(solution));
+    if unchanged {
+        return;
+    }
+
+    if let Some(level_slots) = slots.0.get_mut(&selected_level.0) {
+        level_slots.follow(solution);
+    }
+}
+
+fn record_score_system(
+    score: Res<Score>,
+    quotas: Res<QuotasMet>,
+    sim_state: Res<SimulationState>,
+    selected_level: Res<SelectedLevel>,
+    mut slots: ResMut<SolutionSlots>,
+) {
+    if !score.is_changed() || *sim_state != SimulationState::Finished || !quotas.0 {
+        return;
+    }
+
+    let Some(val) = score.0 else {
+        return;
+    };
+
+    let Some(slot) = slots.0.get_mut(&selected_level.0).and_then(|l| {
+        let active = l.active?;
+        l.slots.get_mut(active)
+    }) else {
+        return;
+    };
+
+    slot.score = Some(val);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{segment, solution};
+
+    /// A solution with a single road from the origin to `(x, 0)`.
+    fn road_to(x: f32) -> Solution {
+        solution([segment(Vec2::ZERO, Vec2::new(x, 0.0))])
+    }
+
+    #[test]
+    fn save_as_names_and_activates() {
+        let mut slots = LevelSlots::default();
+
+        assert_eq!(slots.save_as(road_to(48.0), Some(10)), 0);
+        assert_eq!(slots.save_as(road_to(96.0), None), 1);
+
+        assert_eq!(slots.slots[0].name, "A");
+        assert_eq!(slots.slots[1].name, "B");
+        assert_eq!(slots.active, Some(1));
+        assert_eq!(slots.slots[0].score, Some(10));
+    }
+
+    #[test]
+    fn duplicate_and_delete_keep_active_slot() {
+        let mut slots = LevelSlots::default();
+        slots.save_as(road_to(48.0), None);
+        slots.save_as(road_to(96.0), None);
+
+        assert_eq!(slots.duplicate(0), Some(1));
+        assert_eq!(slots.slots[1].name, "C");
+        assert_eq!(slots.slots[1].solution, road_to(48.0));
+        assert_eq!(slots.active, Some(1));
+
+        slots.load(2, &road_to(96.0));
+        slots.delete(0);
+        assert_eq!(slots.active, Some(1));
+        assert_eq!(slots.active_slot().unwrap().name, "B");
+
+        slots.delete(1);
+        assert_eq!(slots.active, None);
+
+        // names that were freed up are reused
+        assert_eq!(slots.next_name(), "A");
+    }
+
+    #[test]
+    fn follow_only_changes_active_slot() {
+        let mut slots = LevelSlots::default();
+        slots.save_as(road_to(48.0), Some(10));
+        slots.save_as(road_to(96.0), Some(20));
+        slots.load(0, &road_to(96.0));
+
+        slots.follow(&road_to(48.0));
+        assert_eq!(slots.slots[0].score, Some(10));
+
+        slots.follow(&road_to(144.0));
+        assert_eq!(slots.slots[0].solution, road_to(144.0));
+        assert_eq!(slots.slots[0].score, None);
+        assert_eq!(slots.slots[1].solution, road_to(96.0));
+        assert_eq!(slots.slots[1].score, Some(20));
+    }
+
+    #[test]
+    fn set_aside_keeps_unsaved_roads() {
+        let mut slots = LevelSlots::default();
+        slots.save_as(road_to(48.0), None);
+
+        // already in a slot
+        assert_eq!(slots.set_aside(&road_to(48.0), &road_to(96.0)), None);
+        assert_eq!(slots.active, None);
+
+        // the same as what replaces it
+        assert_eq!(slots.set_aside(&road_to(96.0), &road_to(96.0)), None);
+        assert_eq!(slots.set_aside(&Solution::default(), &road_to(96.0)), None);
+
+        assert_eq!(slots.set_aside(&road_to(144.0), &road_to(96.0)), Some(1));
+        assert_eq!(slots.slots[1].name, "B");
+        assert_eq!(slots.slots[1].solution, road_to(144.0));
+        assert_eq!(slots.active, None);
+    }
+
+    #[test]
+    fn load_keeps_unsaved_roads() {
+        let mut slots = LevelSlots::default();
+        slots.save_as(road_to(48.0), None);
+        slots.active = None;
+
+        assert_eq!(slots.load(0, &road_to(144.0)), Some(road_to(48.0)));
+        assert_eq!(slots.slots.len(), 2);
+        assert_eq!(slots.slots[1].solution, road_to(144.0));
+        assert_eq!(slots.active, Some(0));
+
+        // roads that are already in a slot aren't copied again
+        assert_eq!(slots.load(1, &road_to(48.0)), Some(road_to(144.0)));
+        assert_eq!(slots.slots.len(), 2);
+        assert_eq!(slots.active, Some(1));
+    }
+}
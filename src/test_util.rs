@@ -0,0 +1,63 @@
+//! Factories shared by unit tests across modules.
+
+use bevy::prelude::*;
+
+use crate::{
+    level::{Level, Obstacle, Terminus},
+    road_class::RoadClass,
+    save::Solution,
+    PixieFlavor, RoadSegment,
+};
+
+/// A placeholder entity for keying per-entity maps.
+pub fn entity(index: u32) -> Entity {
+    Entity::from_raw_u32(index).unwrap()
+}
+
+/// A pixie flavor with the given color and net.
+pub fn flavor(color: u32, net: u32) -> PixieFlavor {
+    PixieFlavor { color, net }
+}
+
+/// A straight, two-way, standard road on the first layer.
+///
+/// Tests needing anything else can override fields with struct update syntax.
+pub fn segment(a: impl Into<Vec2>, b: impl Into<Vec2>) -> RoadSegment {
+    RoadSegment {
+        points: (a.into(), b.into()),
+        layer: 1,
+        one_way: false,
+        class: RoadClass::Standard,
+        arc: None,
+    }
+}
+
+/// A solution made of `segments`, without any traffic controls.
+pub fn solution(segments: impl IntoIterator<Item = RoadSegment>) -> Solution {
+    Solution {
+        segments: segments.into_iter().collect(),
+        controls: vec![],
+    }
+}
+
+/// A two-layer level with a terminus at `(96, 0)` and an obstacle covering
+/// `(-24, 24)..(24, 72)`.
+pub fn level() -> Level {
+    Level {
+        name: "test".to_string(),
+        name_position: Vec2::ZERO,
+        layers: 2,
+        terminuses: vec![Terminus {
+            point: Vec2::new(96.0, 0.0),
+            ..default()
+        }],
+        obstacles: vec![Obstacle::Rect(
+            Vec2::new(-24.0, 72.0),
+            Vec2::new(24.0, 24.0),
+        )],
+        star_thresholds: vec![],
+        forbid_shared_roads: false,
+        physics: default(),
+        bounds: default(),
+    }
+}
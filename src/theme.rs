@@ -5,6 +5,16 @@ pub const FINISHED_ROAD: [Srgba; 3] = [
     bevy::color::palettes::tailwind::GREEN_600,
     bevy::color::palettes::tailwind::INDIGO_600,
 ];
+pub const SLOW_ROAD: [Srgba; 3] = [
+    bevy::color::palettes::tailwind::CYAN_800,
+    bevy::color::palettes::tailwind::GREEN_800,
+    bevy::color::palettes::tailwind::INDIGO_800,
+];
+pub const FAST_ROAD: [Srgba; 3] = [
+    bevy::color::palettes::tailwind::CYAN_400,
+    bevy::color::palettes::tailwind::GREEN_400,
+    bevy::color::palettes::tailwind::INDIGO_400,
+];
 pub const DRAWING_ROAD: [Srgba; 3] = [
     bevy::color::palettes::tailwind::CYAN_700,
     bevy::color::palettes::tailwind::GREEN_700,
@@ -23,6 +33,14 @@ pub const BACKGROUND: Srgba = bevy::color::palettes::tailwind::GRAY_950;
 pub const GRID: Srgba = bevy::color::palettes::tailwind::GRAY_900;
 pub const LEVEL_NAME: Srgba = bevy::color::palettes::tailwind::GRAY_700;
 pub const OBSTACLE: Srgba = bevy::color::palettes::tailwind::GRAY_900;
+pub const EXPLOSION_HOTSPOT: Srgba = bevy::color::palettes::tailwind::RED_500;
+pub const TRAFFIC_SIGNAL: Srgba = bevy::color::palettes::tailwind::EMERALD_400;
+pub const TRAFFIC_YIELD: Srgba = bevy::color::palettes::tailwind::AMBER_300;
+pub const SHARED_ROAD_WARNING: Srgba = bevy::color::palettes::tailwind::AMBER_400;
+pub const MISPLACED_ROAD_WARNING: Srgba = bevy::color::palettes::tailwind::RED_500;
+pub const DIFF_ADDED: Srgba = bevy::color::palettes::tailwind::EMERALD_400;
+pub const DIFF_REMOVED: Srgba = bevy::color::palettes::tailwind::RED_500;
+pub const DIFF_UNCHANGED: Srgba = bevy::color::palettes::tailwind::GRAY_500;
 
 pub const DARK_OVERLAY: Color = Color::srgba(0.0, 0.0, 0.0, 0.7);
 
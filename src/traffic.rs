@@ -0,0 +1,389 @@
+//! Signals and yield markers that players can place at road junctions to
+//! control the order in which pixies pass through them.
+
+use std::f32::consts::FRAC_PI_4;
+
+use bevy::prelude::*;
+use bevy_prototype_lyon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cursor_over_play_area, layer,
+    lines::direction_at,
+    physics::PixiePhysics,
+    pixie::{Pixie, PIXIE_RADIUS},
+    sim::{SimulationState, SimulationSteps, SIMULATION_TIMESTEP},
+    theme, DrawingInteraction, GameState, MouseSnappedPos, PlayAreaNode, RoadGraph, RoadSegment,
+    SelectedTool, Tool,
+};
+
+/// The number of seconds a signal stays green for each road leading into its
+/// junction.
+pub const SIGNAL_PHASE: f32 = 3.0;
+/// Pixies that have just passed through a junction keep pixies coming from
+/// other directions waiting at a yield marker until they are this far away.
+pub const YIELD_CLEARANCE: f32 = PIXIE_RADIUS * 3.0;
+/// The number of roads that must meet at a point for it to be considered a
+/// junction.
+const MIN_JUNCTION_ROADS: usize = 3;
+
+pub struct TrafficPlugin;
+impl Plugin for TrafficPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            traffic_control_click_system.in_set(DrawingInteraction),
+        );
+        app.add_systems(
+            Update,
+            prune_traffic_controls_system
+                .after(DrawingInteraction)
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub enum TrafficControlKind {
+    /// Lets pixies in from each road leading into the junction in turn.
+    #[default]
+    Signal,
+    /// Makes pixies wait for any pixie that reached the junction first from
+    /// another road.
+    Yield,
+}
+impl TrafficControlKind {
+    /// The cost of placing this control, in the same units as roads.
+    pub fn cost(&self) -> f32 {
+        match self {
+            Self::Signal => 3.0,
+            Self::Yield => 1.0,
+        }
+    }
+}
+
+/// A signal or yield marker at the junction at `point` on a particular layer.
+///
+/// Controls are entities of their own rather than part of the [`RoadGraph`].
+/// Each end of each road is a separate node of the graph, so there is no one
+/// node to attach a junction's control to, and the graph is rebuilt whenever
+/// roads change while controls are kept.
+#[derive(Component, Clone, Debug, Default, PartialEq, Reflect)]
+pub struct TrafficControl {
+    pub kind: TrafficControlKind,
+    pub point: Vec2,
+    pub layer: u32,
+}
+
+/// Returns the direction a road leading into `point` from `from` approaches
+/// it, as one of eight compass directions.
+pub fn approach(from: Vec2, point: Vec2) -> u8 {
+    let dir = from - point;
+
+    ((dir.y.atan2(dir.x) / FRAC_PI_4).round() as i32).rem_euclid(8) as u8
+}
+
+/// Returns the direction from which a road segment that starts or ends at
+/// `point` meets it. Arcs meet it along their tangent there.
+pub fn road_approach(points: (Vec2, Vec2), arc: Option<Vec2>, point: Vec2) -> u8 {
+    let tangent = direction_at(points, arc, point);
+    let away = if point == points.1 { -tangent } else { tangent };
+
+    approach(point + away, point)
+}
+
+/// Returns the directions of each road that pixies can arrive at `point` on
+/// `layer` along. One-way roads that only leave `point` are left out.
+pub fn junction_approaches<'a>(
+    point: Vec2,
+    layer: u32,
+    segments: impl IntoIterator<Item = &'a RoadSegment>,
+) -> Vec<u8> {
+    let mut approaches = segments
+        .into_iter()
+        .filter(|segment| segment.layer == layer)
+        .filter(|segment| {
+            segment.points.1 == point || (segment.points.0 == point && !segment.one_way)
+        })
+        .map(|segment| road_approach(segment.points, segment.arc, point))
+        .collect::<Vec<_>>();
+
+    approaches.sort();
+    approaches.dedup();
+    approaches
+}
+
+/// Returns the direction from which a signal is letting pixies into its
+/// junction at the given simulation step.
+pub fn green_approach(approaches: &[u8], step: u32) -> Option<u8> {
+    if approaches.is_empty() {
+        return None;
+    }
+
+    let phase = (step as f32 * SIMULATION_TIMESTEP / SIGNAL_PHASE) as usize;
+
+    Some(approaches[phase % approaches.len()])
+}
+
+pub fn spawn_traffic_control(commands: &mut Commands, control: TrafficControl) -> Entity {
+    let builder = match control.kind {
+        TrafficControlKind::Signal => ShapeBuilder::with(&shapes::Circle {
+            radius: 8.5,
+            ..default()
+        })
+        .stroke((theme::TRAFFIC_SIGNAL, 2.0)),
+        TrafficControlKind::Yield => ShapeBuilder::with(&shapes::RegularPolygon {
+            sides: 3,
+            feature: shapes::RegularPolygonFeature::Radius(10.5),
+            ..default()
+        })
+        .stroke((theme::TRAFFIC_YIELD, 2.0)),
+    };
+
+    commands
+        .spawn((
+            builder.build(),
+            Transform::from_translation(control.point.extend(layer::ROAD_OVERLAY)),
+            control,
+            DespawnOnExit(GameState::Playing),
+        ))
+        .id()
+}
+
+/// Decides which pixies must wait before entering a junction.
+pub fn traffic_control_system(
+    sim_steps: Res<SimulationSteps>,
+    physics: Res<PixiePhysics>,
+    q_controls: Query<&TrafficControl>,
+    q_segments: Query<&RoadSegment>,
+    mut q_pixies: Query<(Entity, &mut Pixie, &Transform)>,
+) {
+    for (_, mut pixie, _) in q_pixies.iter_mut() {
+        pixie.hold = None;
+    }
+
+    let mut holds = vec![];
+
+    for control in q_controls.iter() {
+        // pixies about to enter the junction, and the pixies that are passing
+        // through it, along with the direction they came from.
+        let mut approaching = vec![];
+        let mut passing = vec![];
+
+        for (entity, pixie, transform) in q_pixies.iter() {
+            let Some(segment) = pixie.path.get(pixie.path_index) else {
+                continue;
+            };
+
+            if segment.layer != control.layer {
+                continue;
+            }
+
+            let dist = transform.translation.truncate().distance(control.point);
+
+            if segment.points.1 == control.point {
+                let from = road_approach(segment.points, segment.arc, control.point);
+
+                // waiting pixies stop a pixie radius short of the junction, so
+                // anything much closer than that is already committed.
+                if dist < PIXIE_RADIUS / 2.0 {
+                    passing.push(Some(from));
+                } else if dist < physics.vision_distance {
+                    approaching.push((entity, dist, from));
+                }
+            } else if segment.points.0 == control.point && dist < YIELD_CLEARANCE {
+                let from = pixie
+                    .path_index
+                    .checked_sub(1)
+                    .and_then(|i| pixie.path.get(i))
+                    .map(|prev| road_approach(prev.points, prev.arc, control.point));
+
+                passing.push(from);
+            }
+        }
+
+        match control.kind {
+            TrafficControlKind::Signal => {
+                let approaches =
+                    junction_approaches(control.point, control.layer, q_segments.iter());
+                let green = green_approach(&approaches, sim_steps.get_step());
+
+                for (entity, dist, from) in approaching.iter() {
+                    if green != Some(*from) {
+                        holds.push((*entity, *dist));
+                    }
+                }
+            }
+            TrafficControlKind::Yield => {
+                let Some((_, _, first)) = approaching
+                    .iter()
+                    .min_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)))
+                    .copied()
+                else {
+                    continue;
+                };
+
+                for (entity, dist, from) in approaching.iter() {
+                    let blocked =
+                        *from != first || passing.iter().any(|passing| *passing != Some(*from));
+
+                    if blocked {
+                        holds.push((*entity, *dist));
+                    }
+                }
+            }
+        }
+    }
+
+    for (entity, dist) in holds {
+        if let Ok((_, mut pixie, _)) = q_pixies.get_mut(entity) {
+            pixie.hold = Some(pixie.hold.map_or(dist, |hold| hold.min(dist)));
+        }
+    }
+}
+
+fn traffic_control_click_system(
+    mut commands: Commands,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    mouse_snapped: Res<MouseSnappedPos>,
+    selected_tool: Res<SelectedTool>,
+    sim_state: Res<SimulationState>,
+    mut graph: ResMut<RoadGraph>,
+    q_segments: Query<&RoadSegment>,
+    q_controls: Query<(Entity, &TrafficControl)>,
+    q_play_area: Query<&Interaction, With<PlayAreaNode>>,
+) {
+    let Tool::TrafficControl(kind) = selected_tool.0 else {
+        return;
+    };
+
+    if *sim_state != SimulationState::NotStarted {
+        return;
+    }
+
+    if !mouse_input.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    if !cursor_over_play_area(&q_play_area) {
+        return;
+    }
+
+    let point = mouse_snapped.0;
+
+    // clicking an existing control removes it
+    if let Some((entity, _)) = q_controls.iter().find(|(_, c)| c.point == point) {
+        commands.entity(entity).despawn();
+        graph.set_changed();
+        return;
+    }
+
+    // prefer the top-most junction at this point
+    let Some(layer) = (1..=3).find(|layer| {
+        q_segments
+            .iter()
+            .filter(|s| s.layer == *layer && (s.points.0 == point || s.points.1 == point))
+            .count()
+            >= MIN_JUNCTION_ROADS
+    }) else {
+        return;
+    };
+
+    spawn_traffic_control(&mut commands, TrafficControl { kind, point, layer });
+
+    // placing a control changes the cost of the solution, and it needs to be
+    // saved.
+    graph.set_changed();
+}
+
+/// Removes controls from points that are no longer junctions because the roads
+/// that met there were removed.
+fn prune_traffic_controls_system(
+    mut commands: Commands,
+    graph: Res<RoadGraph>,
+    q_segments: Query<&RoadSegment>,
+    q_controls: Query<(Entity, &TrafficControl)>,
+) {
+    if !graph.is_changed() {
+        return;
+    }
+
+    for (entity, control) in q_controls.iter() {
+        let roads = q_segments
+            .iter()
+            .filter(|s| {
+                s.layer == control.layer
+                    && (s.points.0 == control.point || s.points.1 == control.point)
+            })
+            .count();
+
+        if roads < MIN_JUNCTION_ROADS {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::segment;
+
+    #[test]
+    fn approach_directions() {
+        assert_eq!(approach(Vec2::new(48.0, 0.0), Vec2::ZERO), 0);
+        assert_eq!(approach(Vec2::new(48.0, 48.0), Vec2::ZERO), 1);
+        assert_eq!(approach(Vec2::new(0.0, 48.0), Vec2::ZERO), 2);
+        assert_eq!(approach(Vec2::new(-48.0, 0.0), Vec2::ZERO), 4);
+        assert_eq!(approach(Vec2::new(0.0, -48.0), Vec2::ZERO), 6);
+    }
+
+    #[test]
+    fn junction_approaches_ignores_other_layers() {
+        let segments = [
+            segment((-48.0, 0.0), (0.0, 0.0)),
+            segment((0.0, 0.0), (48.0, 0.0)),
+            segment((0.0, 48.0), (0.0, 0.0)),
+            RoadSegment {
+                layer: 2,
+                ..segment((0.0, -48.0), (0.0, 0.0))
+            },
+            segment((48.0, 48.0), (96.0, 48.0)),
+        ];
+
+        assert_eq!(junction_approaches(Vec2::ZERO, 1, &segments), vec![0, 2, 4]);
+    }
+
+    #[test]
+    fn junction_approaches_follow_arrivals() {
+        let segments = [
+            segment((-48.0, 0.0), (0.0, 0.0)),
+            // arrives from the east, although its far end is to the north-east
+            RoadSegment {
+                arc: Some(Vec2::new(0.0, 48.0)),
+                ..segment((0.0, 0.0), (48.0, 48.0))
+            },
+            // one-way roads only count if pixies can arrive along them
+            RoadSegment {
+                one_way: true,
+                ..segment((0.0, 48.0), (0.0, 0.0))
+            },
+            RoadSegment {
+                one_way: true,
+                ..segment((0.0, 0.0), (0.0, -48.0))
+            },
+        ];
+
+        assert_eq!(junction_approaches(Vec2::ZERO, 1, &segments), vec![0, 2, 4]);
+    }
+
+    #[test]
+    fn green_approach_cycles() {
+        let approaches = [0, 2, 4];
+        let phase_steps = (SIGNAL_PHASE / SIMULATION_TIMESTEP).ceil() as u32;
+
+        assert_eq!(green_approach(&approaches, 0), Some(0));
+        assert_eq!(green_approach(&approaches, phase_steps), Some(2));
+        assert_eq!(green_approach(&approaches, phase_steps * 3), Some(0));
+        assert_eq!(green_approach(&[], 0), None);
+    }
+}
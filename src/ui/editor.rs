@@ -1,8 +1,5 @@
-use crate::{layer, theme, GameState, GridPoint, BOTTOM_BAR_HEIGHT, GRID_SIZE};
+use crate::{grid::Grid, layer, level::LevelBounds, theme, GameState, BOTTOM_BAR_HEIGHT};
 use bevy::prelude::*;
-use bevy_prototype_lyon::geometry::ShapeBuilder;
-use bevy_prototype_lyon::shapes;
-use bevy_prototype_lyon::prelude::*;
 
 pub struct EditorPlugin;
 #[derive(Component)]
@@ -13,6 +10,16 @@ struct ExitEditorButton;
 struct Draggable;
 #[derive(Component)]
 struct Size(Vec2);
+#[derive(Component)]
+enum BoundsButton {
+    Grow,
+    Shrink,
+}
+#[derive(Component)]
+struct BoundsLabel;
+/// The part of the grid that the level being edited lets roads be built on.
+#[derive(Resource, Default)]
+struct EditorBounds(LevelBounds);
 #[derive(Resource, Default)]
 struct DragState {
     entity: Option<Entity>,
@@ -22,8 +29,13 @@ struct DragState {
 impl Plugin for EditorPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(DragState::default());
+        app.init_resource::<EditorBounds>();
         app.add_systems(OnEnter(GameState::Editor), (spawn_grid, editor_ui).chain());
         app.add_systems(Update, exit_editor_button_system);
+        app.add_systems(
+            Update,
+            bounds_button_system.run_if(in_state(GameState::Editor)),
+        );
         app.add_systems(Update, (drag_system).chain());
         app.add_systems(OnExit(GameState::Editor), editor_destroy);
     }
@@ -52,29 +64,53 @@ fn exit_editor_button_system(
     }
 }
 
-fn spawn_grid(
+fn spawn_grid(mut commands: Commands, bounds: Res<EditorBounds>) {
+    commands.spawn((
+        Grid(bounds.0),
+        Transform::from_xyz(0.0, 0.0, layer::GRID),
+        DespawnOnExit(GameState::Editor),
+    ));
+}
+
+fn bounds_text(bounds: &LevelBounds) -> String {
+    let size = bounds.max - bounds.min + IVec2::ONE;
+    format!("{} × {}", size.x, size.y)
+}
+
+fn bounds_button_system(
     mut commands: Commands,
+    query: Query<(&Interaction, &BoundsButton), Changed<Interaction>>,
+    mut bounds: ResMut<EditorBounds>,
+    q_grid: Query<Entity, With<Grid>>,
+    mut q_label: Query<&mut Text, With<BoundsLabel>>,
 ) {
-    for x in ((-25 * (GRID_SIZE as i32))..=25 * (GRID_SIZE as i32)).step_by(GRID_SIZE as usize) {
-        for y in (-15 * (GRID_SIZE as i32)..=15 * (GRID_SIZE as i32)).step_by(GRID_SIZE as usize) {
-            commands.spawn((
-                ShapeBuilder::with(&shapes::Circle {
-                    radius: 2.5,
-                    ..default()
-                })
-                    .fill(theme::GRID)
-                    .build(),
-                Transform::from_xyz(x as f32, y as f32, layer::GRID),
-                GridPoint,
-                DespawnOnExit(GameState::Editor),
-            ));
+    for (_, button) in query.iter().filter(|(i, _)| **i == Interaction::Pressed) {
+        let step = match button {
+            BoundsButton::Grow => IVec2::ONE,
+            BoundsButton::Shrink => {
+                // leave at least a 3 × 3 grid around the origin
+                if bounds.0.max.min_element() <= 1 || bounds.0.min.max_element() >= -1 {
+                    continue;
+                }
+                IVec2::NEG_ONE
+            }
+        };
+
+        bounds.0.min -= step;
+        bounds.0.max += step;
+
+        for entity in &q_grid {
+            commands.entity(entity).despawn();
+        }
+        commands.run_system_cached(spawn_grid);
+
+        for mut text in &mut q_label {
+            text.0 = bounds_text(&bounds.0);
         }
     }
 }
 
-fn editor_ui(
-    mut commands: Commands
-) {
+fn editor_ui(mut commands: Commands, bounds: Res<EditorBounds>) {
     let editor_root = commands
         .spawn((
             Node {
@@ -126,6 +162,37 @@ fn editor_ui(
                     Text::new("←"),
                 );
             });
+
+            parent
+                .spawn(Node {
+                    align_self: AlignSelf::Center,
+                    align_items: AlignItems::Center,
+                    column_gap: Val::Px(10.0),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for (button, label) in [
+                        (BoundsButton::Shrink, "−"),
+                        (BoundsButton::Grow, "+"),
+                    ] {
+                        parent
+                            .spawn((
+                                Node {
+                                    width: Val::Px(50.0),
+                                    height: Val::Px(50.0),
+                                    justify_content: JustifyContent::Center,
+                                    align_items: AlignItems::Center,
+                                    ..default()
+                                },
+                                button,
+                                Button,
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(Text::new(label));
+                            });
+                    }
+                    parent.spawn((Text::new(bounds_text(&bounds.0)), BoundsLabel));
+                });
         })
         .id();
 
@@ -1,19 +1,33 @@
 use crate::{
+    export::{copy_code, paste_code, SaveConflicts, SaveData, SaveExport, SAVE_CODE_PREFIX},
     level::Level,
     loading::NUM_LEVELS,
-    save::{BestScores, MusicVolume},
+    run_history::{RunBests, RunHistory, MAX_RUNS_PER_LEVEL},
+    save::{BestScores, BestSolutions, MusicVolume, Solutions},
+    solution_slots::SolutionSlots,
     theme,
-    ui::button,
+    ui::{button, pareto_chart::ParetoChart, run_table::RunTable},
     GameState, Handles, BOTTOM_BAR_HEIGHT,
 };
 
-use bevy::prelude::*;
+use bevy::{prelude::*, window::FileDragAndDrop};
 
 pub struct LevelSelectPlugin;
 #[derive(Component)]
 pub struct LevelSelectScreen;
 #[derive(Component)]
 pub struct LevelSelectButton(u32);
+/// Plays a level starting from the solution that earned its best score.
+#[derive(Component)]
+struct LoadBestButton(u32);
+/// Shows a level's run history and its best runs by cost, time and delivered
+/// pixies.
+#[derive(Component)]
+struct RunsButton(u32);
+#[derive(Component)]
+struct RunsOverlay;
+#[derive(Component)]
+struct CloseRunsButton;
 #[derive(Component)]
 struct EditorButton;
 #[derive(Component)]
@@ -26,21 +40,78 @@ struct MusicVolumeDown;
 struct MusicVolumeUp;
 #[derive(Component)]
 struct MusicVolumeLabel;
+#[derive(Component, Clone, Copy)]
+enum SaveDataButton {
+    Export,
+    Import,
+    Reset,
+    Merge,
+    Replace,
+    ConfirmReset,
+    Cancel,
+}
+#[derive(Component)]
+struct SaveDataStatusLabel;
+#[derive(Component)]
+struct ImportChoices;
+#[derive(Component)]
+struct ResetChoices;
+
+/// What the save data section of the settings panel is showing.
+#[derive(Resource, Default)]
+struct SaveDataPanel {
+    prompt: SaveDataPrompt,
+    status: String,
+}
+#[derive(Default)]
+enum SaveDataPrompt {
+    #[default]
+    None,
+    /// Waiting for the player to choose whether to merge or replace.
+    Import(SaveData),
+    /// Waiting for the player to confirm that everything should be erased.
+    Reset,
+}
+impl SaveDataPrompt {
+    fn import_display(&self) -> Display {
+        match self {
+            Self::Import(_) => Display::Flex,
+            _ => Display::None,
+        }
+    }
+
+    fn reset_display(&self) -> Display {
+        match self {
+            Self::Reset => Display::Flex,
+            _ => Display::None,
+        }
+    }
+}
 
 impl Plugin for LevelSelectPlugin {
     fn build(&self, app: &mut App) {
+        app.init_resource::<SaveDataPanel>();
+
         app.add_systems(OnEnter(GameState::LevelSelect), level_select_enter);
 
         app.add_systems(
             Update,
             (
                 level_select_button_system,
+                load_best_button_system,
+                (runs_button_system, close_runs_button_system),
                 editor_button_system,
                 (
                     music_volume_button_system,
                     music_volume_text_system.run_if(resource_changed::<MusicVolume>),
                 )
                     .chain(),
+                (
+                    save_data_button_system,
+                    save_data_drop_system,
+                    save_data_panel_system.run_if(resource_changed::<SaveDataPanel>),
+                )
+                    .chain(),
             )
                 .run_if(in_state(GameState::LevelSelect)),
         );
@@ -90,6 +161,129 @@ fn level_select_button_system(
     }
 }
 
+fn load_best_button_system(
+    query: Query<(&Interaction, &LoadBestButton), Changed<Interaction>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut level: ResMut<crate::SelectedLevel>,
+    mut solutions: ResMut<Solutions>,
+    mut slots: ResMut<SolutionSlots>,
+    best_solutions: Res<BestSolutions>,
+) {
+    for (_, button) in query.iter().filter(|(i, _)| **i == Interaction::Pressed) {
+        let Some(best) = best_solutions.0.get(&button.0) else {
+            continue;
+        };
+
+        if let Some(current) = solutions.0.insert(button.0, best.clone()) {
+            let level_slots = slots.0.entry(button.0).or_default();
+            if let Some(index) = level_slots.set_aside(&current, best) {
+                info!(
+                    "Saved the current roads for level {} to slot {}",
+                    button.0, level_slots.slots[index].name
+                );
+            }
+        }
+
+        level.0 = button.0;
+        next_state.set(GameState::Playing);
+    }
+}
+
+fn runs_button_system(
+    mut commands: Commands,
+    query: Query<(&Interaction, &RunsButton), Changed<Interaction>>,
+    handles: Res<Handles>,
+    q_overlay: Query<Entity, With<RunsOverlay>>,
+) {
+    for (_, button) in query.iter().filter(|(i, _)| **i == Interaction::Pressed) {
+        for entity in &q_overlay {
+            commands.entity(entity).despawn();
+        }
+
+        commands.spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BackgroundColor(theme::DARK_OVERLAY),
+            GlobalZIndex(1),
+            RunsOverlay,
+            LevelSelectScreen,
+            Children::spawn(Spawn((
+                Node {
+                    width: Val::Px(720.),
+                    height: Val::Percent(80.),
+                    padding: UiRect::all(Val::Px(20.)),
+                    row_gap: Val::Px(10.),
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                BackgroundColor(theme::UI_PANEL_BACKGROUND.into()),
+                Children::spawn((
+                    Spawn((
+                        Text::new(format!("LEVEL {} RUNS", button.0)),
+                        TextFont {
+                            font: handles.fonts[0].clone(),
+                            font_size: 25.0,
+                            ..default()
+                        },
+                        TextColor(theme::UI_LABEL.into()),
+                    )),
+                    Spawn((
+                        Node {
+                            flex_direction: FlexDirection::Column,
+                            flex_shrink: 0.,
+                            row_gap: Val::Px(5.),
+                            ..default()
+                        },
+                        ParetoChart { level: button.0 },
+                    )),
+                    Spawn((
+                        Node {
+                            flex_grow: 1.,
+                            overflow: Overflow::scroll_y(),
+                            ..default()
+                        },
+                        RunTable {
+                            level: button.0,
+                            max_rows: MAX_RUNS_PER_LEVEL,
+                            compact: false,
+                        },
+                    )),
+                    Spawn((
+                        Node {
+                            height: Val::Px(40.),
+                            flex_shrink: 0.,
+                            justify_content: JustifyContent::FlexEnd,
+                            ..default()
+                        },
+                        Children::spawn(Spawn((
+                            CloseRunsButton,
+                            button("CLOSE", handles.fonts[0].clone(), 150.),
+                        ))),
+                    )),
+                )),
+            ))),
+        ));
+    }
+}
+
+fn close_runs_button_system(
+    mut commands: Commands,
+    query: Query<&Interaction, (Changed<Interaction>, With<CloseRunsButton>)>,
+    q_overlay: Query<Entity, With<RunsOverlay>>,
+) {
+    for _ in query.iter().filter(|i| **i == Interaction::Pressed) {
+        for entity in &q_overlay {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
 fn level_select_enter(
     mut commands: Commands,
     best_scores: Res<BestScores>,
@@ -428,6 +622,7 @@ fn populate_settings_panel_body(
     mut commands: Commands,
     handles: Res<Handles>,
     music_volume: Res<MusicVolume>,
+    save_data: Res<SaveDataPanel>,
 ) {
     commands.entity(trigger.event().entity).with_child((
         Text::new("Music"),
@@ -467,6 +662,70 @@ fn populate_settings_panel_body(
             Spawn((MusicVolumeUp, button(">", handles.fonts[0].clone(), 50.0))),
         )),
     ));
+
+    commands.entity(trigger.event().entity).with_child((
+        Text::new("Data"),
+        TextFont {
+            font: handles.fonts[0].clone(),
+            font_size: 25.0,
+            ..default()
+        },
+    ));
+
+    let row = |display: Display| Node {
+        display,
+        flex_direction: FlexDirection::Row,
+        align_items: AlignItems::Stretch,
+        column_gap: Val::Px(10.0),
+        height: Val::Px(50.0),
+        ..default()
+    };
+    let font = &handles.fonts[0];
+
+    commands.entity(trigger.event().entity).with_child((
+        row(Display::Flex),
+        Children::spawn((
+            Spawn((SaveDataButton::Export, button("EXPORT", font.clone(), 90.0))),
+            Spawn((SaveDataButton::Import, button("IMPORT", font.clone(), 90.0))),
+            Spawn((SaveDataButton::Reset, button("RESET", font.clone(), 90.0))),
+        )),
+    ));
+
+    commands.entity(trigger.event().entity).with_child((
+        SaveDataStatusLabel,
+        Text::new(save_data.status.clone()),
+        TextFont {
+            font: font.clone(),
+            font_size: 18.0,
+            ..default()
+        },
+        TextColor(theme::UI_LABEL_MUTED.into()),
+    ));
+
+    commands.entity(trigger.event().entity).with_child((
+        ImportChoices,
+        row(save_data.prompt.import_display()),
+        Children::spawn((
+            Spawn((SaveDataButton::Merge, button("MERGE", font.clone(), 90.0))),
+            Spawn((
+                SaveDataButton::Replace,
+                button("REPLACE", font.clone(), 110.0),
+            )),
+            Spawn((SaveDataButton::Cancel, button("CANCEL", font.clone(), 90.0))),
+        )),
+    ));
+
+    commands.entity(trigger.event().entity).with_child((
+        ResetChoices,
+        row(save_data.prompt.reset_display()),
+        Children::spawn((
+            Spawn((
+                SaveDataButton::ConfirmReset,
+                button("ERASE", font.clone(), 90.0),
+            )),
+            Spawn((SaveDataButton::Cancel, button("CANCEL", font.clone(), 90.0))),
+        )),
+    ));
 }
 
 fn populate_levels_panel_body(
@@ -474,6 +733,8 @@ fn populate_levels_panel_body(
     mut commands: Commands,
     handles: Res<Handles>,
     best_scores: Res<BestScores>,
+    best_solutions: Res<BestSolutions>,
+    history: Res<RunHistory>,
     levels: Res<Assets<Level>>,
 ) {
     for level_index in 1..=NUM_LEVELS {
@@ -488,12 +749,36 @@ fn populate_levels_panel_body(
 
         commands
             .entity(trigger.event().entity)
-            .with_child(level_item(
-                level,
-                level_index,
-                &best_scores,
-                &handles.fonts[0],
-            ));
+            .with_children(|parent| {
+                parent
+                    .spawn(Node {
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(5.),
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(level_item(
+                            level,
+                            level_index,
+                            &best_scores,
+                            &handles.fonts[0],
+                        ));
+
+                        if best_solutions.0.contains_key(&level_index) {
+                            parent.spawn((
+                                LoadBestButton(level_index),
+                                button("★ BEST", handles.fonts[0].clone(), 150.),
+                            ));
+                        }
+
+                        if history.0.contains_key(&level_index) {
+                            parent.spawn((
+                                RunsButton(level_index),
+                                button("RUNS", handles.fonts[0].clone(), 150.),
+                            ));
+                        }
+                    });
+            });
     }
 }
 
@@ -523,6 +808,253 @@ fn music_volume_text_system(
     }
 }
 
+fn save_data_button_system(
+    mut commands: Commands,
+    query: Query<(&Interaction, &SaveDataButton), Changed<Interaction>>,
+    mut panel: ResMut<SaveDataPanel>,
+    mut best_scores: ResMut<BestScores>,
+    mut solutions: ResMut<Solutions>,
+    mut music_volume: ResMut<MusicVolume>,
+    mut slots: ResMut<SolutionSlots>,
+    mut best_solutions: ResMut<BestSolutions>,
+    mut history: ResMut<RunHistory>,
+    mut run_bests: ResMut<RunBests>,
+    handles: Res<Handles>,
+    levels: Res<Assets<Level>>,
+    q_screen: Query<Entity, With<LevelSelectScreen>>,
+) {
+    for (_, button) in query.iter().filter(|(i, _)| **i == Interaction::Pressed) {
+        match button {
+            SaveDataButton::Export => {
+                let export = SaveExport::new(
+                    &best_scores,
+                    &solutions,
+                    *music_volume,
+                    &slots,
+                    &best_solutions,
+                    &history,
+                    &run_bests,
+                );
+                panel.status = if copy_code(&export.to_code(), "save code") {
+                    "Copied the save code".to_string()
+                } else {
+                    "Failed to copy the save code".to_string()
+                };
+
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    use crate::export::{write_export, SAVE_EXPORT_PATH};
+                    use bevy::asset::ron::ser::{to_string_pretty, PrettyConfig};
+                    use std::fmt::Write;
+
+                    let path = std::path::Path::new(SAVE_EXPORT_PATH);
+                    let text = to_string_pretty(&export, PrettyConfig::default()).unwrap();
+                    let _ = match write_export(path, &text) {
+                        Ok(()) => write!(panel.status, ". Exported to {SAVE_EXPORT_PATH}"),
+                        Err(e) => write!(panel.status, ". Export failed: {e}"),
+                    };
+                }
+            }
+            SaveDataButton::Import => {
+                // a save code on the clipboard is imported rather than the
+                // exported file
+                let pasted = paste_code("save code")
+                    .filter(|text| text.trim().starts_with(SAVE_CODE_PREFIX));
+
+                #[cfg(not(target_arch = "wasm32"))]
+                let pasted = pasted.or_else(|| {
+                    use crate::export::SAVE_EXPORT_PATH;
+
+                    match std::fs::read_to_string(SAVE_EXPORT_PATH) {
+                        Ok(text) => Some(text),
+                        Err(e) => {
+                            panel.status = format!("Failed to read {SAVE_EXPORT_PATH}: {e}");
+                            None
+                        }
+                    }
+                });
+
+                match pasted {
+                    Some(text) => stage_import(
+                        &text,
+                        &mut panel,
+                        &best_scores,
+                        &solutions,
+                        &handles,
+                        &levels,
+                    ),
+                    None if cfg!(target_arch = "wasm32") => {
+                        panel.status = "No save code was pasted".to_string();
+                    }
+                    None => {}
+                }
+            }
+            SaveDataButton::Reset => {
+                panel.prompt = SaveDataPrompt::Reset;
+                panel.status = "Erase all scores, solutions, runs and settings?".to_string();
+            }
+            SaveDataButton::Merge => {
+                let SaveDataPrompt::Import(data) = std::mem::take(&mut panel.prompt) else {
+                    continue;
+                };
+                data.merge_into(
+                    &mut best_scores,
+                    &mut solutions,
+                    &mut slots,
+                    &mut best_solutions,
+                    &mut history,
+                    &mut run_bests,
+                );
+                panel.status = "Merged imported data".to_string();
+                refresh_level_select(&mut commands, &q_screen);
+            }
+            SaveDataButton::Replace => {
+                let SaveDataPrompt::Import(data) = std::mem::take(&mut panel.prompt) else {
+                    continue;
+                };
+                data.replace(
+                    &mut best_scores,
+                    &mut solutions,
+                    &mut music_volume,
+                    &mut slots,
+                    &mut best_solutions,
+                    &mut history,
+                    &mut run_bests,
+                );
+                panel.status = "Replaced all data".to_string();
+                refresh_level_select(&mut commands, &q_screen);
+            }
+            SaveDataButton::ConfirmReset => {
+                *best_scores = BestScores::default();
+                *solutions = Solutions::default();
+                *slots = SolutionSlots::default();
+                *best_solutions = BestSolutions::default();
+                *history = RunHistory::default();
+                *run_bests = RunBests::default();
+                *music_volume = MusicVolume::default();
+                panel.prompt = SaveDataPrompt::None;
+                panel.status = "Erased all data".to_string();
+                refresh_level_select(&mut commands, &q_screen);
+            }
+            SaveDataButton::Cancel => {
+                panel.prompt = SaveDataPrompt::None;
+                panel.status.clear();
+            }
+        }
+    }
+}
+
+fn save_data_drop_system(
+    mut drops: MessageReader<FileDragAndDrop>,
+    mut panel: ResMut<SaveDataPanel>,
+    best_scores: Res<BestScores>,
+    solutions: Res<Solutions>,
+    handles: Res<Handles>,
+    levels: Res<Assets<Level>>,
+) {
+    for drop in drops.read() {
+        let FileDragAndDrop::DroppedFile { path_buf, .. } = drop else {
+            continue;
+        };
+
+        match std::fs::read_to_string(path_buf) {
+            Ok(text) => stage_import(
+                &text,
+                &mut panel,
+                &best_scores,
+                &solutions,
+                &handles,
+                &levels,
+            ),
+            Err(e) => panel.status = format!("Failed to read {}: {e}", path_buf.display()),
+        }
+    }
+}
+
+fn save_data_panel_system(
+    panel: Res<SaveDataPanel>,
+    mut labels: Query<&mut Text, With<SaveDataStatusLabel>>,
+    mut import_rows: Query<&mut Node, (With<ImportChoices>, Without<ResetChoices>)>,
+    mut reset_rows: Query<&mut Node, (With<ResetChoices>, Without<ImportChoices>)>,
+) {
+    for mut text in &mut labels {
+        text.0.clone_from(&panel.status);
+    }
+    for mut node in &mut import_rows {
+        node.display = panel.prompt.import_display();
+    }
+    for mut node in &mut reset_rows {
+        node.display = panel.prompt.reset_display();
+    }
+}
+
+/// Checks imported save data and asks the player how to apply it.
+fn stage_import(
+    text: &str,
+    panel: &mut SaveDataPanel,
+    best_scores: &BestScores,
+    solutions: &Solutions,
+    handles: &Handles,
+    levels: &Assets<Level>,
+) {
+    let level = |number: u32| {
+        handles
+            .levels
+            .get((number as usize).checked_sub(1)?)
+            .and_then(|handle| levels.get(handle))
+    };
+
+    match SaveExport::parse(text).and_then(|export| export.validate(level)) {
+        Ok(data) => {
+            let conflicts = data.conflicts(best_scores, solutions);
+            panel.status = import_summary(&data, &conflicts);
+            panel.prompt = SaveDataPrompt::Import(data);
+        }
+        Err(e) => {
+            panel.status = format!("Import failed: {e}");
+            panel.prompt = SaveDataPrompt::None;
+        }
+    }
+}
+
+fn import_summary(data: &SaveData, conflicts: &SaveConflicts) -> String {
+    let found = format!(
+        "Found {} scores, {} solutions, {} slots and {} runs.",
+        data.scores.0.len(),
+        data.solutions.0.len(),
+        data.slots.0.values().map(|s| s.slots.len()).sum::<usize>(),
+        data.runs.0.values().map(Vec::len).sum::<usize>()
+    );
+
+    if conflicts.is_empty() {
+        return format!("{found} No conflicts.");
+    }
+
+    let levels = conflicts
+        .levels()
+        .iter()
+        .map(u32::to_string)
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    format!(
+        "{found} Differs from yours on levels {levels}. \
+        Merge keeps your solutions and the best scores."
+    )
+}
+
+/// Rebuilds the level select screen so that it shows the current scores.
+fn refresh_level_select(
+    commands: &mut Commands,
+    q_screen: &Query<Entity, With<LevelSelectScreen>>,
+) {
+    for entity in q_screen {
+        commands.entity(entity).despawn();
+    }
+
+    commands.run_system_cached(level_select_enter);
+}
+
 /// Returns a tuple containing the number of stars the player has
 /// earned and the total number of stars available to earn.
 fn num_stars(
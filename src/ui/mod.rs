@@ -1,20 +1,31 @@
 use bevy::prelude::*;
 use level_select::LevelSelectPlugin;
+use pareto_chart::ParetoChartPlugin;
 use radio_button::RadioButtonPlugin;
+use run_table::RunTablePlugin;
 use score_dialog::ScoreDialogPlugin;
 use editor::EditorPlugin;
 
 use crate::theme;
 
 pub mod level_select;
+pub mod pareto_chart;
 pub mod radio_button;
+pub mod run_table;
 pub mod score_dialog;
 pub mod editor;
 
 pub struct UiPlugin;
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins((RadioButtonPlugin, LevelSelectPlugin, ScoreDialogPlugin, EditorPlugin));
+        app.add_plugins((
+            RadioButtonPlugin,
+            LevelSelectPlugin,
+            ScoreDialogPlugin,
+            EditorPlugin,
+            RunTablePlugin,
+            ParetoChartPlugin,
+        ));
         app.add_systems(Update, button_system);
     }
 }
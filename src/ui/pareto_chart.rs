@@ -0,0 +1,199 @@
+//! A chart of a level's working runs by cost and time, highlighting the ones
+//! on the Pareto front, along with the best run on each axis.
+
+use bevy::prelude::*;
+
+use crate::{
+    run_history::{pareto_front, RunBests, RunHistory, RunRecord},
+    theme, Handles,
+};
+
+const PLOT_WIDTH: f32 = 360.;
+const PLOT_HEIGHT: f32 = 180.;
+const MIN_DOT_SIZE: f32 = 6.;
+const MAX_DOT_SIZE: f32 = 14.;
+
+pub struct ParetoChartPlugin;
+impl Plugin for ParetoChartPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, populate_pareto_chart_system);
+    }
+}
+
+/// Fills this node with a chart of the level's runs.
+#[derive(Component)]
+pub struct ParetoChart {
+    pub level: u32,
+}
+
+/// Where `value` falls between `min` and `max`, from 0 to 1.
+fn fraction(value: f32, min: f32, max: f32) -> f32 {
+    if max > min {
+        (value - min) / (max - min)
+    } else {
+        0.5
+    }
+}
+
+fn populate_pareto_chart_system(
+    mut commands: Commands,
+    history: Res<RunHistory>,
+    bests: Res<RunBests>,
+    handles: Res<Handles>,
+    q_charts: Query<(Entity, Ref<ParetoChart>)>,
+) {
+    for (entity, chart) in &q_charts {
+        if !chart.is_added() && !history.is_changed() && !bests.is_changed() {
+            continue;
+        }
+
+        let history_runs = history.0.get(&chart.level).map_or(&[][..], Vec::as_slice);
+        let level_bests = bests.0.get(&chart.level).cloned().unwrap_or_default();
+
+        // the best runs may have been forgotten from the history
+        let mut runs = history_runs
+            .iter()
+            .filter(|r| r.quotas_met)
+            .collect::<Vec<_>>();
+        for best in level_bests.iter() {
+            if !runs.iter().any(|r| r.run == best.run) {
+                runs.push(best);
+            }
+        }
+
+        let front = pareto_front(runs.iter().copied())
+            .into_iter()
+            .map(|r| r.run)
+            .collect::<Vec<_>>();
+
+        let text_font = TextFont {
+            font: handles.fonts[0].clone(),
+            font_size: 18.0,
+            ..default()
+        };
+
+        commands.entity(entity).despawn_children();
+
+        if runs.is_empty() {
+            commands.entity(entity).with_child((
+                Text::new("NO WORKING RUNS YET"),
+                text_font,
+                TextColor(theme::UI_LABEL_MUTED.into()),
+            ));
+            continue;
+        }
+
+        let range = |value: fn(&RunRecord) -> f32| {
+            runs.iter()
+                .map(|r| value(r))
+                .fold((f32::MAX, f32::MIN), |(min, max), v| {
+                    (min.min(v), max.max(v))
+                })
+        };
+        let (min_cost, max_cost) = range(|r| r.cost as f32);
+        let (min_elapsed, max_elapsed) = range(|r| r.elapsed);
+        let (min_delivered, max_delivered) = range(|r| r.delivered as f32);
+
+        let best_lines = [
+            ("CHEAPEST", &level_bests.cheapest),
+            ("FASTEST", &level_bests.fastest),
+            ("MOST DELIVERED", &level_bests.most_delivered),
+        ]
+        .into_iter()
+        .filter_map(|(label, best)| {
+            best.as_ref().map(|r| {
+                format!(
+                    "{label}\nRUN {}: §{} ŧ{:.1} ₽{} Æ{}",
+                    r.run, r.cost, r.elapsed, r.delivered, r.score
+                )
+            })
+        })
+        .collect::<Vec<_>>();
+
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn((
+                Text::new(format!(
+                    "§{min_cost}–{max_cost} → · ŧ{min_elapsed:.1}–{max_elapsed:.1} ↑ · \
+                     DOT SIZE ₽{min_delivered}–{max_delivered}"
+                )),
+                text_font.clone(),
+                TextColor(theme::UI_LABEL_MUTED.into()),
+            ));
+
+            parent
+                .spawn(Node {
+                    column_gap: Val::Px(20.),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent
+                        .spawn((
+                            Node {
+                                width: Val::Px(PLOT_WIDTH),
+                                height: Val::Px(PLOT_HEIGHT),
+                                flex_shrink: 0.,
+                                ..default()
+                            },
+                            BackgroundColor(theme::UI_NORMAL_BUTTON.into()),
+                        ))
+                        .with_children(|parent| {
+                            // dominated runs first, so that the front is drawn
+                            // over them
+                            let mut runs = runs.clone();
+                            runs.sort_by_key(|r| front.contains(&r.run));
+
+                            for run in runs {
+                                let on_front = front.contains(&run.run);
+                                let size = MIN_DOT_SIZE
+                                    + (MAX_DOT_SIZE - MIN_DOT_SIZE)
+                                        * fraction(
+                                            run.delivered as f32,
+                                            min_delivered,
+                                            max_delivered,
+                                        );
+                                let x = fraction(run.cost as f32, min_cost, max_cost);
+                                let y = fraction(run.elapsed, min_elapsed, max_elapsed);
+
+                                parent.spawn((
+                                    Node {
+                                        position_type: PositionType::Absolute,
+                                        left: Val::Px(x * (PLOT_WIDTH - size)),
+                                        bottom: Val::Px(y * (PLOT_HEIGHT - size)),
+                                        width: Val::Px(size),
+                                        height: Val::Px(size),
+                                        ..default()
+                                    },
+                                    BorderRadius::MAX,
+                                    BackgroundColor(if on_front {
+                                        theme::FINISHED_ROAD[1].into()
+                                    } else {
+                                        theme::UI_LABEL_MUTED.into()
+                                    }),
+                                ));
+                            }
+                        });
+
+                    parent
+                        .spawn(Node {
+                            flex_direction: FlexDirection::Column,
+                            row_gap: Val::Px(10.),
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            for line in best_lines {
+                                parent.spawn((
+                                    Text::new(line),
+                                    text_font.clone(),
+                                    TextColor(theme::UI_LABEL.into()),
+                                ));
+                            }
+                            parent.spawn((
+                                Text::new(format!("{} ON THE PARETO FRONT", front.len())),
+                                text_font.clone(),
+                                TextColor(theme::FINISHED_ROAD[1].into()),
+                            ));
+                        });
+                });
+        });
+    }
+}
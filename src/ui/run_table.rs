@@ -0,0 +1,226 @@
+//! A table of a level's completed runs that can be sorted by clicking a column
+//! header.
+
+use bevy::prelude::*;
+
+use crate::{
+    run_history::{format_timestamp, sorted_runs, RunColumn, RunHistory},
+    theme, Handles,
+};
+
+pub struct RunTablePlugin;
+impl Plugin for RunTablePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RunTableSort>();
+        app.add_systems(
+            Update,
+            (run_table_header_system, populate_run_table_system).chain(),
+        );
+    }
+}
+
+/// Fills this node with a table of the level's runs.
+#[derive(Component)]
+pub struct RunTable {
+    pub level: u32,
+    /// The most rows to show after sorting.
+    pub max_rows: usize,
+    /// Leaves out the date of each run, for narrow spaces.
+    pub compact: bool,
+}
+
+/// How every run table is sorted. This is shared so that a table keeps its
+/// sorting the next time it is shown.
+#[derive(Resource)]
+pub struct RunTableSort {
+    pub column: RunColumn,
+    pub descending: bool,
+}
+impl Default for RunTableSort {
+    fn default() -> Self {
+        // most recent first
+        Self {
+            column: RunColumn::Run,
+            descending: true,
+        }
+    }
+}
+
+#[derive(Component)]
+struct RunTableHeader(RunColumn);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Cell {
+    Run,
+    When,
+    Score,
+    Cost,
+    Elapsed,
+    Delivered,
+    Layout,
+}
+
+impl Cell {
+    const ALL: [Cell; 7] = [
+        Cell::Run,
+        Cell::When,
+        Cell::Score,
+        Cell::Cost,
+        Cell::Elapsed,
+        Cell::Delivered,
+        Cell::Layout,
+    ];
+
+    fn title(&self) -> &'static str {
+        match self {
+            Cell::Run => "RUN",
+            Cell::When => "WHEN",
+            Cell::Score => "Æ",
+            Cell::Cost => "§",
+            Cell::Elapsed => "ŧ",
+            Cell::Delivered => "₽",
+            Cell::Layout => "LAYOUT",
+        }
+    }
+
+    fn column(&self) -> Option<RunColumn> {
+        match self {
+            Cell::Run => Some(RunColumn::Run),
+            Cell::Score => Some(RunColumn::Score),
+            Cell::Cost => Some(RunColumn::Cost),
+            Cell::Elapsed => Some(RunColumn::Elapsed),
+            Cell::Delivered => Some(RunColumn::Delivered),
+            Cell::When | Cell::Layout => None,
+        }
+    }
+}
+
+fn run_table_header_system(
+    query: Query<(&Interaction, &RunTableHeader), Changed<Interaction>>,
+    mut sort: ResMut<RunTableSort>,
+) {
+    for (_, header) in query.iter().filter(|(i, _)| **i == Interaction::Pressed) {
+        if sort.column == header.0 {
+            sort.descending = !sort.descending;
+        } else {
+            sort.column = header.0;
+            sort.descending = true;
+        }
+    }
+}
+
+fn populate_run_table_system(
+    mut commands: Commands,
+    history: Res<RunHistory>,
+    sort: Res<RunTableSort>,
+    handles: Res<Handles>,
+    q_tables: Query<(Entity, Ref<RunTable>)>,
+) {
+    for (entity, table) in &q_tables {
+        if !table.is_added() && !history.is_changed() && !sort.is_changed() {
+            continue;
+        }
+
+        let runs = history.0.get(&table.level).map_or(&[][..], Vec::as_slice);
+        let latest = runs.last().map(|r| r.run);
+
+        let cells = Cell::ALL
+            .into_iter()
+            .filter(|c| !table.compact || *c != Cell::When)
+            .collect::<Vec<_>>();
+
+        let text_font = TextFont {
+            font: handles.fonts[0].clone(),
+            font_size: 18.0,
+            ..default()
+        };
+
+        commands.entity(entity).despawn_children();
+
+        if runs.is_empty() {
+            commands.entity(entity).with_child((
+                Text::new("NO RUNS YET"),
+                text_font,
+                TextColor(theme::UI_LABEL_MUTED.into()),
+            ));
+            continue;
+        }
+
+        commands.entity(entity).with_children(|parent| {
+            parent
+                .spawn(Node {
+                    display: Display::Grid,
+                    grid_template_columns: vec![GridTrack::auto(); cells.len()],
+                    column_gap: Val::Px(10.),
+                    row_gap: Val::Px(2.),
+                    align_items: AlignItems::Center,
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for cell in &cells {
+                        let Some(column) = cell.column() else {
+                            parent.spawn((
+                                Text::new(cell.title()),
+                                text_font.clone(),
+                                TextColor(theme::UI_LABEL_MUTED.into()),
+                            ));
+                            continue;
+                        };
+
+                        let arrow = match (sort.column == column, sort.descending) {
+                            (false, _) => "",
+                            (true, true) => " ↓",
+                            (true, false) => " ↑",
+                        };
+
+                        parent.spawn((
+                            Button,
+                            Node {
+                                padding: UiRect::axes(Val::Px(5.), Val::Px(2.)),
+                                ..default()
+                            },
+                            BackgroundColor(theme::UI_NORMAL_BUTTON.into()),
+                            RunTableHeader(column),
+                            Children::spawn(Spawn((
+                                Text::new(format!("{}{arrow}", cell.title())),
+                                text_font.clone(),
+                                TextColor(theme::UI_BUTTON_TEXT.into()),
+                            ))),
+                        ));
+                    }
+
+                    for record in sorted_runs(runs, sort.column, sort.descending)
+                        .into_iter()
+                        .take(table.max_rows)
+                    {
+                        // runs that didn't meet their quotas don't count
+                        let color = if !record.quotas_met {
+                            theme::UI_LABEL_MUTED
+                        } else if Some(record.run) == latest {
+                            theme::FINISHED_ROAD[1]
+                        } else {
+                            theme::UI_LABEL
+                        };
+
+                        for cell in &cells {
+                            let text = match cell {
+                                Cell::Run => format!("{}", record.run),
+                                Cell::When => format_timestamp(record.timestamp),
+                                Cell::Score => format!("{}", record.score),
+                                Cell::Cost => format!("{}", record.cost),
+                                Cell::Elapsed => format!("{:.1}", record.elapsed),
+                                Cell::Delivered => format!("{}", record.delivered),
+                                Cell::Layout => format!("{:06x}", record.solution_hash >> 40),
+                            };
+
+                            parent.spawn((
+                                Text::new(text),
+                                text_font.clone(),
+                                TextColor(color.into()),
+                            ));
+                        }
+                    }
+                });
+        });
+    }
+}
@@ -4,18 +4,30 @@ use bevy::prelude::*;
 use bevy_easings::{Ease, EaseFunction, *};
 
 use crate::{
-    level::Level, pixie::PixieEmitter, sim::SimulationState, theme, AfterUpdate, BackButton,
-    DrawingInteraction, GameState, Handles, PixieCount, PlayAreaNode, Score, ScoreUi,
-    SelectedLevel,
+    deliveries::QuotasMet,
+    hotspots::{hotspots, ExplosionLog},
+    level::Level,
+    pixie::PixieEmitter,
+    run_history::RunHistory,
+    sim::SimulationState,
+    theme,
+    ui::run_table::RunTable,
+    AfterUpdate, BackButton, DrawingInteraction, GameState, Handles, MainCamera, PixieCount,
+    PlayAreaNode, Score, ScoreUi, SelectedLevel, GRID_SIZE,
 };
 
+/// The maximum number of explosion hotspots listed in the score dialog.
+const MAX_LISTED_HOTSPOTS: usize = 4;
+/// The maximum number of past runs listed in the score dialog.
+const MAX_LISTED_RUNS: usize = 5;
+
 pub struct ScoreDialogPlugin;
 
 impl Plugin for ScoreDialogPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(
             Update,
-            dismiss_score_dialog_button_system
+            (dismiss_score_dialog_button_system, hotspot_button_system)
                 .after(DrawingInteraction)
                 .run_if(in_state(GameState::Playing)),
         );
@@ -28,6 +40,8 @@ impl Plugin for ScoreDialogPlugin {
 struct DismissScoreDialogButton;
 #[derive(Component)]
 struct ScoreDialog;
+#[derive(Component)]
+struct HotspotButton(Vec2);
 
 fn show_score_dialog_system(
     mut commands: Commands,
@@ -36,6 +50,9 @@ fn show_score_dialog_system(
     selected_level: Res<SelectedLevel>,
     levels: Res<Assets<Level>>,
     score: Res<Score>,
+    explosion_log: Res<ExplosionLog>,
+    quotas: Res<QuotasMet>,
+    history: Res<RunHistory>,
     mut q_node: Query<(Entity, &mut BackgroundColor), With<PlayAreaNode>>,
     q_dialog: Query<Entity, With<ScoreDialog>>,
 ) {
@@ -61,15 +78,35 @@ fn show_score_dialog_system(
 
     let Some(score) = score.0 else { return };
 
-    let num_stars = level
-        .star_thresholds
-        .iter()
-        .filter(|t| **t <= score)
-        .count();
+    // no stars are awarded for a run that didn't deliver enough pixies
+    let num_stars = if quotas.0 {
+        level
+            .star_thresholds
+            .iter()
+            .filter(|t| **t <= score)
+            .count()
+    } else {
+        0
+    };
+    let quota_height = if quotas.0 { 0.0 } else { 40.0 };
+
+    let hotspots = hotspots(&explosion_log.0);
+    let listed_hotspots = hotspots.len().min(MAX_LISTED_HOTSPOTS);
+    let hotspots_height = if listed_hotspots > 0 {
+        40.0 + listed_hotspots as f32 * 40.0
+    } else {
+        0.0
+    };
+
+    let listed_runs = history
+        .0
+        .get(&selected_level.0)
+        .map_or(0, |runs| runs.len().min(MAX_LISTED_RUNS));
+    let runs_height = 40.0 + (listed_runs + 1) as f32 * 25.0;
 
     let dialog_node = Node {
-        width: Val::Px(320.0),
-        height: Val::Px(300.0),
+        width: Val::Px(400.0),
+        height: Val::Px(300.0 + quota_height + hotspots_height + runs_height),
         margin: UiRect {
             top: Val::Px(-1000.0),
             ..default()
@@ -130,6 +167,99 @@ fn show_score_dialog_system(
                 TextColor(theme::FINISHED_ROAD[1].into()),
             ));
 
+            if !quotas.0 {
+                parent.spawn((
+                    Text::new("QUOTA NOT MET"),
+                    TextFont {
+                        font: handles.fonts[0].clone(),
+                        font_size: 30.0,
+                        ..default()
+                    },
+                    TextColor(theme::UI_LABEL_BAD.into()),
+                ));
+            }
+
+            // explosion hotspots, most explosive first
+            if listed_hotspots > 0 {
+                parent
+                    .spawn(Node {
+                        width: Val::Percent(100.),
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(5.),
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn((
+                            Text::new(format!("HOTSPOTS ({})", hotspots.len())),
+                            TextFont {
+                                font: handles.fonts[0].clone(),
+                                font_size: 25.0,
+                                ..default()
+                            },
+                            TextColor(theme::UI_LABEL_MUTED.into()),
+                        ));
+
+                        for hotspot in hotspots.iter().take(MAX_LISTED_HOTSPOTS) {
+                            let grid_pos = (hotspot.position / GRID_SIZE).as_ivec2();
+
+                            parent
+                                .spawn((
+                                    Button,
+                                    Node {
+                                        height: Val::Px(35.),
+                                        padding: UiRect::horizontal(Val::Px(10.)),
+                                        align_items: AlignItems::Center,
+                                        ..default()
+                                    },
+                                    BackgroundColor(theme::UI_NORMAL_BUTTON.into()),
+                                    HotspotButton(hotspot.position),
+                                ))
+                                .with_children(|parent| {
+                                    parent.spawn((
+                                        Text::new(format!(
+                                            "×{} AT {}, {}",
+                                            hotspot.count, grid_pos.x, grid_pos.y
+                                        )),
+                                        TextFont {
+                                            font: handles.fonts[0].clone(),
+                                            font_size: 20.0,
+                                            ..default()
+                                        },
+                                        TextColor(theme::EXPLOSION_HOTSPOT.into()),
+                                    ));
+                                });
+                        }
+                    });
+            }
+
+            // this level's runs, so the player can see how this one compares
+            parent
+                .spawn(Node {
+                    width: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(5.),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        Text::new("RUNS"),
+                        TextFont {
+                            font: handles.fonts[0].clone(),
+                            font_size: 25.0,
+                            ..default()
+                        },
+                        TextColor(theme::UI_LABEL_MUTED.into()),
+                    ));
+                    parent.spawn((
+                        Node::default(),
+                        RunTable {
+                            level: selected_level.0,
+                            max_rows: MAX_LISTED_RUNS,
+                            compact: true,
+                        },
+                    ));
+                });
+
             // bottom buttons
             parent
                 .spawn(Node {
@@ -230,3 +360,18 @@ fn dismiss_score_dialog_button_system(
         }
     }
 }
+
+fn hotspot_button_system(
+    q_interaction: Query<(&Interaction, &HotspotButton), Changed<Interaction>>,
+    mut q_camera: Query<&mut Transform, With<MainCamera>>,
+) {
+    for (_, hotspot) in q_interaction
+        .iter()
+        .filter(|(i, _)| **i == Interaction::Pressed)
+    {
+        for mut transform in q_camera.iter_mut() {
+            transform.translation.x = hotspot.0.x;
+            transform.translation.y = hotspot.0.y;
+        }
+    }
+}